@@ -1,6 +1,7 @@
 #[derive(serde::Deserialize, serde::Serialize, std::fmt::Debug)]
 pub struct CreateCRRequest {
     pub title: Option<String>,
+    pub reviewers: Option<String>,
 }
 
 pub async fn create_cr(
@@ -31,6 +32,7 @@ async fn create_cr_worker(
         title: cr_request.title.unwrap_or(default_title),
         cr_number: cr_number as usize,
         open: true,
+        reviewers: cr_request.reviewers,
     };
     fastn_core::commands::create_cr::add_cr_to_workspace(&config, &cr_meta).await?;
     Ok(cr_number as usize)
@@ -58,3 +60,164 @@ async fn create_cr_page_worker(req: fastn_core::http::Request) -> fastn_core::Re
 
     fastn_core::package::package_doc::read_ftd(&mut config, &main_document, "/", false).await
 }
+
+#[derive(serde::Deserialize, serde::Serialize, std::fmt::Debug)]
+pub struct CRReviewRequest {
+    /// `None` for a comment
+    pub status: Option<fastn_core::cr_review::ReviewStatus>,
+    pub message: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<i64>,
+    pub section: Option<String>,
+}
+
+/// `cr_number_from_url()` is the CR number in `/-/cr/<cr-number>/...`.
+fn cr_number_from_url(path: &str) -> fastn_core::Result<usize> {
+    match path
+        .strip_prefix("/-/cr/")
+        .and_then(|path| path.split('/').next())
+    {
+        Some(cr_number) => Ok(cr_number.parse::<usize>()?),
+        None => fastn_core::usage_error(format!("`{}` is not a CR url", path)),
+    }
+}
+
+/// `cr_review()` handles the reviews posted to `/-/cr/<cr-number>/review/` by the page of the CR.
+/// They have to be posted as json, from the same origin, see `commands::serve::cr_review()`.
+pub async fn cr_review(
+    req: &fastn_core::http::Request,
+    cr_review_req: CRReviewRequest,
+) -> fastn_core::Result<fastn_core::http::Response> {
+    match cr_review_worker(req, cr_review_req).await {
+        Ok(cr_number) => {
+            #[derive(serde::Serialize)]
+            struct CRReviewResponse {
+                url: String,
+            }
+            let url = format!("/-/cr/{}/", cr_number);
+            fastn_core::http::api_ok(CRReviewResponse { url })
+        }
+        Err(err) => fastn_core::http::api_error(err.to_string()),
+    }
+}
+
+async fn cr_review_worker(
+    req: &fastn_core::http::Request,
+    cr_review_req: CRReviewRequest,
+) -> fastn_core::Result<usize> {
+    let config = fastn_core::Config::read(None, false, Some(req)).await?;
+    let cr_number = cr_number_from_url(req.path())?;
+    let reviewer = match fastn_core::apis::sync2::author(req.cookies())? {
+        Some(reviewer) => reviewer,
+        None => return fastn_core::usage_error("Login to review the CR".to_string()),
+    };
+
+    // the identities which decide if the reviewer is a writer of the documents changed by the CR
+    let changed_files = fastn_core::cr_review::changed_files(&config, cr_number).await?;
+    let mut identities: Vec<fastn_core::user_group::UserIdentity> = vec![];
+    for file in changed_files.iter() {
+        let document = fastn_core::cr_review::document_path(file.as_str());
+        for identity in
+            fastn_core::user_group::access_identities(&config, req, document.as_str(), false)
+                .await?
+        {
+            if !identities.contains(&identity) {
+                identities.push(identity);
+            }
+        }
+    }
+    let identities = identities
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ");
+    let timestamp = fastn_core::timestamp_nanosecond() as i64;
+
+    let mut cr_review = fastn_core::cr_review::get_cr_review(&config, cr_number).await?;
+    match (cr_review_req.status, cr_review_req.file) {
+        (Some(status), _) => cr_review.reviews.push(fastn_core::cr_review::Review {
+            reviewer: reviewer.clone(),
+            status,
+            identities: Some(identities),
+            timestamp,
+            message: cr_review_req.message,
+        }),
+        (None, Some(file)) => {
+            fastn_core::cr_review::check_comment(
+                file.as_str(),
+                cr_review_req.section.as_deref(),
+                changed_files.as_slice(),
+            )?;
+            cr_review.comments.push(fastn_core::cr_review::Comment {
+                author: reviewer.clone(),
+                file,
+                line: cr_review_req.line,
+                section: cr_review_req.section,
+                timestamp,
+                message: match cr_review_req.message {
+                    Some(message) if !message.trim().is_empty() => message,
+                    _ => return fastn_core::usage_error("The comment is empty".to_string()),
+                },
+            })
+        }
+        (None, None) => {
+            return fastn_core::usage_error(
+                "Either the status of the review or the file to comment on is required".to_string(),
+            )
+        }
+    }
+
+    // the review is synced, so that it is in the history of the CR like its other files
+    let path = config.path_without_root(&config.cr_review_path(cr_number))?;
+    let content = fastn_core::cr_review::generate_cr_review_content(&cr_review).into_bytes();
+    let file = match config.get_remote_manifest(false).await?.get(&path) {
+        Some(file_edit) => fastn_core::apis::sync2::SyncRequestFile::Update {
+            path,
+            content,
+            version: file_edit.version,
+            src_cr: None,
+        },
+        None => fastn_core::apis::sync2::SyncRequestFile::Add {
+            path,
+            content,
+            src_cr: None,
+        },
+    };
+    let message = format!("Review CR#{}", cr_number);
+    fastn_core::apis::sync2::do_sync(
+        &config,
+        &[file],
+        Some(message.as_str()),
+        Some(reviewer.as_str()),
+    )
+    .await?;
+    Ok(cr_number)
+}
+
+pub async fn cr_review_page(
+    req: fastn_core::http::Request,
+) -> fastn_core::Result<fastn_core::http::Response> {
+    match cr_review_page_worker(req).await {
+        Ok(body) => Ok(fastn_core::http::ok(body)),
+        Err(err) => fastn_core::http::api_error(err.to_string()),
+    }
+}
+
+async fn cr_review_page_worker(req: fastn_core::http::Request) -> fastn_core::Result<Vec<u8>> {
+    let mut config = fastn_core::Config::read(None, false, Some(&req)).await?;
+    let cr_number = cr_number_from_url(req.path())?;
+    let cr_meta = fastn_core::cr::get_cr_meta(&config, cr_number).await?;
+    let cr_review = fastn_core::cr_review::get_cr_review(&config, cr_number).await?;
+    let pending = fastn_core::cr_review::pending_approvals(&config, cr_number).await?;
+    let cr_review_ftd =
+        fastn_core::package_info_cr_review(&config, &cr_meta, &cr_review, pending.as_slice())?;
+
+    let main_document = fastn_core::Document {
+        id: "cr-review.ftd".to_string(),
+        content: cr_review_ftd,
+        parent_path: config.root.as_str().to_string(),
+        package_name: config.package.name.clone(),
+    };
+
+    fastn_core::package::package_doc::read_ftd(&mut config, &main_document, "/", false).await
+}
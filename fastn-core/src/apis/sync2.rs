@@ -19,11 +19,32 @@ pub enum SyncRequestFile {
     },
 }
 
+impl SyncRequestFile {
+    pub(crate) fn path(&self) -> &str {
+        match self {
+            SyncRequestFile::Add { path, .. }
+            | SyncRequestFile::Update { path, .. }
+            | SyncRequestFile::Delete { path, .. } => path.as_str(),
+        }
+    }
+
+    pub(crate) fn src_cr(&self) -> Option<usize> {
+        match self {
+            SyncRequestFile::Add { src_cr, .. }
+            | SyncRequestFile::Update { src_cr, .. }
+            | SyncRequestFile::Delete { src_cr, .. } => *src_cr,
+        }
+    }
+}
+
 #[derive(serde::Deserialize, serde::Serialize, std::fmt::Debug)]
 pub struct SyncRequest {
     pub package_name: String,
     pub files: Vec<SyncRequestFile>,
     pub history: String,
+    /// Stored as the message of every file edit of this sync
+    #[serde(default)]
+    pub message: Option<String>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, std::fmt::Debug)]
@@ -121,9 +142,13 @@ pub async fn sync2(
     }
 }
 
+/// `do_sync()` applies `files` and records them in the history with `message` and `author`.
+/// The files are not checked, the files sent by a client are checked by `sync_worker()`.
 pub(crate) async fn do_sync(
     config: &fastn_core::Config,
     files: &[SyncRequestFile],
+    message: Option<&str>,
+    author: Option<&str>,
 ) -> fastn_core::Result<std::collections::HashMap<String, SyncResponseFile>> {
     let mut remote_history = config.get_history().await?;
     let remote_manifest =
@@ -133,8 +158,15 @@ pub(crate) async fn do_sync(
         fastn_core::history::FileEditTemp,
     > = Default::default();
     let mut synced_files = std::collections::HashMap::new();
+    let edit = |src_cr: &Option<usize>, operation: fastn_core::history::FileOperation| {
+        fastn_core::history::FileEditTemp {
+            message: message.map(ToString::to_string),
+            author: author.map(ToString::to_string),
+            src_cr: *src_cr,
+            operation,
+        }
+    };
     for file in files {
-        // TODO: get all data like message, author, src-cr from request
         match file {
             SyncRequestFile::Add {
                 path,
@@ -156,12 +188,7 @@ pub(crate) async fn do_sync(
                 fastn_core::utils::update(&config.root.join(path), content).await?;
                 to_be_in_history.insert(
                     path.to_string(),
-                    fastn_core::history::FileEditTemp {
-                        message: None,
-                        author: None,
-                        src_cr: *src_cr,
-                        operation: fastn_core::history::FileOperation::Added,
-                    },
+                    edit(src_cr, fastn_core::history::FileOperation::Added),
                 );
             }
             SyncRequestFile::Update {
@@ -173,54 +200,67 @@ pub(crate) async fn do_sync(
                 if let Some(file_edit) = remote_manifest.get(path) {
                     if file_edit.version.eq(version) {
                         fastn_core::utils::update(&config.root.join(path), content).await?;
-                        // TODO: get all data like message, author, src-cr from request
                         to_be_in_history.insert(
                             path.to_string(),
-                            fastn_core::history::FileEditTemp {
-                                message: None,
-                                author: None,
-                                src_cr: *src_cr,
-                                operation: fastn_core::history::FileOperation::Updated,
-                            },
+                            edit(src_cr, fastn_core::history::FileOperation::Updated),
                         );
                     } else {
                         // else: Both has modified the same file
-                        let ancestor_path = config.history_path(path, *version);
-                        let ancestor_content = if let Ok(ancestor_content) =
-                            tokio::fs::read_to_string(ancestor_path).await
-                        {
-                            ancestor_content
-                        } else {
-                            // It's a binary file like image etc, can't try merging
-                            synced_files.insert(
-                                path.to_string(),
-                                SyncResponseFile::Update {
-                                    path: path.to_string(),
-                                    status: SyncStatus::RegularConflict,
-                                    content: content.clone(),
-                                },
-                            );
-                            continue;
+                        let theirs_content =
+                            tokio::fs::read(config.history_path(path, file_edit.version)).await?;
+                        let (ancestor_content, ours_content, theirs_content) = match (
+                            tokio::fs::read_to_string(config.history_path(path, *version))
+                                .await
+                                .ok(),
+                            String::from_utf8(content.clone()).ok(),
+                            String::from_utf8(theirs_content.clone()).ok(),
+                        ) {
+                            (Some(ancestor), Some(ours), Some(theirs)) => (ancestor, ours, theirs),
+                            _ => {
+                                // It's a binary file like image etc, can't try merging, so keep
+                                // both: ours is added next to theirs
+                                let kept = fastn_core::ftd_merge::keep_both_path(path, |p| {
+                                    remote_manifest.contains_key(p)
+                                        || to_be_in_history.contains_key(p)
+                                        || config.root.join(p).exists()
+                                });
+                                fastn_core::utils::update(&config.root.join(&kept), content)
+                                    .await?;
+                                to_be_in_history.insert(
+                                    kept.to_string(),
+                                    edit(src_cr, fastn_core::history::FileOperation::Added),
+                                );
+                                synced_files.insert(
+                                    kept.to_string(),
+                                    SyncResponseFile::Add {
+                                        path: kept,
+                                        status: SyncStatus::NoConflict,
+                                        content: content.clone(),
+                                    },
+                                );
+                                synced_files.insert(
+                                    path.to_string(),
+                                    SyncResponseFile::Update {
+                                        path: path.to_string(),
+                                        status: SyncStatus::NoConflict,
+                                        content: theirs_content,
+                                    },
+                                );
+                                continue;
+                            }
                         };
-                        let theirs_path = config.history_path(path, file_edit.version);
-                        let theirs_content = tokio::fs::read_to_string(theirs_path).await?;
-                        let ours_content = String::from_utf8(content.clone())
-                            .map_err(|e| fastn_core::Error::APIResponseError(e.to_string()))?;
-                        match diffy::MergeOptions::new()
-                            .set_conflict_style(diffy::ConflictStyle::Merge)
-                            .merge(&ancestor_content, &ours_content, &theirs_content)
-                        {
+                        match fastn_core::ftd_merge::merge(
+                            path,
+                            &ancestor_content,
+                            &ours_content,
+                            &theirs_content,
+                        ) {
                             Ok(data) => {
                                 fastn_core::utils::update(&config.root.join(path), data.as_bytes())
                                     .await?;
                                 to_be_in_history.insert(
                                     path.to_string(),
-                                    fastn_core::history::FileEditTemp {
-                                        message: None,
-                                        author: None,
-                                        src_cr: *src_cr,
-                                        operation: fastn_core::history::FileOperation::Updated,
-                                    },
+                                    edit(src_cr, fastn_core::history::FileOperation::Updated),
                                 );
                                 synced_files.insert(
                                     path.to_string(),
@@ -290,12 +330,7 @@ pub(crate) async fn do_sync(
                     }
                     to_be_in_history.insert(
                         path.to_string(),
-                        fastn_core::history::FileEditTemp {
-                            message: None,
-                            author: None,
-                            src_cr: *src_cr,
-                            operation: fastn_core::history::FileOperation::Deleted,
-                        },
+                        edit(src_cr, fastn_core::history::FileOperation::Deleted),
                     );
                 }
             }
@@ -315,7 +350,37 @@ pub(crate) async fn sync_worker(
 
     // TODO: Need to call at once only
     let config = fastn_core::Config::read(None, false, Some(req)).await?;
-    let mut synced_files = do_sync(&config, request.files.as_slice()).await?;
+    // the review file of a CR is written by `/-/cr/<cr-number>/review/` only, which takes the
+    // reviewer from the session, see `fastn_core::cr_review`
+    if let Some(file) = request
+        .files
+        .iter()
+        .find(|file| fastn_core::cr_review::is_cr_review_path(file.path()))
+    {
+        return fastn_core::usage_error(format!(
+            "`{}` can not be synced, review the CR on its page instead",
+            file.path()
+        ));
+    }
+    // `fastn merge` syncs the files of a CR into main only once it is approved, the client can
+    // not be trusted with that so the approvals are checked here too
+    for cr_number in request
+        .files
+        .iter()
+        .filter(|file| !file.path().trim_start_matches('/').starts_with("-/"))
+        .filter_map(SyncRequestFile::src_cr)
+        .unique()
+    {
+        fastn_core::cr_review::check_approvals(&config, cr_number).await?;
+    }
+    let author = author(req.cookies())?;
+    let mut synced_files = do_sync(
+        &config,
+        request.files.as_slice(),
+        request.message.as_deref(),
+        author.as_deref(),
+    )
+    .await?;
     let remote_history = config.get_history().await?;
     let remote_manifest =
         fastn_core::history::FileHistory::get_remote_manifest(remote_history.as_slice(), true)?;
@@ -335,6 +400,18 @@ pub(crate) async fn sync_worker(
     })
 }
 
+/// `author()` is the user who is logged in, as `<provider>-<user-name>`. It comes from the
+/// session, the client can not send it.
+pub(crate) fn author(
+    cookies: &std::collections::HashMap<String, String>,
+) -> fastn_core::Result<Option<String>> {
+    Ok(fastn_core::auth::link::user(cookies)?
+        .identities
+        .into_iter()
+        .find(|identity| identity.logged_in)
+        .map(|identity| format!("{}-{}", identity.provider, identity.user_name)))
+}
+
 async fn clone_history_files(
     config: &fastn_core::Config,
     remote_manifest: &std::collections::BTreeMap<String, fastn_core::history::FileEdit>,
@@ -0,0 +1,357 @@
+// Password-less login by email.
+//
+// `/auth/login/?platform=email&email=<address>` mails a one-time login link to the address,
+// through `fastn_core::auth::mailer`. Without `email` a form asking for it is shown. The link
+// carries a signed token with a random nonce; the nonce is kept in the session store till the
+// link is used or expires, so a link logs in only once.
+//
+// The link points to the `canonical-url` of the package, no link is sent if it is not set. Only
+// `MAX_LINKS_PER_EMAIL` links are sent to an address, and `MAX_LINKS_PER_CLIENT` on the request
+// of a client, in `LINK_TTL`. Opening the link shows a page to confirm the login, which is a
+// POST, so mail scanners that open the links in a mail do not use it up.
+//
+// The logged-in user has the `email: <address>` identity, as used by `fastn.user-group`:
+//
+//     -- fastn.user-group readers:
+//     email: alice@example.com
+pub const CALLBACK_URL: &str = "/auth/email/callback/";
+
+/// How long a login link can be used.
+const LINK_TTL: std::time::Duration = std::time::Duration::from_secs(15 * 60);
+const MAX_LINKS_PER_EMAIL: i64 = 3;
+const MAX_LINKS_PER_CLIENT: i64 = 10;
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+pub struct UserDetail {
+    pub user_id: String,
+    pub user_name: String,
+    pub email: String,
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+struct LoginLink {
+    nonce: String,
+    email: String,
+    next: String,
+}
+
+pub(crate) struct Email;
+
+impl fastn_core::auth::AuthProvider for Email {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    fn callback_url(&self) -> Option<&str> {
+        Some(CALLBACK_URL)
+    }
+
+    fn login<'a>(
+        &'a self,
+        req: actix_web::HttpRequest,
+    ) -> futures::future::LocalBoxFuture<'a, fastn_core::Result<Option<fastn_core::http::Response>>>
+    {
+        Box::pin(async move { login(req).await.map(Some) })
+    }
+
+    fn callback<'a>(
+        &'a self,
+        req: actix_web::HttpRequest,
+    ) -> futures::future::LocalBoxFuture<'a, fastn_core::Result<fastn_core::http::Response>> {
+        Box::pin(callback(req))
+    }
+
+    fn user_detail(&self, user_detail: &str, field: &str) -> fastn_core::Result<Option<String>> {
+        let ud: UserDetail = serde_json::from_str(user_detail)?;
+        match field {
+            "email" | "username" | "user_name" | "user-name" | "id" | "uid" | "userid"
+            | "user-id" | "user_id" => Ok(Some(ud.email)),
+            _ => Err(fastn_core::Error::GenericError(format!(
+                "invalid field {} requested for platform email",
+                field
+            ))),
+        }
+    }
+
+    fn matched_identities<'a>(
+        &'a self,
+        user_detail: &'a str,
+        identities: &'a [fastn_core::user_group::UserIdentity],
+    ) -> futures::future::LocalBoxFuture<
+        'a,
+        fastn_core::Result<Vec<fastn_core::user_group::UserIdentity>>,
+    > {
+        Box::pin(async move {
+            let ud: UserDetail = serde_json::from_str(user_detail)?;
+            Ok(matched_identities(ud.email.as_str(), identities))
+        })
+    }
+}
+
+fn matched_identities(
+    email: &str,
+    identities: &[fastn_core::user_group::UserIdentity],
+) -> Vec<fastn_core::user_group::UserIdentity> {
+    identities
+        .iter()
+        .filter(|identity| identity.key.eq("email") && identity.value.eq_ignore_ascii_case(email))
+        .cloned()
+        .collect()
+}
+
+/// A valid enough address to send a mail to, the mail server does the rest of the checking.
+fn is_email(email: &str) -> bool {
+    match email.split_once('@') {
+        Some((user, domain)) => {
+            !user.is_empty()
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !email.contains(|c: char| c.is_whitespace() || c == '<' || c == '>')
+        }
+        None => false,
+    }
+}
+
+/// `rate_limited()` records a login link sent to `email` on the request of `client`, unless
+/// either of them has had too many links in the last `LINK_TTL`.
+fn rate_limited(email: &str, client: &str) -> fastn_core::Result<bool> {
+    let now = fastn_core::auth::session::now();
+    let conn = fastn_core::auth::session::store()?.lock();
+    conn.execute(
+        "DELETE FROM fastn_email_sent WHERE sent_at < ?1",
+        [now - LINK_TTL.as_secs() as i64],
+    )?;
+    let sent = |column: &str, value: &str| -> rusqlite::Result<i64> {
+        conn.query_row(
+            format!(
+                "SELECT COUNT(*) FROM fastn_email_sent WHERE {} = ?1",
+                column
+            )
+            .as_str(),
+            [value],
+            |row| row.get(0),
+        )
+    };
+    if sent("email", email)? >= MAX_LINKS_PER_EMAIL
+        || sent("client", client)? >= MAX_LINKS_PER_CLIENT
+    {
+        return Ok(true);
+    }
+    conn.execute(
+        "INSERT INTO fastn_email_sent (email, client, sent_at) VALUES (?1, ?2, ?3)",
+        rusqlite::params![email, client, now],
+    )?;
+    Ok(false)
+}
+
+// route: /auth/login/?platform=email
+pub async fn login(req: actix_web::HttpRequest) -> fastn_core::Result<fastn_core::http::Response> {
+    #[derive(serde::Deserialize)]
+    struct QueryParams {
+        email: Option<String>,
+        next: Option<String>,
+    }
+    let query = actix_web::web::Query::<QueryParams>::from_query(req.query_string())?.0;
+    let next = query
+        .next
+        .filter(|n| fastn_core::auth::utils::is_local(n))
+        .unwrap_or_else(|| "/".to_string());
+    let email = match query.email.map(|e| e.trim().to_lowercase()) {
+        Some(email) if is_email(email.as_str()) => email,
+        Some(_) => return Ok(actix_web::HttpResponse::BadRequest().body("invalid email address")),
+        None => {
+            return Ok(actix_web::HttpResponse::Ok()
+                .content_type(actix_web::http::header::ContentType::html())
+                .body(LOGIN_FORM))
+        }
+    };
+
+    // the Host header is sent by the client, so the link is made from the configured url
+    let fastn_req =
+        fastn_core::http::Request::from_actix(req.clone(), actix_web::web::Bytes::new());
+    let base_url = match fastn_core::config::cache::read(&fastn_req)
+        .await?
+        .package
+        .canonical_url
+        .clone()
+    {
+        Some(url) => url,
+        None => {
+            return Err(fastn_core::Error::UsageError {
+                message: "canonical-url of the package is not set in FASTN.ftd, it is needed to \
+                    send login links"
+                    .to_string(),
+            })
+        }
+    };
+
+    let client = req
+        .peer_addr()
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_default();
+    if rate_limited(email.as_str(), client.as_str())? {
+        return Ok(actix_web::HttpResponse::TooManyRequests()
+            .body("too many login links have been sent, please try again later"));
+    }
+
+    let nonce = fastn_core::auth::session::random_id();
+    fastn_core::auth::session::store()?.lock().execute(
+        "INSERT INTO fastn_email_login (nonce, expires_at) VALUES (?1, ?2)",
+        rusqlite::params![
+            nonce,
+            fastn_core::auth::session::now() + LINK_TTL.as_secs() as i64
+        ],
+    )?;
+    let token = fastn_core::auth::session::sign(
+        fastn_core::auth::session::Purpose::EmailLink,
+        serde_json::to_string(&LoginLink {
+            nonce,
+            email: email.clone(),
+            next,
+        })?
+        .as_str(),
+        LINK_TTL,
+    );
+    let link = format!(
+        "{}{}?token={}",
+        base_url.trim_end_matches('/'),
+        CALLBACK_URL,
+        token
+    );
+
+    fastn_core::auth::mailer::send(
+        email.as_str(),
+        "Your login link",
+        format!(
+            "Open this link to log in to {}:\n\n{}\n\nThe link can be used once, in the next {} \
+            minutes. If you did not ask to log in, ignore this email.",
+            base_url.trim_end_matches('/'),
+            link,
+            LINK_TTL.as_secs() / 60
+        )
+        .as_str(),
+    )
+    .await?;
+
+    Ok(actix_web::HttpResponse::Ok().body(format!(
+        "A login link has been sent to {}, please check your email.",
+        email
+    )))
+}
+
+// route: /auth/email/callback/
+//
+// GET shows the page to confirm the login, its form POSTs to the same url to log in.
+pub async fn callback(req: actix_web::HttpRequest) -> fastn_core::Result<actix_web::HttpResponse> {
+    #[derive(serde::Deserialize)]
+    struct QueryParams {
+        token: String,
+    }
+    let query = actix_web::web::Query::<QueryParams>::from_query(req.query_string())?.0;
+    let link: LoginLink = match fastn_core::auth::session::verify(
+        fastn_core::auth::session::Purpose::EmailLink,
+        query.token.as_str(),
+    ) {
+        Some(link) => serde_json::from_str(link.as_str())?,
+        None => {
+            return Ok(fastn_core::unauthorised!(
+                "login link is invalid or expired"
+            ))
+        }
+    };
+
+    if req.method() != actix_web::http::Method::POST {
+        return Ok(actix_web::HttpResponse::Ok()
+            .content_type(actix_web::http::header::ContentType::html())
+            .body(
+                CONFIRM_FORM
+                    .replace(
+                        "{email}",
+                        fastn_core::seo::escape(link.email.as_str()).as_str(),
+                    )
+                    .replace(
+                        "{action}",
+                        fastn_core::seo::escape(
+                            format!("{}?token={}", CALLBACK_URL, query.token).as_str(),
+                        )
+                        .as_str(),
+                    ),
+            ));
+    }
+
+    {
+        let conn = fastn_core::auth::session::store()?.lock();
+        conn.execute(
+            "DELETE FROM fastn_email_login WHERE expires_at < ?1",
+            [fastn_core::auth::session::now()],
+        )?;
+        if conn.execute(
+            "DELETE FROM fastn_email_login WHERE nonce = ?1",
+            [link.nonce.as_str()],
+        )? == 0
+        {
+            return Ok(fastn_core::unauthorised!(
+                "login link has already been used"
+            ));
+        }
+    }
+
+    let user_detail_str = serde_json::to_string(&UserDetail {
+        user_id: link.email.clone(),
+        user_name: link.email.clone(),
+        email: link.email,
+    })?;
+    Ok(actix_web::HttpResponse::Found()
+        .cookie(
+            actix_web::cookie::Cookie::build(
+                fastn_core::auth::AuthProvider::cookie_name(&Email),
+                fastn_core::auth::session::create(
+                    fastn_core::auth::AuthProvider::name(&Email),
+                    user_detail_str.as_str(),
+                )?,
+            )
+            .domain(fastn_core::auth::utils::domain(
+                req.connection_info().host(),
+            ))
+            .path("/")
+            .http_only(true)
+            .max_age(fastn_core::auth::session::cookie_max_age())
+            .finish(),
+        )
+        .append_header((actix_web::http::header::LOCATION, link.next))
+        .finish())
+}
+
+const LOGIN_FORM: &str = r#"<html>
+    <head><title>Login</title></head>
+    <body>
+        <form method="get" action="/auth/login/">
+            <input type="hidden" name="platform" value="email">
+            <label>Email <input type="email" name="email" required></label>
+            <button type="submit">Send login link</button>
+        </form>
+    </body>
+</html>"#;
+
+const CONFIRM_FORM: &str = r#"<html>
+    <head><title>Login</title></head>
+    <body>
+        <form method="post" action="{action}">
+            <p>Log in as {email}?</p>
+            <button type="submit">Log in</button>
+        </form>
+    </body>
+</html>"#;
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn is_email() {
+        assert!(super::is_email("alice@example.com"));
+        assert!(!super::is_email("alice"));
+        assert!(!super::is_email("@example.com"));
+        assert!(!super::is_email("alice@localhost"));
+        assert!(!super::is_email("alice@example.com>, bob@example.com"));
+    }
+}
@@ -4,28 +4,68 @@ pub const CALLBACK_URL: &str = "/auth/github/callback/";
 pub struct UserDetail {
     pub token: String,
     pub user_name: String,
+    pub user_id: String,
 }
+
+pub(crate) struct GitHub;
+
+impl fastn_core::auth::AuthProvider for GitHub {
+    fn name(&self) -> &str {
+        "github"
+    }
+
+    fn callback_url(&self) -> Option<&str> {
+        Some(CALLBACK_URL)
+    }
+
+    fn login<'a>(
+        &'a self,
+        req: actix_web::HttpRequest,
+    ) -> futures::future::LocalBoxFuture<'a, fastn_core::Result<Option<fastn_core::http::Response>>>
+    {
+        Box::pin(async move { login(req).await.map(Some) })
+    }
+
+    fn callback<'a>(
+        &'a self,
+        req: actix_web::HttpRequest,
+    ) -> futures::future::LocalBoxFuture<'a, fastn_core::Result<fastn_core::http::Response>> {
+        Box::pin(callback(req))
+    }
+
+    fn matched_identities<'a>(
+        &'a self,
+        user_detail: &'a str,
+        identities: &'a [fastn_core::user_group::UserIdentity],
+    ) -> futures::future::LocalBoxFuture<
+        'a,
+        fastn_core::Result<Vec<fastn_core::user_group::UserIdentity>>,
+    > {
+        Box::pin(async move {
+            let ud: UserDetail = serde_json::from_str(user_detail)?;
+            matched_identities(ud, identities).await
+        })
+    }
+}
+
 // route: /auth/login/
 pub async fn login(req: actix_web::HttpRequest) -> fastn_core::Result<fastn_core::http::Response> {
     // GitHub will be redirect to this url after login process completed
 
-    let mut next_url = "/".to_string();
-    if let Ok(queries) =
-        actix_web::web::Query::<std::collections::HashMap<String, String>>::from_query(
-            req.query_string(),
-        )
-    {
-        if queries.get("next").is_some() {
-            next_url = queries.get("next").unwrap().to_string();
-        }
-    }
+    let next_url = actix_web::web::Query::<std::collections::HashMap<String, String>>::from_query(
+        req.query_string(),
+    )
+    .ok()
+    .and_then(|q| q.get("next").cloned())
+    .filter(|n| fastn_core::auth::utils::is_local(n))
+    .unwrap_or_else(|| "/".to_string());
 
     let redirect_url: String = format!(
         "{}://{}{}?next={}",
         req.connection_info().scheme(),
         req.connection_info().host(),
         CALLBACK_URL,
-        next_url,
+        url::form_urlencoded::byte_serialize(next_url.as_bytes()).collect::<String>(),
     );
 
     // Set up the config for the Github OAuth2 process.
@@ -64,6 +104,12 @@ pub async fn callback(req: actix_web::HttpRequest) -> fastn_core::Result<actix_w
         pub next: String,
     }
     let query = actix_web::web::Query::<QueryParams>::from_query(req.query_string())?.0;
+    // `next` comes from the client, only redirect to the urls of this site
+    let next = if fastn_core::auth::utils::is_local(query.next.as_str()) {
+        query.next
+    } else {
+        "/".to_string()
+    };
     let auth_url = format!(
         "{}://{}{}",
         req.connection_info().scheme(),
@@ -78,29 +124,34 @@ pub async fn callback(req: actix_web::HttpRequest) -> fastn_core::Result<actix_w
     {
         Ok(access_token) => {
             let token = oauth2::TokenResponse::access_token(&access_token).secret();
-            let user_name = apis::user_details(token).await?;
+            let (user_name, user_id) = apis::user_details(token).await?;
             let user_detail_obj: UserDetail = UserDetail {
                 token: token.to_owned(),
                 user_name,
+                user_id,
             };
             let user_detail_str = serde_json::to_string(&user_detail_obj)?;
             return Ok(actix_web::HttpResponse::Found()
                 .cookie(
                     actix_web::cookie::Cookie::build(
-                        fastn_core::auth::AuthProviders::GitHub.as_str(),
-                        fastn_core::auth::utils::encrypt_str(&user_detail_str).await,
+                        fastn_core::auth::AuthProvider::cookie_name(&GitHub),
+                        fastn_core::auth::session::create(
+                            fastn_core::auth::AuthProvider::name(&GitHub),
+                            user_detail_str.as_str(),
+                        )?,
                     )
                     .domain(fastn_core::auth::utils::domain(
                         req.connection_info().host(),
                     ))
                     .path("/")
-                    .permanent()
+                    .http_only(true)
+                    .max_age(fastn_core::auth::session::cookie_max_age())
                     // TODO: AbrarK is running on http,
                     // will remove it later
                     // .secure(true)
                     .finish(),
                 )
-                .append_header((actix_web::http::header::LOCATION, query.next))
+                .append_header((actix_web::http::header::LOCATION, next))
                 .finish());
         }
         Err(err) => Ok(actix_web::HttpResponse::InternalServerError().body(err.to_string())),
@@ -549,12 +600,13 @@ pub mod apis {
         }
     }
     // TODO: It can be stored in the request cookies
-    pub async fn user_details(token: &str) -> fastn_core::Result<String> {
+    pub async fn user_details(token: &str) -> fastn_core::Result<(String, String)> {
         // API Docs: https://docs.github.com/en/rest/users/users#get-the-authenticated-user
         // TODO: Handle paginated response
         #[derive(Debug, serde::Deserialize)]
         struct UserDetails {
             login: String,
+            id: u64,
         }
         let user_obj: UserDetails = fastn_core::auth::utils::get_api(
             "https://api.github.com/user",
@@ -562,7 +614,7 @@ pub mod apis {
         )
         .await?;
 
-        Ok(String::from(&user_obj.login))
+        Ok((user_obj.login, user_obj.id.to_string()))
     }
 
     pub async fn graphql_sponsor_api(
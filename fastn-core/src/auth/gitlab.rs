@@ -1,23 +1,8 @@
-#[derive(Debug, serde::Deserialize, serde::Serialize)]
-pub struct UserDetail {
-    pub token: String,
-    pub user_name: String,
-    pub user_id: String,
-}
-pub async fn matched_identities(
-    _ud: UserDetail,
-    _identities: &[fastn_core::user_group::UserIdentity],
-) -> fastn_core::Result<Vec<fastn_core::user_group::UserIdentity>> {
-    /*let gitlab_identities = identities
-        .iter()
-        .filter(|identity| identity.key.starts_with("gitlab"))
-        .collect::<Vec<&fastn_core::user_group::UserIdentity>>();
-
-    if gitlab_identities.is_empty() {
-        return Ok(vec![]);
-    }*/
-
-    let matched_identities = vec![];
+// TODO: GitLab login is not implemented yet, see `fastn_core::auth::AuthProvider`.
+pub(crate) struct GitLab;
 
-    Ok(matched_identities)
+impl fastn_core::auth::AuthProvider for GitLab {
+    fn name(&self) -> &str {
+        "gitlab"
+    }
 }
@@ -0,0 +1,199 @@
+// Linked accounts.
+//
+// A user who logs in with more than one provider can link those logins into one fastn user.
+// `user-group` membership is then checked against the identities of every linked provider,
+// not only of the providers the user is currently logged in with. The links live in the
+// session store, with the latest user detail of each provider:
+//
+//     fastn_user_link: (provider, subject) -> (user_name, user_id, user_detail)
+//
+// `subject` is the provider's id of the user, see `fastn_core::auth::session::subject()`.
+//
+// Links are created with `/-/auth/link/` while logged in with all the providers to link, so
+// the user has shown they own each of them.
+
+#[derive(Debug, Default, serde::Serialize)]
+pub(crate) struct User {
+    /// The fastn user the logins are linked to, `None` if they are not linked.
+    #[serde(rename = "user-id")]
+    pub user_id: Option<String>,
+    pub identities: Vec<Identity>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub(crate) struct Identity {
+    pub provider: String,
+    #[serde(skip)]
+    pub subject: String,
+    #[serde(rename = "user-name")]
+    pub user_name: String,
+    /// `false` for a linked provider the user is not logged in with right now.
+    #[serde(rename = "logged-in")]
+    pub logged_in: bool,
+    #[serde(skip)]
+    pub user_detail: String,
+}
+
+/// `user()` returns the identities of the user who owns the sessions in `cookies`: the
+/// providers they are logged in with, and the providers linked to those. Nothing is linked
+/// unless the user is logged in with at least one provider.
+pub(crate) fn user(
+    cookies: &std::collections::HashMap<String, String>,
+) -> fastn_core::Result<User> {
+    let mut user = User::default();
+    for (session, user_detail) in fastn_core::auth::session::from_cookies(cookies)? {
+        user.identities.push(Identity {
+            provider: session.provider,
+            subject: session.subject,
+            user_name: session.user_name,
+            logged_in: true,
+            user_detail,
+        });
+    }
+    if user.identities.is_empty() {
+        return Ok(user);
+    }
+
+    let conn = fastn_core::auth::session::store()?.lock();
+    let user_ids = linked_user_ids(&conn, user.identities.as_slice())?;
+    let mut stmt = conn.prepare(
+        "SELECT provider, subject, user_name, user_detail FROM fastn_user_link WHERE user_id = ?1",
+    )?;
+    for user_id in user_ids.iter() {
+        let rows = stmt.query_map([user_id], |row| {
+            Ok(Identity {
+                provider: row.get(0)?,
+                subject: row.get(1)?,
+                user_name: row.get(2)?,
+                logged_in: false,
+                user_detail: row.get(3)?,
+            })
+        })?;
+        for identity in rows {
+            let identity = identity?;
+            if !user
+                .identities
+                .iter()
+                .any(|i| i.provider == identity.provider && i.subject == identity.subject)
+            {
+                user.identities.push(identity);
+            }
+        }
+    }
+    user.user_id = user_ids.into_iter().next();
+    Ok(user)
+}
+
+/// The fastn users any of `identities` is linked to.
+fn linked_user_ids(
+    conn: &rusqlite::Connection,
+    identities: &[Identity],
+) -> fastn_core::Result<Vec<String>> {
+    let mut stmt =
+        conn.prepare("SELECT user_id FROM fastn_user_link WHERE provider = ?1 AND subject = ?2")?;
+    let mut user_ids: Vec<String> = vec![];
+    for identity in identities {
+        let rows = stmt.query_map(
+            rusqlite::params![identity.provider, identity.subject],
+            |row| row.get(0),
+        )?;
+        for user_id in rows {
+            let user_id = user_id?;
+            if !user_ids.contains(&user_id) {
+                user_ids.push(user_id);
+            }
+        }
+    }
+    Ok(user_ids)
+}
+
+/// `link()` links every provider the user is logged in with into one fastn user. If some of
+/// them are already linked to different fastn users, those users are merged into one.
+fn link(cookies: &std::collections::HashMap<String, String>) -> fastn_core::Result<()> {
+    let logged_in = user(cookies)?
+        .identities
+        .into_iter()
+        .filter(|i| i.logged_in)
+        .collect::<Vec<Identity>>();
+
+    let conn = fastn_core::auth::session::store()?.lock();
+    let user_ids = linked_user_ids(&conn, logged_in.as_slice())?;
+    let user_id = user_ids
+        .first()
+        .cloned()
+        .unwrap_or_else(fastn_core::auth::session::random_id);
+    for other in user_ids.iter().skip(1) {
+        conn.execute(
+            "UPDATE fastn_user_link SET user_id = ?1 WHERE user_id = ?2",
+            [user_id.as_str(), other.as_str()],
+        )?;
+    }
+    for identity in logged_in {
+        conn.execute(
+            "INSERT OR REPLACE INTO fastn_user_link
+             (provider, subject, user_name, user_id, user_detail)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![
+                identity.provider,
+                identity.subject,
+                identity.user_name,
+                user_id,
+                identity.user_detail
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// `unlink()` removes `provider` from the fastn user the request cookies are linked to.
+fn unlink(
+    cookies: &std::collections::HashMap<String, String>,
+    provider: &str,
+) -> fastn_core::Result<bool> {
+    let user_id = match user(cookies)?.user_id {
+        Some(user_id) => user_id,
+        None => return Ok(false),
+    };
+    let deleted = fastn_core::auth::session::store()?.lock().execute(
+        "DELETE FROM fastn_user_link WHERE user_id = ?1 AND provider = ?2",
+        [user_id.as_str(), provider],
+    )?;
+    Ok(deleted > 0)
+}
+
+// route: /-/auth/link/
+//
+// GET returns the linked identities of the logged-in user. POST with no body links all the providers
+// the user is logged in with, `{"unlink": "<provider>"}` removes a provider from the links.
+pub(crate) async fn handle(
+    req: fastn_core::http::Request,
+) -> fastn_core::Result<fastn_core::http::Response> {
+    if user(req.cookies())?.identities.is_empty() {
+        return Ok(fastn_core::unauthorised!("not logged in"));
+    }
+
+    match req.method().to_lowercase().as_str() {
+        "get" => fastn_core::http::api_ok(user(req.cookies())?),
+        "post" => {
+            #[derive(serde::Deserialize)]
+            struct Link {
+                unlink: Option<String>,
+            }
+            let body: Link = if req.body().is_empty() {
+                Link { unlink: None }
+            } else {
+                req.json()?
+            };
+            match body.unlink {
+                Some(provider) => {
+                    if !unlink(req.cookies(), provider.as_str())? {
+                        return fastn_core::http::api_error("provider not linked");
+                    }
+                }
+                None => link(req.cookies())?,
+            }
+            fastn_core::http::api_ok(user(req.cookies())?)
+        }
+        _ => Ok(actix_web::HttpResponse::MethodNotAllowed().finish()),
+    }
+}
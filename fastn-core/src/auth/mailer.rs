@@ -0,0 +1,141 @@
+// Mailers send the emails of fastn, like the login links of the `email` auth provider.
+//
+// The mailer is picked with the `FASTN_MAILER` environment variable:
+//
+// - `smtp`: sends through the SMTP relay `FASTN_SMTP_HOST`, logging in with
+//   `FASTN_SMTP_USERNAME` and `FASTN_SMTP_PASSWORD`, as `FASTN_SMTP_SENDER`.
+// - `file` (default): writes every email to a file in `FASTN_MAIL_DIR`, `.fastn/mail` of the
+//   package by default, so login links can be tried out locally without a mail server.
+//
+// Something embedding fastn can plug in its own `Mailer` with `set_mailer()`.
+
+pub(crate) trait Mailer: Send + Sync {
+    fn send<'a>(
+        &'a self,
+        to: &'a str,
+        subject: &'a str,
+        body: &'a str,
+    ) -> futures::future::BoxFuture<'a, fastn_core::Result<()>>;
+}
+
+static MAILER: once_cell::sync::OnceCell<Box<dyn Mailer>> = once_cell::sync::OnceCell::new();
+
+/// `set_mailer()` replaces the mailer configured with `FASTN_MAILER`. It has to be called
+/// before the first email is sent, and returns `false` if a mailer is already in use.
+#[allow(dead_code)]
+pub(crate) fn set_mailer(mailer: Box<dyn Mailer>) -> bool {
+    MAILER.set(mailer).is_ok()
+}
+
+fn env(name: &str) -> fastn_core::Result<String> {
+    std::env::var(name).map_err(|_| fastn_core::Error::UsageError {
+        message: format!("{} is not set, it is needed to send emails", name),
+    })
+}
+
+async fn mailer() -> fastn_core::Result<&'static dyn Mailer> {
+    if let Some(mailer) = MAILER.get() {
+        return Ok(mailer.as_ref());
+    }
+    let mailer: Box<dyn Mailer> = match std::env::var("FASTN_MAILER").as_deref() {
+        Ok("smtp") => Box::new(Smtp::from_env()?),
+        Ok("file") | Err(_) => Box::new(File {
+            dir: match std::env::var("FASTN_MAIL_DIR") {
+                Ok(dir) => camino::Utf8PathBuf::from(dir),
+                Err(_) => fastn_core::Config::root().await?.join(".fastn/mail"),
+            },
+        }),
+        Ok(other) => {
+            return Err(fastn_core::Error::UsageError {
+                message: format!("unknown FASTN_MAILER: {}, expected smtp or file", other),
+            })
+        }
+    };
+    // if another request configured the mailer first, that one is used
+    let _ = MAILER.set(mailer);
+    Ok(MAILER.get().expect("mailer is set above").as_ref())
+}
+
+/// `send()` sends an email with the configured mailer.
+pub(crate) async fn send(to: &str, subject: &str, body: &str) -> fastn_core::Result<()> {
+    mailer().await?.send(to, subject, body).await
+}
+
+pub(crate) struct Smtp {
+    transport: lettre::AsyncSmtpTransport<lettre::Tokio1Executor>,
+    sender: lettre::message::Mailbox,
+}
+
+impl Smtp {
+    fn from_env() -> fastn_core::Result<Smtp> {
+        let transport =
+            lettre::AsyncSmtpTransport::<lettre::Tokio1Executor>::relay(&env("FASTN_SMTP_HOST")?)
+                .map_err(|e| fastn_core::Error::generic(e.to_string()))?
+                .credentials(lettre::transport::smtp::authentication::Credentials::new(
+                    env("FASTN_SMTP_USERNAME")?,
+                    env("FASTN_SMTP_PASSWORD")?,
+                ))
+                .build();
+        let sender =
+            env("FASTN_SMTP_SENDER")?
+                .parse()
+                .map_err(|e| fastn_core::Error::UsageError {
+                    message: format!("invalid FASTN_SMTP_SENDER: {}", e),
+                })?;
+        Ok(Smtp { transport, sender })
+    }
+}
+
+impl Mailer for Smtp {
+    fn send<'a>(
+        &'a self,
+        to: &'a str,
+        subject: &'a str,
+        body: &'a str,
+    ) -> futures::future::BoxFuture<'a, fastn_core::Result<()>> {
+        Box::pin(async move {
+            let message = lettre::Message::builder()
+                .from(self.sender.clone())
+                .to(to
+                    .parse()
+                    .map_err(|e| fastn_core::Error::generic(e.to_string()))?)
+                .subject(subject)
+                .body(body.to_string())
+                .map_err(|e| fastn_core::Error::generic(e.to_string()))?;
+            lettre::AsyncTransport::send(&self.transport, message)
+                .await
+                .map_err(|e| fastn_core::Error::generic(e.to_string()))?;
+            Ok(())
+        })
+    }
+}
+
+/// Writes every email to its own file in `dir`, for local testing.
+pub(crate) struct File {
+    pub dir: camino::Utf8PathBuf,
+}
+
+impl Mailer for File {
+    fn send<'a>(
+        &'a self,
+        to: &'a str,
+        subject: &'a str,
+        body: &'a str,
+    ) -> futures::future::BoxFuture<'a, fastn_core::Result<()>> {
+        Box::pin(async move {
+            tokio::fs::create_dir_all(&self.dir).await?;
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_millis())
+                .unwrap_or_default();
+            let path = self.dir.join(format!("{}-{}.eml", now, slug::slugify(to)));
+            tokio::fs::write(
+                &path,
+                format!("To: {}\nSubject: {}\n\n{}\n", to, subject, body),
+            )
+            .await?;
+            fastn_core::warning!("email to {} written to {}", to, path);
+            Ok(())
+        })
+    }
+}
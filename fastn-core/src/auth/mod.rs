@@ -7,18 +7,23 @@ pub(crate) mod digitalocean;
 pub(crate) mod discord;
 pub(crate) mod doorkeeper;
 pub(crate) mod dropbox;
+pub(crate) mod email;
 pub(crate) mod facebook;
 pub(crate) mod github;
 pub(crate) mod gitlab;
 pub(crate) mod gmail;
 pub(crate) mod google;
 pub(crate) mod instagram;
+pub(crate) mod link;
 pub(crate) mod linkedin;
+pub(crate) mod mailer;
 pub(crate) mod microsoft;
+pub(crate) mod oidc;
 pub(crate) mod okta;
 pub(crate) mod pintrest;
 pub(crate) mod processor;
 pub(crate) mod routes;
+pub(crate) mod session;
 pub(crate) mod slack;
 pub(crate) mod telegram;
 pub(crate) mod tiktok;
@@ -29,190 +34,169 @@ pub(crate) mod yahoo;
 pub(crate) mod zoho;
 
 pub mod utils;
-#[derive(Debug)]
-pub(crate) enum AuthProviders {
-    GitHub,
-    TeleGram,
-    Google,
-    Discord,
-    Slack,
-    Amazon,
-    Apple,
-    Baidu,
-    BitBucket,
-    DigitalOcean,
-    DoorKeeper,
-    DropBox,
-    Facebook,
-    GitLab,
-    Instagram,
-    LinkedIn,
-    Microsoft,
-    Okta,
-    Pintrest,
-    TikTok,
-    Twitch,
-    Twitter,
-    WeChat,
-    Yahoo,
-    Zoho,
-    Gmail,
-}
 
-impl AuthProviders {
-    pub(crate) const AUTH_ITER: [AuthProviders; 26] = [
-        AuthProviders::GitHub,
-        AuthProviders::TeleGram,
-        AuthProviders::Google,
-        AuthProviders::Discord,
-        AuthProviders::Slack,
-        AuthProviders::Amazon,
-        AuthProviders::Apple,
-        AuthProviders::Baidu,
-        AuthProviders::BitBucket,
-        AuthProviders::DigitalOcean,
-        AuthProviders::DoorKeeper,
-        AuthProviders::DropBox,
-        AuthProviders::Facebook,
-        AuthProviders::GitLab,
-        AuthProviders::Instagram,
-        AuthProviders::LinkedIn,
-        AuthProviders::Microsoft,
-        AuthProviders::Okta,
-        AuthProviders::Pintrest,
-        AuthProviders::TikTok,
-        AuthProviders::Twitch,
-        AuthProviders::Twitter,
-        AuthProviders::WeChat,
-        AuthProviders::Yahoo,
-        AuthProviders::Zoho,
-        AuthProviders::Gmail,
-    ];
-    pub(crate) fn as_str(&self) -> &'static str {
-        match self {
-            AuthProviders::GitHub => "github",
-            AuthProviders::TeleGram => "telegram",
-            AuthProviders::Google => "google",
-            AuthProviders::Discord => "discord",
-            AuthProviders::Slack => "slack",
-            AuthProviders::Amazon => "amazon",
-            AuthProviders::Apple => "apple",
-            AuthProviders::Baidu => "baidu",
-            AuthProviders::BitBucket => "bitbucket",
-            AuthProviders::DigitalOcean => "digitalocean",
-            AuthProviders::DoorKeeper => "doorkeeper",
-            AuthProviders::DropBox => "dropbox",
-            AuthProviders::Facebook => "facebook",
-            AuthProviders::GitLab => "gitlab",
-            AuthProviders::Instagram => "instagram",
-            AuthProviders::LinkedIn => "linkedin",
-            AuthProviders::Microsoft => "microsoft",
-            AuthProviders::Okta => "okta",
-            AuthProviders::Pintrest => "pintrest",
-            AuthProviders::TikTok => "tiktok",
-            AuthProviders::Twitch => "twitch",
-            AuthProviders::Twitter => "twitter",
-            AuthProviders::WeChat => "wechat",
-            AuthProviders::Yahoo => "yahoo",
-            AuthProviders::Zoho => "zoho",
-            AuthProviders::Gmail => "gmail",
-        }
+/// `AuthProvider` is implemented by every login platform fastn supports. `fastn_core::auth`
+/// only talks to the providers through this trait, so adding a provider means implementing
+/// it in its own module and listing it in `PROVIDERS`, or, for OpenID Connect providers,
+/// declaring it with `fastn.auth-provider` in FASTN.ftd (see `oidc`).
+///
+/// The futures are not `Send`, as `actix_web::HttpRequest` is not. A provider that only
+/// implements `name()` is listed but can not be logged in with yet: `/auth/login/` shows the
+/// `/sorry/` page for it, and none of its `<name>-*` identities are matched.
+pub(crate) trait AuthProvider: Send + Sync {
+    /// The platform name, used in `/auth/login/?platform=<name>`, and as the prefix of the
+    /// `<name>-<field>` user-id of `fastn#app`.
+    fn name(&self) -> &str;
+
+    /// The cookie in which the session token is stored after login.
+    fn cookie_name(&self) -> &str {
+        self.name()
     }
 
-    pub(crate) fn from_str(s: &str) -> Self {
-        match s {
-            "github" => AuthProviders::GitHub,
-            "telegram" => AuthProviders::TeleGram,
-            "google" => AuthProviders::Google,
-            "discord" => AuthProviders::Discord,
-            "slack" => AuthProviders::Slack,
-            _ => panic!("Invalid auth provider {}", s),
+    /// The url the platform redirects to after login, `None` if login is not supported yet.
+    fn callback_url(&self) -> Option<&str> {
+        None
+    }
+
+    /// Starts the login flow. `None` means this provider does not support login yet.
+    fn login<'a>(
+        &'a self,
+        _req: actix_web::HttpRequest,
+    ) -> futures::future::LocalBoxFuture<'a, fastn_core::Result<Option<fastn_core::http::Response>>>
+    {
+        Box::pin(async { Ok(None) })
+    }
+
+    /// Handles the request made to `callback_url()`, and sets `cookie_name()` cookie.
+    fn callback<'a>(
+        &'a self,
+        _req: actix_web::HttpRequest,
+    ) -> futures::future::LocalBoxFuture<'a, fastn_core::Result<fastn_core::http::Response>> {
+        Box::pin(async {
+            Ok(actix_web::HttpResponse::new(
+                actix_web::http::StatusCode::NOT_FOUND,
+            ))
+        })
+    }
+
+    /// Returns `field` of the user detail stored in the session.
+    fn user_detail(&self, user_detail: &str, field: &str) -> fastn_core::Result<Option<String>> {
+        let key = match field {
+            "username" | "user_name" | "user-name" => "user_name",
+            "id" | "uid" | "userid" | "user-id" | "user_id" => "user_id",
+            "token" => "token",
+            _ => "",
+        };
+        let user_detail: std::collections::HashMap<String, serde_json::Value> =
+            serde_json::from_str(user_detail)?;
+        match user_detail.get(key).and_then(|v| v.as_str()) {
+            Some(value) => Ok(Some(value.to_string())),
+            None => Err(fastn_core::Error::GenericError(format!(
+                "invalid field {} requested for platform {}",
+                field,
+                self.name()
+            ))),
         }
     }
+
+    /// Returns the identities out of `identities` the logged-in user has.
+    fn matched_identities<'a>(
+        &'a self,
+        _user_detail: &'a str,
+        _identities: &'a [fastn_core::user_group::UserIdentity],
+    ) -> futures::future::LocalBoxFuture<
+        'a,
+        fastn_core::Result<Vec<fastn_core::user_group::UserIdentity>>,
+    > {
+        Box::pin(async { Ok(vec![]) })
+    }
 }
 
-pub fn secret_key() -> String {
-    match std::env::var("SECRET_KEY") {
-        Ok(secret) => secret,
-        Err(_e) => {
-            println!("WARN: SECRET_KEY not set");
-            // TODO: Need to change this approach later
-            "FASTN_TEMP_SECRET".to_string()
+static PROVIDERS: once_cell::sync::Lazy<Vec<std::sync::Arc<dyn AuthProvider>>> =
+    once_cell::sync::Lazy::new(|| {
+        vec![
+            std::sync::Arc::new(github::GitHub),
+            std::sync::Arc::new(telegram::Telegram),
+            std::sync::Arc::new(google::Google),
+            std::sync::Arc::new(discord::Discord),
+            std::sync::Arc::new(slack::Slack),
+            std::sync::Arc::new(amazon::Amazon),
+            std::sync::Arc::new(apple::Apple),
+            std::sync::Arc::new(baidu::Baidu),
+            std::sync::Arc::new(bitbucket::BitBucket),
+            std::sync::Arc::new(digitalocean::DigitalOcean),
+            std::sync::Arc::new(doorkeeper::DoorKeeper),
+            std::sync::Arc::new(dropbox::DropBox),
+            std::sync::Arc::new(facebook::Facebook),
+            std::sync::Arc::new(gitlab::GitLab),
+            std::sync::Arc::new(instagram::Instagram),
+            std::sync::Arc::new(linkedin::LinkedIn),
+            std::sync::Arc::new(microsoft::Microsoft),
+            std::sync::Arc::new(okta::Okta),
+            std::sync::Arc::new(pintrest::Pintrest),
+            std::sync::Arc::new(tiktok::TikTok),
+            std::sync::Arc::new(twitch::Twitch),
+            std::sync::Arc::new(twitter::Twitter),
+            std::sync::Arc::new(wechat::WeChat),
+            std::sync::Arc::new(yahoo::Yahoo),
+            std::sync::Arc::new(zoho::Zoho),
+            std::sync::Arc::new(gmail::Gmail),
+            std::sync::Arc::new(email::Email),
+        ]
+    });
+
+/// `providers()` returns the providers declared in FASTN.ftd followed by the built-in ones.
+/// A declared provider replaces the built-in provider of the same name.
+pub(crate) fn providers() -> Vec<std::sync::Arc<dyn AuthProvider>> {
+    let mut providers = oidc::providers();
+    for provider in PROVIDERS.iter() {
+        if !providers.iter().any(|p| p.name() == provider.name()) {
+            providers.push(provider.clone());
         }
     }
+    providers
+}
+
+/// `provider()` returns the registered provider with the given name, if any.
+pub(crate) fn provider(name: &str) -> fastn_core::Result<std::sync::Arc<dyn AuthProvider>> {
+    providers()
+        .into_iter()
+        .find(|p| p.name() == name)
+        .ok_or_else(|| fastn_core::Error::UnknownAuthProvider {
+            name: name.to_string(),
+        })
+}
+
+/// `is_login()` checks if any of the provider cookies carries a live session.
+pub(crate) fn is_login(cookies: &std::collections::HashMap<String, String>) -> bool {
+    session::from_cookies(cookies)
+        .map(|sessions| !sessions.is_empty())
+        .unwrap_or(false)
 }
 
-/// will fetch out the decrypted user data from cookies
+/// will fetch out the user data of the session in cookies, or of a provider linked to it,
 /// and return it as string
-/// if no cookie wrt to platform found it throws an error
+/// if no session or link wrt to platform found it returns None
 pub async fn get_user_data_from_cookies(
     platform: &str,
     requested_field: &str,
     cookies: &std::collections::HashMap<String, String>,
 ) -> fastn_core::Result<Option<String>> {
-    let ud_encrypted = cookies.get(platform).ok_or_else(|| {
-        fastn_core::Error::GenericError(format!(
-            "user detail not found for platform {} in the cookies",
-            platform
-        ))
-    });
-    match ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                match fastn_core::auth::AuthProviders::from_str(platform) {
-                    fastn_core::auth::AuthProviders::GitHub => {
-                        let github_ud: github::UserDetail =
-                            serde_json::from_str(ud_decrypted.as_str())?;
-                        return match requested_field {
-                            "username" | "user_name" | "user-name" => Ok(Some(github_ud.user_name)),
-                            "token" => Ok(Some(github_ud.token)),
-                            _ => Err(fastn_core::Error::GenericError(format!(
-                                "invalid field {} requested for platform {}",
-                                requested_field, platform
-                            ))),
-                        };
-                    }
-                    fastn_core::auth::AuthProviders::TeleGram => {
-                        let telegram_ud: telegram::UserDetail =
-                            serde_json::from_str(ud_decrypted.as_str())?;
-                        return match requested_field {
-                            "username" | "user_name" | "user-name" => {
-                                Ok(Some(telegram_ud.user_name))
-                            }
-                            "uid" | "userid" | "user-id" => Ok(Some(telegram_ud.user_id)),
-                            "token" => Ok(Some(telegram_ud.token)),
-                            _ => Err(fastn_core::Error::GenericError(format!(
-                                "invalid field {} requested for platform {}",
-                                requested_field, platform
-                            ))),
-                        };
-                    }
-                    fastn_core::auth::AuthProviders::Discord => {
-                        let discord_ud: discord::UserDetail =
-                            serde_json::from_str(ud_decrypted.as_str())?;
-                        return match requested_field {
-                            "username" | "user_name" | "user-name" => {
-                                Ok(Some(discord_ud.user_name))
-                            }
-                            "id" | "userid" | "user-id" => Ok(Some(discord_ud.user_id)),
-                            "token" => Ok(Some(discord_ud.token)),
-                            _ => Err(fastn_core::Error::GenericError(format!(
-                                "invalid field {} requested for platform {}",
-                                requested_field, platform
-                            ))),
-                        };
-                    }
-                    _ => unimplemented!(),
-                }
-            }
-        }
-        Err(err) => {
-            // Debug out the error and return None
-            let error_msg = format!("User data error: {}", err);
-            dbg!(&error_msg);
+    let provider = provider(platform)?;
+    match link::user(cookies)?
+        .identities
+        .into_iter()
+        .find(|identity| identity.provider.eq(provider.name()))
+    {
+        Some(identity) => provider.user_detail(identity.user_detail.as_str(), requested_field),
+        None => {
+            tracing::info!(
+                msg = "user detail not found in the cookies",
+                platform = platform
+            );
+            Ok(None)
         }
-    };
-    Ok(None)
+    }
 }
 
 // TODO: rename the method later
@@ -223,549 +207,19 @@ pub async fn get_auth_identities(
 ) -> fastn_core::Result<Vec<fastn_core::user_group::UserIdentity>> {
     let mut matched_identities: Vec<fastn_core::user_group::UserIdentity> = vec![];
 
-    let github_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::GitHub.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError(
-                "github user detail not found in the cookies".to_string(),
-            )
-        });
-    match github_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(github_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let github_ud: github::UserDetail =
-                    serde_json::from_str(github_ud_decrypted.as_str())?;
-                matched_identities.extend(github::matched_identities(github_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            // TODO: What to do with this error
-            format!("{}{}", "github user detail not found in the cookies", err);
-        }
-    };
-    let telegram_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::TeleGram.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError(
-                "telegram user detail not found in the cookies".to_string(),
-            )
-        });
-    match telegram_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(telegram_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let telegram_ud: telegram::UserDetail =
-                    serde_json::from_str(telegram_ud_decrypted.as_str())?;
-                matched_identities
-                    .extend(telegram::matched_identities(telegram_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            format!("{}{}", "telegram user detail not found in the cookies", err);
-        }
-    };
-    let discord_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::Discord.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError(
-                "discord user detail not found in the cookies".to_string(),
-            )
-        });
-    match discord_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(discord_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let discord_ud: discord::UserDetail =
-                    serde_json::from_str(discord_ud_decrypted.as_str())?;
-                matched_identities
-                    .extend(discord::matched_identities(discord_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            format!("{}{}", "discord user detail not found in the cookies", err);
-        }
-    };
-    let twitter_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::Twitter.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError(
-                "twitter user detail not found in the cookies".to_string(),
-            )
-        });
-
-    match twitter_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(twitter_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let twitter_ud: twitter::UserDetail =
-                    serde_json::from_str(twitter_ud_decrypted.as_str())?;
-                matched_identities
-                    .extend(twitter::matched_identities(twitter_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            format!("{}{}", "twitter user detail not found in the cookies", err);
-        }
-    };
-    let amazon_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::Amazon.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError(
-                "amazon user detail not found in the cookies".to_string(),
-            )
-        });
-    match amazon_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(amazon_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let amazon_ud: amazon::UserDetail =
-                    serde_json::from_str(amazon_ud_decrypted.as_str())?;
-                matched_identities.extend(amazon::matched_identities(amazon_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            format!("{}{}", "amazon user detail not found in the cookies", err);
-        }
-    };
-    let facebook_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::Facebook.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError(
-                "facebook user detail not found in the cookies".to_string(),
-            )
-        });
-    match facebook_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(facebook_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let facebook_ud: facebook::UserDetail =
-                    serde_json::from_str(facebook_ud_decrypted.as_str())?;
-                matched_identities
-                    .extend(facebook::matched_identities(facebook_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            format!("{}{}", "facebook user detail not found in the cookies", err);
-        }
-    };
-    let gmail_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::Gmail.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError(
-                "gmail user detail not found in the cookies".to_string(),
-            )
-        });
-    match gmail_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(gmail_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let gmail_ud: gmail::UserDetail =
-                    serde_json::from_str(gmail_ud_decrypted.as_str())?;
-                matched_identities.extend(gmail::matched_identities(gmail_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            format!("{}{}", "gmail user detail not found in the cookies", err);
-        }
-    };
-    let slack_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::Slack.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError(
-                "slack user detail not found in the cookies".to_string(),
-            )
-        });
-    match slack_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(slack_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let slack_ud: slack::UserDetail =
-                    serde_json::from_str(slack_ud_decrypted.as_str())?;
-                matched_identities.extend(slack::matched_identities(slack_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            format!("{}{}", "slack user detail not found in the cookies", err);
-        }
-    };
-    let apple_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::Apple.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError(
-                "apple user detail not found in the cookies".to_string(),
-            )
-        });
-    match apple_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(apple_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let apple_ud: apple::UserDetail =
-                    serde_json::from_str(apple_ud_decrypted.as_str())?;
-                matched_identities.extend(apple::matched_identities(apple_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            format!("{}{}", "apple user detail not found in the cookies", err);
-        }
-    };
-    let baidu_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::Baidu.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError(
-                "baidu user detail not found in the cookies".to_string(),
-            )
-        });
-    match baidu_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(baidu_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let baidu_ud: baidu::UserDetail =
-                    serde_json::from_str(baidu_ud_decrypted.as_str())?;
-                matched_identities.extend(baidu::matched_identities(baidu_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            format!("{}{}", "baidu user detail not found in the cookies", err);
-        }
-    };
-    let bitbucket_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::BitBucket.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError(
-                "bitbucket user detail not found in the cookies".to_string(),
-            )
-        });
-    match bitbucket_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(bitbucket_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let bitbucket_ud: bitbucket::UserDetail =
-                    serde_json::from_str(bitbucket_ud_decrypted.as_str())?;
-                matched_identities
-                    .extend(bitbucket::matched_identities(bitbucket_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            format!(
-                "{}{}",
-                "bitbucket user detail not found in the cookies", err
-            );
-        }
-    };
-    let digitalocean_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::DigitalOcean.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError(
-                "digitalocean user detail not found in the cookies".to_string(),
-            )
-        });
-    match digitalocean_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(digitalocean_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let digitalocean_ud: digitalocean::UserDetail =
-                    serde_json::from_str(digitalocean_ud_decrypted.as_str())?;
-                matched_identities
-                    .extend(digitalocean::matched_identities(digitalocean_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            format!(
-                "{}{}",
-                "digitalocean user detail not found in the cookies", err
-            );
-        }
-    };
-    let doorkeeper_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::DoorKeeper.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError(
-                "doorkeeper user detail not found in the cookies".to_string(),
-            )
-        });
-    match doorkeeper_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(doorkeeper_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let doorkeeper_ud: doorkeeper::UserDetail =
-                    serde_json::from_str(doorkeeper_ud_decrypted.as_str())?;
-                matched_identities
-                    .extend(doorkeeper::matched_identities(doorkeeper_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            format!(
-                "{}{}",
-                "doorkeeper user detail not found in the cookies", err
-            );
-        }
-    };
-    let dropbox_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::DropBox.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError(
-                "DropBox user detail not found in the cookies".to_string(),
-            )
-        });
-    match dropbox_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(dropbox_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let dropbox_ud: dropbox::UserDetail =
-                    serde_json::from_str(dropbox_ud_decrypted.as_str())?;
-                matched_identities
-                    .extend(dropbox::matched_identities(dropbox_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            format!("{}{}", "dropbox user detail not found in the cookies", err);
-        }
-    };
-    let gitlab_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::GitLab.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError(
-                "GitLab user detail not found in the cookies".to_string(),
-            )
-        });
-    match gitlab_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(gitlab_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let gitlab_ud: gitlab::UserDetail =
-                    serde_json::from_str(gitlab_ud_decrypted.as_str())?;
-                matched_identities.extend(gitlab::matched_identities(gitlab_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            format!("{}{}", "GitLab user detail not found in the cookies", err);
-        }
-    };
-    let instagram_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::Instagram.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError(
-                "Instagram user detail not found in the cookies".to_string(),
-            )
-        });
-    match instagram_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(instagram_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let instagram_ud: instagram::UserDetail =
-                    serde_json::from_str(instagram_ud_decrypted.as_str())?;
-                matched_identities
-                    .extend(instagram::matched_identities(instagram_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            format!(
-                "{}{}",
-                "Instagram user detail not found in the cookies", err
-            );
-        }
-    };
-    let linkedin_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::LinkedIn.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError(
-                "LinkedIn user detail not found in the cookies".to_string(),
-            )
-        });
-    match linkedin_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(linkedin_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let linkedin_ud: linkedin::UserDetail =
-                    serde_json::from_str(linkedin_ud_decrypted.as_str())?;
-                matched_identities
-                    .extend(linkedin::matched_identities(linkedin_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            format!("{}{}", "LinkedIn user detail not found in the cookies", err);
-        }
-    };
-    let microsoft_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::Microsoft.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError(
-                "Microsoft user detail not found in the cookies".to_string(),
-            )
-        });
-    match microsoft_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(microsoft_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let microsoft_ud: microsoft::UserDetail =
-                    serde_json::from_str(microsoft_ud_decrypted.as_str())?;
-                matched_identities
-                    .extend(microsoft::matched_identities(microsoft_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            format!(
-                "{}{}",
-                "Microsoft user detail not found in the cookies", err
-            );
-        }
-    };
-    let okta_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::Okta.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError("Okta user detail not found in the cookies".to_string())
-        });
-    match okta_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(okta_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let okta_ud: okta::UserDetail = serde_json::from_str(okta_ud_decrypted.as_str())?;
-                matched_identities.extend(okta::matched_identities(okta_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            format!("{}{}", "Okta user detail not found in the cookies", err);
-        }
-    };
-    let pintrest_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::Pintrest.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError(
-                "Pintrest user detail not found in the cookies".to_string(),
-            )
-        });
-    match pintrest_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(pintrest_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let pintrest_ud: pintrest::UserDetail =
-                    serde_json::from_str(pintrest_ud_decrypted.as_str())?;
-                matched_identities
-                    .extend(pintrest::matched_identities(pintrest_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            format!("{}{}", "Pintrest user detail not found in the cookies", err);
-        }
-    };
-    let tiktok_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::TikTok.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError(
-                "TikTok user detail not found in the cookies".to_string(),
-            )
-        });
-    match tiktok_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(tiktok_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let tiktok_ud: tiktok::UserDetail =
-                    serde_json::from_str(tiktok_ud_decrypted.as_str())?;
-                matched_identities.extend(tiktok::matched_identities(tiktok_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            format!("{}{}", "TikTok user detail not found in the cookies", err);
-        }
-    };
-    let twitch_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::Twitch.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError(
-                "Twitch user detail not found in the cookies".to_string(),
-            )
-        });
-    match twitch_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(twitch_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let twitch_ud: twitch::UserDetail =
-                    serde_json::from_str(twitch_ud_decrypted.as_str())?;
-                matched_identities.extend(twitch::matched_identities(twitch_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            format!("{}{}", "Twitch user detail not found in the cookies", err);
-        }
-    };
-    let twitter_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::Twitter.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError(
-                "Twitter user detail not found in the cookies".to_string(),
-            )
-        });
-    match twitter_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(twitter_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let twitter_ud: twitter::UserDetail =
-                    serde_json::from_str(twitter_ud_decrypted.as_str())?;
-                matched_identities
-                    .extend(twitter::matched_identities(twitter_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            format!("{}{}", "Twitter user detail not found in the cookies", err);
-        }
-    };
-    let wechat_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::WeChat.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError(
-                "WeChat user detail not found in the cookies".to_string(),
-            )
-        });
-    match wechat_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(wechat_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let wechat_ud: wechat::UserDetail =
-                    serde_json::from_str(wechat_ud_decrypted.as_str())?;
-                matched_identities.extend(wechat::matched_identities(wechat_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            format!("{}{}", "WeChat user detail not found in the cookies", err);
-        }
-    };
-    let yahoo_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::Yahoo.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError(
-                "Yahoo user detail not found in the cookies".to_string(),
-            )
-        });
-    match yahoo_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(yahoo_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let yahoo_ud: yahoo::UserDetail =
-                    serde_json::from_str(yahoo_ud_decrypted.as_str())?;
-                matched_identities.extend(yahoo::matched_identities(yahoo_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            format!("{}{}", "Yahoo user detail not found in the cookies", err);
-        }
-    };
-    let zoho_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::Zoho.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError("Zoho user detail not found in the cookies".to_string())
-        });
-    match zoho_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(zoho_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let zoho_ud: zoho::UserDetail = serde_json::from_str(zoho_ud_decrypted.as_str())?;
-                matched_identities.extend(zoho::matched_identities(zoho_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            format!("{}{}", "Zoho user detail not found in the cookies", err);
-        }
-    };
-    let google_ud_encrypted = cookies
-        .get(fastn_core::auth::AuthProviders::Google.as_str())
-        .ok_or_else(|| {
-            fastn_core::Error::GenericError(
-                "Google user detail not found in the cookies".to_string(),
-            )
-        });
-    match google_ud_encrypted {
-        Ok(encrypt_str) => {
-            if let Ok(google_ud_decrypted) = utils::decrypt_str(encrypt_str).await {
-                let google_ud: google::UserDetail =
-                    serde_json::from_str(google_ud_decrypted.as_str())?;
-                matched_identities.extend(google::matched_identities(google_ud, identities).await?);
-            }
-        }
-        Err(err) => {
-            format!("{}{}", "Google user detail not found in the cookies", err);
-        }
-    };
-    // TODO: which API to from which platform based on identity
-    // identity can be github-*, discord-*, and etc...
-    //let matched_identities = github::matched_identities(token.as_str(), identities).await?;
+    // the identities of the providers linked to the logged-in ones count too
+    for identity in link::user(cookies)?.identities {
+        // a linked provider may have been removed from FASTN.ftd since
+        let provider = match provider(identity.provider.as_str()) {
+            Ok(provider) => provider,
+            Err(_) => continue,
+        };
+        matched_identities.extend(
+            provider
+                .matched_identities(identity.user_detail.as_str(), identities)
+                .await?,
+        );
+    }
 
-    //TODO: Call discord::matched_identities
-    //TODO: Call google::matched_identities
-    //TODO: Call twitter::matched_identities
     Ok(matched_identities)
 }
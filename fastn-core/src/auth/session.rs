@@ -0,0 +1,455 @@
+// Sessions of the logged-in users.
+//
+// After login the provider's user detail (including its OAuth token) is stored server side, in
+// `.fastn/sessions.sqlite`, and the browser only gets a signed, expiring session token in the
+// `<provider>` cookie:
+//
+//     <session-id>.<expires-at>.<hmac-sha256>
+//
+// Tokens are signed with the first key of `SECRET_KEY`, a comma separated list of keys. All
+// listed keys are accepted when verifying, so a key can be rotated by prepending the new key
+// and dropping the old one once the sessions signed with it have expired.
+//
+// A session is revoked by deleting it from the store: on logout, or with `/-/auth/sessions/`.
+// Sessions of the same user are found by the provider's id of the user (`subject`), not by the
+// user name, which some providers let the user change or do not verify.
+//
+// The same store keeps the linked accounts, see `fastn_core::auth::link`, and the login links
+// sent by `fastn_core::auth::email`.
+
+/// How long a login lasts.
+const SESSION_TTL: std::time::Duration = std::time::Duration::from_secs(30 * 24 * 60 * 60);
+
+static KEYS: once_cell::sync::Lazy<Vec<Vec<u8>>> = once_cell::sync::Lazy::new(|| {
+    let keys = configured_keys();
+    if !keys.is_empty() {
+        return keys;
+    }
+    fastn_core::warning!(
+        "SECRET_KEY not set, using a random key, logins will not survive a restart of fastn"
+    );
+    vec![rand::Rng::gen::<[u8; 32]>(&mut rand::thread_rng()).to_vec()]
+});
+
+static STORE: once_cell::sync::OnceCell<antidote::Mutex<rusqlite::Connection>> =
+    once_cell::sync::OnceCell::new();
+
+fn configured_keys() -> Vec<Vec<u8>> {
+    std::env::var("SECRET_KEY")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|k| !k.is_empty())
+        .map(|k| k.as_bytes().to_vec())
+        .collect()
+}
+
+/// `init()` opens the session store of the package at `root`. `fastn serve` calls it before
+/// it starts listening, and refuses to start in remote mode if `SECRET_KEY` is not set.
+pub(crate) fn init(root: &camino::Utf8Path) -> fastn_core::Result<()> {
+    if cfg!(feature = "remote") && configured_keys().is_empty() {
+        return Err(fastn_core::Error::UsageError {
+            message: "SECRET_KEY must be set when running fastn in remote mode".to_string(),
+        });
+    }
+    once_cell::sync::Lazy::force(&KEYS);
+
+    let dir = root.join(".fastn");
+    std::fs::create_dir_all(&dir)?;
+    let conn = rusqlite::Connection::open(dir.join("sessions.sqlite"))?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS fastn_session (
+            id          TEXT PRIMARY KEY,
+            provider    TEXT NOT NULL,
+            subject     TEXT NOT NULL,
+            user_name   TEXT NOT NULL,
+            user_detail TEXT NOT NULL,
+            created_at  INTEGER NOT NULL,
+            expires_at  INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS fastn_user_link (
+            provider    TEXT NOT NULL,
+            subject     TEXT NOT NULL,
+            user_name   TEXT NOT NULL,
+            user_id     TEXT NOT NULL,
+            user_detail TEXT NOT NULL,
+            PRIMARY KEY (provider, subject)
+        );
+        CREATE TABLE IF NOT EXISTS fastn_email_login (
+            nonce       TEXT PRIMARY KEY,
+            expires_at  INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS fastn_email_sent (
+            email       TEXT NOT NULL,
+            client      TEXT NOT NULL,
+            sent_at     INTEGER NOT NULL
+        );",
+    )?;
+    // calling `init()` again (tests) keeps the store opened first
+    let _ = STORE.set(antidote::Mutex::new(conn));
+    Ok(())
+}
+
+pub(super) fn store() -> fastn_core::Result<&'static antidote::Mutex<rusqlite::Connection>> {
+    STORE
+        .get()
+        .ok_or_else(|| fastn_core::Error::generic("session store is not initialised"))
+}
+
+pub(super) fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// A random id, for sessions and linked users.
+pub(super) fn random_id() -> String {
+    hex(&rand::Rng::gen::<[u8; 16]>(&mut rand::thread_rng()))
+}
+
+fn unhex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn mac(key: &[u8], payload: &str) -> hmac::Hmac<sha2::Sha256> {
+    let mut mac = <hmac::Hmac<sha2::Sha256> as hmac::Mac>::new_from_slice(key)
+        .expect("HMAC can take key of any size");
+    hmac::Mac::update(&mut mac, payload.as_bytes());
+    mac
+}
+
+/// `Purpose` is what a token is signed for. It is part of the signed data, so a token signed for
+/// one purpose, say the state of an OpenID Connect login, is not accepted as a session.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Purpose {
+    Session,
+    OidcState,
+    EmailLink,
+}
+
+impl Purpose {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Purpose::Session => "session",
+            Purpose::OidcState => "oidc-state",
+            Purpose::EmailLink => "email-link",
+        }
+    }
+}
+
+fn sign_with(key: &[u8], purpose: Purpose, value: &str, expires_at: i64) -> String {
+    let payload = format!("{}.{}", hex(value.as_bytes()), expires_at);
+    let signed = format!("{}|{}", purpose.as_str(), payload);
+    let signature = hex(&hmac::Mac::finalize(mac(key, signed.as_str())).into_bytes());
+    format!("{}.{}", payload, signature)
+}
+
+fn verify_with(keys: &[Vec<u8>], purpose: Purpose, token: &str, now: i64) -> Option<String> {
+    let (payload, signature) = token.rsplit_once('.')?;
+    let (value, expires_at) = payload.split_once('.')?;
+    let signature = unhex(signature)?;
+    let signed = format!("{}|{}", purpose.as_str(), payload);
+    if !keys
+        .iter()
+        .any(|key| hmac::Mac::verify_slice(mac(key, signed.as_str()), &signature).is_ok())
+    {
+        return None;
+    }
+    if expires_at.parse::<i64>().ok()? < now {
+        return None;
+    }
+    String::from_utf8(unhex(value)?).ok()
+}
+
+/// `sign()` returns a token carrying `value` that can not be altered, and that `verify()`
+/// accepts for the same `purpose` till `ttl` has passed. `value` is readable by whoever has the
+/// token.
+pub(crate) fn sign(purpose: Purpose, value: &str, ttl: std::time::Duration) -> String {
+    sign_with(&KEYS[0], purpose, value, now() + ttl.as_secs() as i64)
+}
+
+/// `verify()` returns the value of a token created by `sign()` for `purpose`, if it is
+/// unaltered, signed with one of the keys and not expired.
+pub(crate) fn verify(purpose: Purpose, token: &str) -> Option<String> {
+    verify_with(KEYS.as_slice(), purpose, token, now())
+}
+
+/// `subject()` is the id `provider` knows the user of `user_detail` by.
+pub(super) fn subject(provider: &str, user_detail: &str) -> fastn_core::Result<String> {
+    match fastn_core::auth::provider(provider)?.user_detail(user_detail, "user-id")? {
+        Some(subject) if !subject.is_empty() => Ok(subject),
+        _ => Err(fastn_core::Error::generic(format!(
+            "{} did not return the id of the user",
+            provider
+        ))),
+    }
+}
+
+/// `create()` stores a new session for the user detail returned by `provider`, and returns the
+/// token to set in the `provider` cookie.
+pub(crate) fn create(provider: &str, user_detail: &str) -> fastn_core::Result<String> {
+    let subject = subject(provider, user_detail)?;
+    let user_name = fastn_core::auth::provider(provider)?
+        .user_detail(user_detail, "user-name")
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    let id = random_id();
+    let now = now();
+    let expires_at = now + SESSION_TTL.as_secs() as i64;
+
+    let conn = store()?.lock();
+    conn.execute("DELETE FROM fastn_session WHERE expires_at < ?1", [now])?;
+    conn.execute(
+        "INSERT INTO fastn_session
+         (id, provider, subject, user_name, user_detail, created_at, expires_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        rusqlite::params![
+            id,
+            provider,
+            subject,
+            user_name,
+            user_detail,
+            now,
+            expires_at
+        ],
+    )?;
+    // a linked account keeps the latest user detail of the provider
+    conn.execute(
+        "UPDATE fastn_user_link SET user_name = ?1, user_detail = ?2
+         WHERE provider = ?3 AND subject = ?4",
+        rusqlite::params![user_name, user_detail, provider, subject],
+    )?;
+
+    Ok(sign_with(
+        &KEYS[0],
+        Purpose::Session,
+        id.as_str(),
+        expires_at,
+    ))
+}
+
+/// `cookie_max_age()` is how long the browser should keep the session cookie.
+pub(crate) fn cookie_max_age() -> actix_web::cookie::time::Duration {
+    actix_web::cookie::time::Duration::seconds(SESSION_TTL.as_secs() as i64)
+}
+
+#[derive(Debug, serde::Serialize)]
+pub(crate) struct Session {
+    pub id: String,
+    pub provider: String,
+    #[serde(skip)]
+    pub subject: String,
+    #[serde(rename = "user-name")]
+    pub user_name: String,
+    #[serde(rename = "created-at")]
+    pub created_at: i64,
+    #[serde(rename = "expires-at")]
+    pub expires_at: i64,
+}
+
+fn session(id: &str) -> fastn_core::Result<Option<(Session, String)>> {
+    let conn = store()?.lock();
+    let mut stmt = conn.prepare(
+        "SELECT id, provider, subject, user_name, created_at, expires_at, user_detail
+         FROM fastn_session WHERE id = ?1 AND expires_at >= ?2",
+    )?;
+    let mut rows = stmt.query(rusqlite::params![id, now()])?;
+    match rows.next()? {
+        Some(row) => Ok(Some((
+            Session {
+                id: row.get(0)?,
+                provider: row.get(1)?,
+                subject: row.get(2)?,
+                user_name: row.get(3)?,
+                created_at: row.get(4)?,
+                expires_at: row.get(5)?,
+            },
+            row.get(6)?,
+        ))),
+        None => Ok(None),
+    }
+}
+
+/// `revoke()` ends the session of the given token, if any.
+pub(crate) fn revoke(token: &str) -> fastn_core::Result<()> {
+    if let Some(id) = verify(Purpose::Session, token) {
+        store()?
+            .lock()
+            .execute("DELETE FROM fastn_session WHERE id = ?1", [id])?;
+    }
+    Ok(())
+}
+
+/// `from_cookies()` returns the live sessions in the provider cookies, with their user detail.
+pub(crate) fn from_cookies(
+    cookies: &std::collections::HashMap<String, String>,
+) -> fastn_core::Result<Vec<(Session, String)>> {
+    let mut sessions = vec![];
+    for provider in fastn_core::auth::providers() {
+        let id = match cookies
+            .get(provider.cookie_name())
+            .and_then(|token| verify(Purpose::Session, token))
+        {
+            Some(id) => id,
+            None => continue,
+        };
+        if let Some((session, user_detail)) = session(id.as_str())? {
+            if session.provider.eq(provider.name()) {
+                sessions.push((session, user_detail));
+            }
+        }
+    }
+    Ok(sessions)
+}
+
+/// The sessions of the user who made the request: every session of the same provider and
+/// subject as one of the sessions in the request cookies.
+fn user_sessions(req: &fastn_core::http::Request) -> fastn_core::Result<Vec<Session>> {
+    let users = from_cookies(req.cookies())?
+        .into_iter()
+        .map(|(session, _)| (session.provider, session.subject))
+        .collect::<Vec<_>>();
+
+    let conn = store()?.lock();
+    let mut stmt = conn.prepare(
+        "SELECT id, provider, subject, user_name, created_at, expires_at
+         FROM fastn_session WHERE provider = ?1 AND subject = ?2 AND expires_at >= ?3
+         ORDER BY created_at DESC",
+    )?;
+    let mut sessions = vec![];
+    for (provider, subject) in users {
+        let rows = stmt.query_map(rusqlite::params![provider, subject, now()], |row| {
+            Ok(Session {
+                id: row.get(0)?,
+                provider: row.get(1)?,
+                subject: row.get(2)?,
+                user_name: row.get(3)?,
+                created_at: row.get(4)?,
+                expires_at: row.get(5)?,
+            })
+        })?;
+        for session in rows {
+            sessions.push(session?);
+        }
+    }
+    Ok(sessions)
+}
+
+// route: /-/auth/sessions/
+//
+// GET lists the sessions of the logged-in user, POST revokes them:
+// `{"id": "<session-id>"}` revokes one session, `{"all": true}` all of them.
+pub(crate) async fn handle(
+    req: fastn_core::http::Request,
+) -> fastn_core::Result<fastn_core::http::Response> {
+    let sessions = user_sessions(&req)?;
+    if sessions.is_empty() {
+        return Ok(fastn_core::unauthorised!("not logged in"));
+    }
+
+    match req.method().to_lowercase().as_str() {
+        "get" => fastn_core::http::api_ok(sessions),
+        "post" => {
+            #[derive(serde::Deserialize)]
+            struct Revoke {
+                id: Option<String>,
+                #[serde(default)]
+                all: bool,
+            }
+            let revoke: Revoke = req.json()?;
+            let ids = sessions
+                .into_iter()
+                .map(|s| s.id)
+                .filter(|id| revoke.all || revoke.id.as_ref() == Some(id))
+                .collect::<Vec<String>>();
+            if ids.is_empty() {
+                return fastn_core::http::api_error("session not found");
+            }
+            let conn = store()?.lock();
+            for id in ids.iter() {
+                conn.execute("DELETE FROM fastn_session WHERE id = ?1", [id])?;
+            }
+            fastn_core::http::api_ok(ids)
+        }
+        _ => Ok(actix_web::HttpResponse::MethodNotAllowed().finish()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn sign_and_verify() {
+        let old = b"old-key".to_vec();
+        let new = b"new-key".to_vec();
+        let token = super::sign_with(&old, super::Purpose::Session, "session-1", 100);
+
+        assert_eq!(
+            super::verify_with(&[old.clone()], super::Purpose::Session, &token, 50),
+            Some("session-1".to_string())
+        );
+        // rotated: signed with a key that is still listed
+        assert_eq!(
+            super::verify_with(
+                &[new.clone(), old.clone()],
+                super::Purpose::Session,
+                &token,
+                50
+            ),
+            Some("session-1".to_string())
+        );
+        // key dropped
+        assert_eq!(
+            super::verify_with(&[new.clone()], super::Purpose::Session, &token, 50),
+            None
+        );
+        // expired
+        assert_eq!(
+            super::verify_with(&[old.clone()], super::Purpose::Session, &token, 101),
+            None
+        );
+        // tampered
+        let other = super::sign_with(&new, super::Purpose::Session, "session-2", 100);
+        let (value, _) = other.split_once('.').unwrap();
+        let (_, rest) = token.split_once('.').unwrap();
+        assert_eq!(
+            super::verify_with(
+                &[old.clone(), new],
+                super::Purpose::Session,
+                &format!("{}.{}", value, rest),
+                50
+            ),
+            None
+        );
+        // signed for another purpose
+        let state = super::sign_with(&old, super::Purpose::OidcState, "session-1", 100);
+        assert_eq!(
+            super::verify_with(&[old.clone()], super::Purpose::Session, &state, 50),
+            None
+        );
+        assert_eq!(
+            super::verify_with(&[old], super::Purpose::OidcState, &state, 50),
+            Some("session-1".to_string())
+        );
+    }
+
+    #[test]
+    fn forged_cookie_is_not_a_login() {
+        let cookies = std::collections::HashMap::from([
+            ("github".to_string(), "x".to_string()),
+            ("email".to_string(), "abcd.4102444800.00".to_string()),
+        ]);
+        assert!(!fastn_core::auth::is_login(&cookies));
+    }
+}
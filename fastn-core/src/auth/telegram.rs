@@ -6,6 +6,48 @@ pub struct UserDetail {
     pub user_name: String,
     pub token: String,
 }
+
+pub(crate) struct Telegram;
+
+impl fastn_core::auth::AuthProvider for Telegram {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    fn callback_url(&self) -> Option<&str> {
+        Some(CALLBACK_URL)
+    }
+
+    fn login<'a>(
+        &'a self,
+        req: actix_web::HttpRequest,
+    ) -> futures::future::LocalBoxFuture<'a, fastn_core::Result<Option<fastn_core::http::Response>>>
+    {
+        Box::pin(async move { login(req).await.map(Some) })
+    }
+
+    fn callback<'a>(
+        &'a self,
+        req: actix_web::HttpRequest,
+    ) -> futures::future::LocalBoxFuture<'a, fastn_core::Result<fastn_core::http::Response>> {
+        Box::pin(token(req))
+    }
+
+    fn matched_identities<'a>(
+        &'a self,
+        user_detail: &'a str,
+        identities: &'a [fastn_core::user_group::UserIdentity],
+    ) -> futures::future::LocalBoxFuture<
+        'a,
+        fastn_core::Result<Vec<fastn_core::user_group::UserIdentity>>,
+    > {
+        Box::pin(async move {
+            let ud: UserDetail = serde_json::from_str(user_detail)?;
+            matched_identities(ud, identities).await
+        })
+    }
+}
+
 // route: /auth/login/
 
 pub async fn login(req: actix_web::HttpRequest) -> fastn_core::Result<fastn_core::http::Response> {
@@ -60,14 +102,18 @@ pub async fn token(req: actix_web::HttpRequest) -> fastn_core::Result<actix_web:
     return Ok(actix_web::HttpResponse::Found()
         .cookie(
             actix_web::cookie::Cookie::build(
-                fastn_core::auth::AuthProviders::TeleGram.as_str(),
-                fastn_core::auth::utils::encrypt_str(&user_detail_str).await,
+                fastn_core::auth::AuthProvider::cookie_name(&Telegram),
+                fastn_core::auth::session::create(
+                    fastn_core::auth::AuthProvider::name(&Telegram),
+                    user_detail_str.as_str(),
+                )?,
             )
             .domain(fastn_core::auth::utils::domain(
                 req.connection_info().host(),
             ))
             .path("/")
-            .permanent()
+            .http_only(true)
+            .max_age(fastn_core::auth::session::cookie_max_age())
             .secure(true)
             .finish(),
         )
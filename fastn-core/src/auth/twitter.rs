@@ -9,6 +9,47 @@ pub struct UserDetail {
     pub user_id: String,
 }
 
+pub(crate) struct Twitter;
+
+impl fastn_core::auth::AuthProvider for Twitter {
+    fn name(&self) -> &str {
+        "twitter"
+    }
+
+    fn callback_url(&self) -> Option<&str> {
+        Some(CALLBACK_URL)
+    }
+
+    fn login<'a>(
+        &'a self,
+        req: actix_web::HttpRequest,
+    ) -> futures::future::LocalBoxFuture<'a, fastn_core::Result<Option<fastn_core::http::Response>>>
+    {
+        Box::pin(async move { login(req).await.map(Some) })
+    }
+
+    fn callback<'a>(
+        &'a self,
+        req: actix_web::HttpRequest,
+    ) -> futures::future::LocalBoxFuture<'a, fastn_core::Result<fastn_core::http::Response>> {
+        Box::pin(callback(req))
+    }
+
+    fn matched_identities<'a>(
+        &'a self,
+        user_detail: &'a str,
+        identities: &'a [fastn_core::user_group::UserIdentity],
+    ) -> futures::future::LocalBoxFuture<
+        'a,
+        fastn_core::Result<Vec<fastn_core::user_group::UserIdentity>>,
+    > {
+        Box::pin(async move {
+            let ud: UserDetail = serde_json::from_str(user_detail)?;
+            matched_identities(ud, identities).await
+        })
+    }
+}
+
 pub(crate) enum TwitterScopes {
     ReadTweet,
     WriteTweet,
@@ -138,14 +179,18 @@ pub async fn callback(req: actix_web::HttpRequest) -> fastn_core::Result<actix_w
             return Ok(actix_web::HttpResponse::Found()
                 .cookie(
                     actix_web::cookie::Cookie::build(
-                        fastn_core::auth::AuthProviders::Twitter.as_str(),
-                        fastn_core::auth::utils::encrypt_str(&user_detail_str).await,
+                        fastn_core::auth::AuthProvider::cookie_name(&Twitter),
+                        fastn_core::auth::session::create(
+                            fastn_core::auth::AuthProvider::name(&Twitter),
+                            user_detail_str.as_str(),
+                        )?,
                     )
                     .domain(fastn_core::auth::utils::domain(
                         req.connection_info().host(),
                     ))
                     .path("/")
-                    .permanent()
+                    .http_only(true)
+                    .max_age(fastn_core::auth::session::cookie_max_age())
                     .finish(),
                 )
                 .append_header((actix_web::http::header::LOCATION, "/".to_string()))
@@ -1,5 +1,3 @@
-use magic_crypt::MagicCryptError;
-
 // 127.0.0.1:8000 -> 127.0.0.1
 pub fn domain(host: &str) -> String {
     match host.split_once(':') {
@@ -32,28 +30,7 @@ pub async fn get_api<T: serde::de::DeserializeOwned>(
 
     Ok(response.json().await?)
 }
-pub async fn encrypt_str(user_detail_str: &String) -> String {
-    use magic_crypt::MagicCryptTrait;
-    let secret_key = fastn_core::auth::secret_key();
-    let mc_obj = magic_crypt::new_magic_crypt!(secret_key.as_str(), 256);
-    mc_obj
-        .encrypt_to_base64(user_detail_str)
-        .as_str()
-        .to_owned()
-}
-pub async fn decrypt_str(encrypted_str: &String) -> Result<String, MagicCryptError> {
-    use magic_crypt::MagicCryptTrait;
-    let secret_key = fastn_core::auth::secret_key();
-    let mc_obj = magic_crypt::new_magic_crypt!(&secret_key, 256);
-    mc_obj.decrypt_base64_to_string(encrypted_str)
-}
-pub fn is_login(req: &actix_web::HttpRequest) -> bool {
-    let mut found_cookie = false;
-    for auth_provider in fastn_core::auth::AuthProviders::AUTH_ITER.iter() {
-        dbg!(&auth_provider);
-        if req.cookie(auth_provider.as_str()).is_some() {
-            found_cookie = true;
-        }
-    }
-    found_cookie
+/// Only paths on this site are followed after login.
+pub(crate) fn is_local(next: &str) -> bool {
+    next.starts_with('/') && !next.starts_with("//") && !next.starts_with("/\\")
 }
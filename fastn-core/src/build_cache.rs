@@ -0,0 +1,258 @@
+// `fastn build` only renders the documents whose inputs changed since the last build. For every
+// file it writes, it records in `.build/.inputs.json` the content hashes of what rendering it
+// read: FASTN.ftd, the document, the modules it imports, from the package or `.packages`, and
+// the files read by processors like `get-data`, `fetch-file` and `package-query`.
+//
+// Documents that used something else that can change, like the `http` processor, are always
+// rendered again, and so is everything when fastn, or the options of `fastn build`, changed, or
+// with `fastn build --force`.
+//
+// Files are hashed once while their size and modification time stay the same, see
+// `hash_file()`, so inputs shared by many documents, like FASTN.ftd or the database of
+// `package-query`, are not read again for every document.
+
+static HASHES: once_cell::sync::Lazy<
+    antidote::Mutex<std::collections::HashMap<camino::Utf8PathBuf, (FileStamp, String)>>,
+> = once_cell::sync::Lazy::new(|| antidote::Mutex::new(std::collections::HashMap::new()));
+
+/// The size and modification time of a file.
+type FileStamp = (u64, Option<std::time::SystemTime>);
+
+/// The inputs read while rendering a document, collected in `Config.build_inputs`.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Inputs {
+    /// Content hash by path, relative to the package root
+    pub hashes: std::collections::BTreeMap<String, String>,
+    /// The document used something its content hashes do not cover
+    pub volatile: bool,
+}
+
+impl Inputs {
+    pub(crate) fn file(
+        &mut self,
+        root: &camino::Utf8Path,
+        path: &camino::Utf8Path,
+        content: &[u8],
+    ) {
+        let path = path.strip_prefix(root).unwrap_or(path);
+        self.hashes.insert(path.to_string(), hash(content));
+    }
+
+    /// `path()` records the file at `path`, for inputs that are not read whole, like databases.
+    pub(crate) fn path(
+        &mut self,
+        root: &camino::Utf8Path,
+        path: &camino::Utf8Path,
+    ) -> std::io::Result<()> {
+        let hash = hash_file(path)?;
+        let path = path.strip_prefix(root).unwrap_or(path);
+        self.hashes.insert(path.to_string(), hash);
+        Ok(())
+    }
+}
+
+pub(crate) fn hash(content: &[u8]) -> String {
+    use sha2::Digest;
+
+    format!("{:x}", sha2::Sha256::digest(content))
+}
+
+/// `hash_file()` is the hash of the content of the file at `path`, it is only read if the file
+/// changed since it was last hashed.
+pub(crate) fn hash_file(path: &camino::Utf8Path) -> std::io::Result<String> {
+    let metadata = std::fs::metadata(path)?;
+    let stamp = (metadata.len(), metadata.modified().ok());
+    if let Some((s, hash)) = HASHES.lock().get(path) {
+        if s.eq(&stamp) && stamp.1.is_some() {
+            return Ok(hash.to_string());
+        }
+    }
+    let hash = hash(&std::fs::read(path)?);
+    HASHES
+        .lock()
+        .insert(path.to_path_buf(), (stamp, hash.to_string()));
+    Ok(hash)
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub(crate) struct BuildCache {
+    /// The fastn version and build options the outputs were built with
+    options: String,
+    /// Inputs by output file, relative to `.build`
+    outputs: std::collections::BTreeMap<String, Inputs>,
+}
+
+impl BuildCache {
+    fn path(config: &fastn_core::Config) -> camino::Utf8PathBuf {
+        config.build_dir().join(".inputs.json")
+    }
+
+    /// `load()` reads the inputs recorded by the last build. Nothing is up to date if it was built
+    /// with other `options`, or with `force`.
+    pub(crate) async fn load(
+        config: &fastn_core::Config,
+        options: String,
+        force: bool,
+    ) -> BuildCache {
+        let content = tokio::fs::read(BuildCache::path(config)).await.ok();
+        BuildCache::parse(content.as_deref(), options, force)
+    }
+
+    fn parse(content: Option<&[u8]>, options: String, force: bool) -> BuildCache {
+        let cache = match content {
+            Some(content) if !force => serde_json::from_slice::<BuildCache>(content).ok(),
+            _ => None,
+        };
+        let options = format!("{} {}", env!("CARGO_PKG_VERSION"), options);
+        match cache {
+            Some(cache) if cache.options.eq(&options) => cache,
+            _ => BuildCache {
+                options,
+                outputs: Default::default(),
+            },
+        }
+    }
+
+    /// `is_fresh()` checks if `output` exists and none of its inputs changed since it was built.
+    pub(crate) fn is_fresh(&self, config: &fastn_core::Config, output: &str) -> bool {
+        self.is_fresh_in(&config.root, &config.build_dir(), output)
+    }
+
+    fn is_fresh_in(
+        &self,
+        root: &camino::Utf8Path,
+        build_dir: &camino::Utf8Path,
+        output: &str,
+    ) -> bool {
+        let inputs = match self.outputs.get(output) {
+            Some(inputs) if !inputs.volatile => inputs,
+            _ => return false,
+        };
+        if !build_dir.join(output).exists() {
+            return false;
+        }
+        inputs
+            .hashes
+            .iter()
+            .all(|(path, hash)| matches!(hash_file(&root.join(path)), Ok(h) if h.eq(hash)))
+    }
+
+    pub(crate) fn insert(&mut self, output: String, inputs: Inputs) {
+        self.outputs.insert(output, inputs);
+    }
+
+    pub(crate) fn remove(&mut self, output: &str) {
+        self.outputs.remove(output);
+    }
+
+    pub(crate) async fn save(&self, config: &fastn_core::Config) -> fastn_core::Result<()> {
+        fastn_core::utils::update(
+            BuildCache::path(config),
+            serde_json::to_string_pretty(self)?.as_bytes(),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    /// A package with `index.ftd`, which imports `lib.ftd` and reads `data.json` with a
+    /// processor, built once.
+    struct Package {
+        root: camino::Utf8PathBuf,
+    }
+
+    impl Package {
+        fn new(name: &str) -> Package {
+            let root: camino::Utf8PathBuf = std::env::temp_dir()
+                .join(format!("fastn-build-cache-{}-{}", name, std::process::id()))
+                .try_into()
+                .unwrap();
+            let _ = std::fs::remove_dir_all(&root);
+            std::fs::create_dir_all(root.join(".build")).unwrap();
+            let package = Package { root };
+            package.write("FASTN.ftd", "-- import: fastn\n\n-- fastn.package: foo\n");
+            package.write("index.ftd", "-- import: foo/lib\n\n-- ftd.text: hello\n");
+            package.write("lib.ftd", "-- string name: foo\n");
+            package.write("data.json", "[1, 2]");
+            package.write(".build/index.html", "<html></html>");
+            package
+        }
+
+        fn write(&self, path: &str, content: &str) {
+            std::fs::write(self.root.join(path), content).unwrap();
+        }
+
+        /// What building `index.ftd` records.
+        fn inputs(&self) -> super::Inputs {
+            let mut inputs = super::Inputs::default();
+            for path in ["FASTN.ftd", "index.ftd", "lib.ftd"] {
+                let content = std::fs::read(self.root.join(path)).unwrap();
+                inputs.file(&self.root, &self.root.join(path), &content);
+            }
+            inputs
+                .path(&self.root, &self.root.join("data.json"))
+                .unwrap();
+            inputs
+        }
+
+        fn built(&self, force: bool) -> super::BuildCache {
+            let mut cache = super::BuildCache::parse(None, "".to_string(), false);
+            cache.insert("index.html".to_string(), self.inputs());
+            let content = serde_json::to_vec(&cache).unwrap();
+            super::BuildCache::parse(Some(&content), "".to_string(), force)
+        }
+
+        fn is_fresh(&self, cache: &super::BuildCache) -> bool {
+            cache.is_fresh_in(&self.root, &self.root.join(".build"), "index.html")
+        }
+    }
+
+    impl Drop for Package {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.root);
+        }
+    }
+
+    #[test]
+    fn unchanged() {
+        let package = Package::new("unchanged");
+        let cache = package.built(false);
+        assert!(package.is_fresh(&cache));
+    }
+
+    #[test]
+    fn changed() {
+        for (path, content) in [
+            (
+                "index.ftd",
+                "-- import: foo/lib\n\n-- ftd.text: hello world\n",
+            ),
+            ("lib.ftd", "-- string name: bar baz\n"),
+            (
+                "FASTN.ftd",
+                "-- import: fastn\n\n-- fastn.package: foo\nzip: x\n",
+            ),
+            ("data.json", "[1, 2, 3]"),
+        ] {
+            let package = Package::new(path);
+            let cache = package.built(false);
+            package.write(path, content);
+            assert!(!package.is_fresh(&cache), "{} changed", path);
+        }
+    }
+
+    #[test]
+    fn removed_output() {
+        let package = Package::new("removed-output");
+        let cache = package.built(false);
+        std::fs::remove_file(package.root.join(".build/index.html")).unwrap();
+        assert!(!package.is_fresh(&cache));
+    }
+
+    #[test]
+    fn force() {
+        let package = Package::new("force");
+        assert!(!package.is_fresh(&package.built(true)));
+    }
+}
@@ -3,12 +3,34 @@ pub async fn build(
     file: Option<&str>,
     base_url: &str,
     ignore_failed: bool,
+    force: bool,
 ) -> fastn_core::Result<()> {
     fastn_core::utils::enable_parse_caching(true);
+    fastn_core::routes::check(config).await?;
 
     tokio::fs::create_dir_all(config.build_dir()).await?;
     let documents = get_documents_for_current_package(config).await?;
 
+    // only the documents of ftd 2022 record what they read, see `fastn_core::build_cache`
+    let incremental = config
+        .ftd_edition
+        .eq(&fastn_core::config::FTDEdition::FTD2022);
+    let mut cache = fastn_core::build_cache::BuildCache::load(
+        config,
+        format!(
+            "{} {:?} {:?} {:?} {:?} {:?}",
+            base_url,
+            config.ftd_edition,
+            config.ftd_external_js,
+            config.ftd_inline_js,
+            config.ftd_external_css,
+            config.ftd_inline_css
+        ),
+        force || !incremental,
+    )
+    .await;
+    let fastn_ftd = tokio::fs::read(config.root.join("FASTN.ftd")).await?;
+
     // No need to build static files when file is passed during fastn_core build (no-static behaviour)
     let no_static: bool = file.is_some();
 
@@ -49,12 +71,24 @@ pub async fn build(
                     );
                     continue;
                 }
+                let output = fastn_core::package::package_doc::output_path(doc.id.as_str());
+                if cache.is_fresh(config, output.as_str()) {
+                    println!("Skipped (up to date)");
+                    continue;
+                }
+                config.build_inputs.take();
                 let resp =
                     fastn_core::package::package_doc::process_ftd(config, doc, base_url, no_static)
                         .await;
                 match (resp, ignore_failed) {
-                    (Ok(_), _) => (),
+                    (Ok(_), _) => {
+                        let mut inputs = config.build_inputs.take();
+                        inputs.file(&config.root, &config.root.join("FASTN.ftd"), &fastn_ftd);
+                        inputs.file(&config.root, &main.get_full_path(), doc.content.as_bytes());
+                        cache.insert(output, inputs);
+                    }
                     (_, true) => {
+                        cache.remove(output.as_str());
                         println!("Failed");
                         continue;
                     }
@@ -145,12 +179,109 @@ pub async fn build(
         );
     }
 
+    build_dynamic_urls(
+        config,
+        file,
+        base_url,
+        ignore_failed,
+        &mut cache,
+        &fastn_ftd,
+    )
+    .await?;
+    if incremental {
+        cache.save(config).await?;
+    }
+
     if !no_static {
+        for (path, content, _) in fastn_core::seo::files(config) {
+            fastn_core::utils::update(config.build_dir().join(path), content.as_bytes()).await?;
+        }
+
         config.download_fonts().await?;
     }
     Ok(())
 }
 
+/// `build_dynamic_urls()` renders the pages of the dynamic urls that say where the values of their
+/// parameters come from, see `fastn_core::sitemap::DynamicUrls::build_urls()`.
+async fn build_dynamic_urls(
+    config: &mut fastn_core::Config,
+    file: Option<&str>,
+    base_url: &str,
+    ignore_failed: bool,
+    cache: &mut fastn_core::build_cache::BuildCache,
+    fastn_ftd: &[u8],
+) -> fastn_core::Result<()> {
+    let urls = match config.package.dynamic_urls.as_ref() {
+        Some(dynamic_urls) => dynamic_urls.build_urls(&config.root).await?,
+        None => return Ok(()),
+    };
+
+    for (url, document, path_params) in urls {
+        if file.is_some() && file != Some(document.as_str()) {
+            continue;
+        }
+        let start = std::time::Instant::now();
+        print!("Processing {}{} ... ", config.package.name.as_str(), url);
+
+        let mut doc = match fastn_core::get_file(
+            config.package.name.to_string(),
+            &config.root.join(document.as_str()),
+            &config.root,
+        )
+        .await?
+        {
+            fastn_core::File::Ftd(doc) => doc,
+            _ => {
+                return Err(fastn_core::Error::PackageError {
+                    message: format!("dynamic url {}: {} is not an ftd document", url, document),
+                })
+            }
+        };
+        doc.id = match url.trim_matches('/') {
+            "" => "index.ftd".to_string(),
+            url => format!("{}/index.ftd", url),
+        };
+        let output = fastn_core::package::package_doc::output_path(doc.id.as_str());
+        if cache.is_fresh(config, output.as_str()) {
+            println!("Skipped (up to date)");
+            continue;
+        }
+
+        config.current_document = Some(doc.id.to_string());
+        config.named_parameters = path_params;
+        config.build_inputs.take();
+        let resp =
+            fastn_core::package::package_doc::process_ftd(config, &doc, base_url, file.is_some())
+                .await;
+        config.named_parameters = vec![];
+        match (resp, ignore_failed) {
+            (Ok(_), _) => {
+                let mut inputs = config.build_inputs.take();
+                inputs.file(&config.root, &config.root.join("FASTN.ftd"), fastn_ftd);
+                inputs.file(
+                    &config.root,
+                    &config.root.join(document.as_str()),
+                    doc.content.as_bytes(),
+                );
+                cache.insert(output, inputs);
+            }
+            (_, true) => {
+                cache.remove(output.as_str());
+                println!("Failed");
+                continue;
+            }
+            (Err(e), _) => return Err(e),
+        }
+
+        fastn_core::utils::print_end(
+            format!("Processed {}{}", config.package.name.as_str(), url).as_str(),
+            start,
+        );
+    }
+    Ok(())
+}
+
 async fn get_documents_for_current_package(
     config: &mut fastn_core::Config,
 ) -> fastn_core::Result<std::collections::BTreeMap<String, fastn_core::File>> {
@@ -190,10 +321,8 @@ async fn get_documents_for_current_package(
             files.insert(file.get_id(), file);
         }
 
-        config
-            .all_packages
-            .borrow_mut()
-            .extend(new_config.all_packages.into_inner());
+        // `new_config` started with a copy of `all_packages`, so it has every package in it
+        *config.all_packages.borrow_mut() = new_config.all_packages.into_inner();
         documents.extend(files);
     }
 
@@ -0,0 +1,176 @@
+pub const COMMAND: &str = "checkout";
+
+pub fn command() -> clap::Command {
+    clap::Command::new(COMMAND)
+        .about("Restore a file, or the entire package, to a version from its history")
+        .arg(clap::arg!(version: <VERSION> "The version of the file, or, for the entire package, the time as `YYYY-MM-DD [HH:MM:SS]` (UTC)"))
+        .arg(clap::arg!(file: [FILE] "The file to restore (leave empty to restore the entire package)"))
+        .arg(clap::arg!(--force "Restore the files with local changes too, the changes are lost"))
+        .hide(true) // hidden since the feature is not being released yet.
+}
+
+pub async fn handle_command(matches: &clap::ArgMatches) -> fastn_core::Result<()> {
+    use fastn_core::utils::ValueOf;
+
+    let config = fastn_core::Config::read(None, true, None).await?;
+    let version = matches.value_of_("version").unwrap();
+    let force = matches.get_flag("force");
+    match matches.value_of_("file") {
+        Some(file) => checkout_file(&config, file, version.parse()?, force).await,
+        None => match fastn_core::history::parse_time(version) {
+            // the edits made in the given second are included
+            Some(timestamp) => checkout(&config, timestamp + 999_999_999, force).await,
+            None => fastn_core::usage_error(format!(
+                "`{}` is not a time, use `YYYY-MM-DD [HH:MM:SS]`",
+                version
+            )),
+        },
+    }
+}
+
+async fn checkout_file(
+    config: &fastn_core::Config,
+    file: &str,
+    version: i32,
+    force: bool,
+) -> fastn_core::Result<()> {
+    use itertools::Itertools;
+
+    let history = config.get_history().await?;
+    let file_edit = match history
+        .iter()
+        .find(|v| v.filename.eq(file))
+        .and_then(|v| v.file_edit(version))
+    {
+        Some(file_edit) => file_edit,
+        None => return fastn_core::usage_error(format!("{} has no version {}", file, version)),
+    };
+    let remote_manifest =
+        fastn_core::history::FileHistory::get_remote_manifest(history.as_slice(), true)?;
+    let mut workspace = config.get_workspace_map().await?;
+    if !force {
+        check_local_changes(config, &mut workspace, &[file]).await?;
+    }
+    restore(
+        config,
+        &mut workspace,
+        file,
+        Some(file_edit),
+        remote_manifest.get(file),
+    )
+    .await?;
+    config
+        .write_workspace(workspace.into_values().collect_vec().as_slice())
+        .await
+}
+
+/// `checkout()` restores every file of the package to its latest version at `timestamp`. The
+/// files of the CRs are left as they are.
+async fn checkout(
+    config: &fastn_core::Config,
+    timestamp: u128,
+    force: bool,
+) -> fastn_core::Result<()> {
+    use itertools::Itertools;
+
+    let history = config.get_history().await?;
+    let remote_manifest =
+        fastn_core::history::FileHistory::get_remote_manifest(history.as_slice(), true)?;
+    let mut workspace = config.get_workspace_map().await?;
+    let package_history = history
+        .iter()
+        .filter(|v| !(v.filename.starts_with("-/") || v.filename.starts_with(".tracks/")))
+        .collect_vec();
+    if !force {
+        let files = package_history
+            .iter()
+            .map(|v| v.filename.as_str())
+            .collect_vec();
+        check_local_changes(config, &mut workspace, files.as_slice()).await?;
+    }
+    for file_history in package_history {
+        restore(
+            config,
+            &mut workspace,
+            file_history.filename.as_str(),
+            file_history.file_edit_at(timestamp),
+            remote_manifest.get(&file_history.filename),
+        )
+        .await?;
+    }
+    config
+        .write_workspace(workspace.into_values().collect_vec().as_slice())
+        .await
+}
+
+/// `check_local_changes()` fails if any of `files` has changes which are not synced yet, as
+/// `fastn sync-status` shows them, which restoring the file would overwrite.
+async fn check_local_changes(
+    config: &fastn_core::Config,
+    workspace: &mut std::collections::BTreeMap<String, fastn_core::workspace::WorkspaceEntry>,
+    files: &[&str],
+) -> fastn_core::Result<()> {
+    use itertools::Itertools;
+
+    let changed = config
+        .get_files_status_with_workspace(workspace)
+        .await?
+        .into_iter()
+        .filter(|v| !matches!(v, fastn_core::sync_utils::FileStatus::Uptodate { .. }))
+        .map(|v| v.get_file_path())
+        .filter(|v| files.contains(&v.as_str()))
+        .collect_vec();
+    if changed.is_empty() {
+        return Ok(());
+    }
+    fastn_core::usage_error(format!(
+        "{} has local changes, which would be lost: sync them first, or use `--force`",
+        changed.join(", ")
+    ))
+}
+
+/// `restore()` brings `file` back to `file_edit`, or removes it if it was deleted, or not yet
+/// added, then. The restored file shows up as edited in `fastn status`, and becomes the latest
+/// version on the next `fastn sync`. The callers check that the file has no local changes,
+/// unless `--force` is used, see `check_local_changes()`.
+async fn restore(
+    config: &fastn_core::Config,
+    workspace: &mut std::collections::BTreeMap<String, fastn_core::workspace::WorkspaceEntry>,
+    file: &str,
+    file_edit: Option<&fastn_core::history::FileEdit>,
+    latest: Option<&fastn_core::history::FileEdit>,
+) -> fastn_core::Result<()> {
+    let path = config.root.join(file);
+    let latest_version = latest.filter(|v| !v.is_deleted()).map(|v| v.version);
+    let workspace_entry =
+        workspace
+            .entry(file.to_string())
+            .or_insert(fastn_core::workspace::WorkspaceEntry {
+                filename: file.to_string(),
+                deleted: None,
+                version: latest_version,
+                cr: fastn_core::cr::get_cr_path_from_url(file),
+            });
+
+    match file_edit.filter(|v| !v.is_deleted()) {
+        Some(file_edit) => {
+            fastn_core::utils::copy(config.history_path(file, file_edit.version), &path).await?;
+            workspace_entry.deleted = None;
+            if latest_version.is_none() {
+                // deleted in the latest version, so it is added again
+                workspace_entry.version = None;
+            }
+        }
+        None => {
+            if path.exists() {
+                tokio::fs::remove_file(&path).await?;
+            }
+            if latest_version.is_some() {
+                workspace_entry.set_deleted();
+            } else {
+                workspace.remove(file);
+            }
+        }
+    }
+    Ok(())
+}
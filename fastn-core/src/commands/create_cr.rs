@@ -1,4 +1,8 @@
-pub async fn create_cr(config: &fastn_core::Config, title: Option<&str>) -> fastn_core::Result<()> {
+pub async fn create_cr(
+    config: &fastn_core::Config,
+    title: Option<&str>,
+    reviewers: Option<&str>,
+) -> fastn_core::Result<()> {
     let cr_number = config.extract_cr_number().await?;
     let cr_meta_content = fastn_core::cr::generate_cr_meta_content(&fastn_core::cr::CRMeta {
         title: title
@@ -6,6 +10,7 @@ pub async fn create_cr(config: &fastn_core::Config, title: Option<&str>) -> fast
             .unwrap_or(format!("CR#{cr_number}")),
         cr_number: cr_number as usize,
         open: true,
+        reviewers: reviewers.map(ToString::to_string),
     });
     /*let cr_meta_content = fastn_core::cr::resolve_cr_meta(
         edit::edit(cr_meta_content)
@@ -0,0 +1,87 @@
+pub const COMMAND: &str = "log";
+
+pub fn command() -> clap::Command {
+    clap::Command::new(COMMAND)
+        .about("Show the history of the files in this fastn package")
+        .arg(clap::arg!(file: [FILE] "The file to see the history of (leave empty to see the history of entire package)"))
+        .arg(clap::arg!(-n --"max-count" <COUNT> "Show only the latest COUNT edits").required(false))
+        .hide(true) // hidden since the feature is not being released yet.
+}
+
+pub async fn handle_command(matches: &clap::ArgMatches) -> fastn_core::Result<()> {
+    use fastn_core::utils::ValueOf;
+
+    log(
+        &fastn_core::Config::read(None, true, None).await?,
+        matches.value_of_("file"),
+        matches
+            .value_of_("max-count")
+            .map(str::parse::<usize>)
+            .transpose()?,
+    )
+    .await
+}
+
+async fn log(
+    config: &fastn_core::Config,
+    file: Option<&str>,
+    max_count: Option<usize>,
+) -> fastn_core::Result<()> {
+    use colored::Colorize;
+
+    let history = config.get_history().await?;
+    let max_count = max_count.unwrap_or(usize::MAX);
+    if let Some(file) = file {
+        let file_history = match history.iter().find(|v| v.filename.eq(file)) {
+            Some(file_history) => file_history,
+            None => {
+                return Err(fastn_core::Error::UsageError {
+                    message: format!("{} has no history", file),
+                })
+            }
+        };
+        println!("{}", file_history.filename.bold());
+        // the latest edit is the first
+        for file_edit in file_history.file_edit.iter().take(max_count) {
+            print_file_edit(file_edit, None);
+        }
+        return Ok(());
+    }
+
+    // the edits of all the files, the latest first
+    let mut edits = history
+        .iter()
+        .flat_map(|v| v.file_edit.iter().map(|e| (v.filename.as_str(), e)))
+        .collect::<Vec<_>>();
+    edits.sort_by(|(f1, e1), (f2, e2)| e2.timestamp.cmp(&e1.timestamp).then(f1.cmp(f2)));
+    for (filename, file_edit) in edits.into_iter().take(max_count) {
+        print_file_edit(file_edit, Some(filename));
+    }
+    Ok(())
+}
+
+fn print_file_edit(file_edit: &fastn_core::history::FileEdit, filename: Option<&str>) {
+    use colored::Colorize;
+
+    let filename = filename
+        .map(|f| format!(" {}", f.bold()))
+        .unwrap_or_default();
+    let src_cr = file_edit
+        .src_cr
+        .map(|cr| format!(" (CR #{})", cr))
+        .unwrap_or_default();
+    println!(
+        "  {}{} {:?}{} {} by {}",
+        format!("v{}", file_edit.version).yellow(),
+        filename,
+        file_edit.operation,
+        src_cr,
+        file_edit.time(),
+        file_edit.author.as_deref().unwrap_or("unknown")
+    );
+    if let Some(message) = file_edit.message.as_ref().filter(|m| !m.trim().is_empty()) {
+        for line in message.trim().lines() {
+            println!("      {}", line);
+        }
+    }
+}
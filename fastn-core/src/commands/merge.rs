@@ -22,12 +22,355 @@ pub async fn merge(
 }
 
 async fn merge_cr_into_cr(
-    _config: &fastn_core::Config,
-    _src: usize,
-    _dest: usize,
-    _file: Option<&str>,
+    config: &fastn_core::Config,
+    src: usize,
+    dest: usize,
+    file: Option<&str>,
 ) -> fastn_core::Result<()> {
-    unimplemented!()
+    use itertools::Itertools;
+    use sha2::Digest;
+
+    if src.eq(&dest) {
+        return fastn_core::usage_error(format!("Can't merge CR#{} into itself", src));
+    }
+    if !fastn_core::cr::is_open_cr_exists(config, dest).await? {
+        return fastn_core::usage_error(format!("CR#{} is closed", dest));
+    }
+
+    let (src_track_manifest, src_file_manifest) = split_cr_manifest(config, src).await?;
+    let (dest_track_manifest, dest_file_manifest) = split_cr_manifest(config, dest).await?;
+    let src_meta_files = [
+        config.path_without_root(&config.cr_meta_path(src))?,
+        config.path_without_root(&config.cr_about_path(src))?,
+        config.path_without_root(&config.cr_review_path(src))?,
+    ];
+    let src_deleted_file = config.path_without_root(&config.cr_deleted_file_path(src))?;
+    let dest_deleted_file = config.path_without_root(&config.cr_deleted_file_path(dest))?;
+
+    let mut conflicted_file_status = vec![];
+    let mut new_file_status: std::collections::BTreeMap<
+        String,
+        fastn_core::sync_utils::FileStatus,
+    > = Default::default();
+
+    for (src_file_path, src_file_edit) in src_file_manifest.iter() {
+        if src_file_edit.is_deleted() || src_meta_files.contains(src_file_path) {
+            continue;
+        }
+
+        if src_file_path.eq(&src_deleted_file) {
+            // the files deleted in src are deleted in dest too, unless dest edits them
+            let src_deleted_list = fastn_core::cr::resolve_cr_deleted(
+                tokio::fs::read_to_string(
+                    config.history_path(src_file_path.as_str(), src_file_edit.version),
+                )
+                .await?
+                .as_str(),
+                src,
+            )
+            .await?;
+            let dest_deleted_file_edit = dest_file_manifest
+                .get(&dest_deleted_file)
+                .filter(|v| !v.is_deleted());
+            let mut dest_deleted_list = match dest_deleted_file_edit {
+                Some(file_edit) => {
+                    fastn_core::cr::resolve_cr_deleted(
+                        tokio::fs::read_to_string(
+                            config.history_path(dest_deleted_file.as_str(), file_edit.version),
+                        )
+                        .await?
+                        .as_str(),
+                        dest,
+                    )
+                    .await?
+                }
+                None => vec![],
+            };
+            let mut changed = false;
+            for src_deleted in src_deleted_list {
+                if file.map(|f| src_deleted.filename.ne(f)).unwrap_or(false)
+                    || dest_deleted_list
+                        .iter()
+                        .any(|v| v.filename.eq(&src_deleted.filename))
+                {
+                    continue;
+                }
+                let dest_file_path =
+                    format!("{}/{}", fastn_core::cr::cr_path(dest), src_deleted.filename);
+                if let Some(dest_file_edit) = dest_file_manifest
+                    .get(&dest_file_path)
+                    .filter(|v| !v.is_deleted())
+                {
+                    conflicted_file_status.push(fastn_core::sync_utils::FileStatus::Delete {
+                        path: dest_file_path,
+                        version: src_deleted.version,
+                        status: fastn_core::sync_utils::Status::CloneDeletedRemoteEdited(
+                            dest_file_edit.version,
+                        ),
+                    });
+                    continue;
+                }
+                dest_deleted_list.push(src_deleted);
+                changed = true;
+            }
+            if changed {
+                let content =
+                    fastn_core::cr::generate_deleted_files_content(dest_deleted_list.as_slice())
+                        .into_bytes();
+                new_file_status.insert(
+                    dest_deleted_file.to_string(),
+                    match dest_deleted_file_edit {
+                        Some(file_edit) => fastn_core::sync_utils::FileStatus::Update {
+                            path: dest_deleted_file.to_string(),
+                            content,
+                            version: file_edit.version,
+                            status: fastn_core::sync_utils::Status::NoConflict,
+                        },
+                        None => fastn_core::sync_utils::FileStatus::Add {
+                            path: dest_deleted_file.to_string(),
+                            content,
+                            status: fastn_core::sync_utils::Status::NoConflict,
+                        },
+                    },
+                );
+            }
+            continue;
+        }
+
+        let filename = fastn_core::cr::cr_path_to_file_name(src, src_file_path.as_str())?
+            .trim_start_matches('/')
+            .to_string();
+        if file.map(|f| filename.ne(f)).unwrap_or(false) {
+            continue;
+        }
+        let dest_file_path = format!("{}/{}", fastn_core::cr::cr_path(dest), filename);
+        let dest_track_file_path =
+            config.path_without_root(&config.track_path(&config.root.join(&dest_file_path)))?;
+
+        let theirs_content_bytes =
+            tokio::fs::read(config.history_path(src_file_path.as_str(), src_file_edit.version))
+                .await?;
+        let src_track_info = get_cr_track_info(
+            config,
+            &src_track_manifest,
+            src_file_path.as_str(),
+            filename.as_str(),
+        )
+        .await?;
+        let dest_track_info = get_cr_track_info(
+            config,
+            &dest_track_manifest,
+            dest_file_path.as_str(),
+            filename.as_str(),
+        )
+        .await?;
+
+        let dest_file_edit = match dest_file_manifest
+            .get(&dest_file_path)
+            .filter(|v| !v.is_deleted())
+        {
+            Some(file_edit) => file_edit,
+            None => {
+                // Not in dest yet, so it gets src's copy, based on the same version of main
+                new_file_status.insert(
+                    dest_file_path.to_string(),
+                    fastn_core::sync_utils::FileStatus::Add {
+                        path: dest_file_path.to_string(),
+                        content: theirs_content_bytes,
+                        status: fastn_core::sync_utils::Status::NoConflict,
+                    },
+                );
+                if let Some(src_track_info) = src_track_info {
+                    let content = fastn_core::track::generate_tracking_info_content(&[
+                        fastn_core::track::TrackingInfo::new(
+                            filename.as_str(),
+                            src_track_info.version,
+                            None,
+                        ),
+                    ])
+                    .into_bytes();
+                    new_file_status.insert(
+                        dest_track_file_path.to_string(),
+                        track_file_status(
+                            dest_track_file_path.as_str(),
+                            content,
+                            dest_track_manifest.get(&dest_track_file_path),
+                        ),
+                    );
+                }
+                continue;
+            }
+        };
+
+        let ours_content_bytes =
+            tokio::fs::read(config.history_path(dest_file_path.as_str(), dest_file_edit.version))
+                .await?;
+        if sha2::Sha256::digest(&ours_content_bytes)
+            .eq(&sha2::Sha256::digest(&theirs_content_bytes))
+        {
+            continue;
+        }
+
+        let (src_track_info, dest_track_info) = match (src_track_info, dest_track_info) {
+            (Some(src_track_info), Some(dest_track_info)) => (src_track_info, dest_track_info),
+            _ => {
+                // Added in one or both of the CRs, there is nothing to merge against
+                conflicted_file_status.push(fastn_core::sync_utils::FileStatus::Add {
+                    path: dest_file_path.to_string(),
+                    content: theirs_content_bytes,
+                    status: fastn_core::sync_utils::Status::CloneAddedRemoteAdded(
+                        dest_file_edit.version,
+                    ),
+                });
+                continue;
+            }
+        };
+
+        // src's changes are made on the version of main it tracks
+        let ancestor_content = if let Ok(content) = tokio::fs::read_to_string(
+            config.history_path(filename.as_str(), src_track_info.version),
+        )
+        .await
+        {
+            content
+        } else {
+            // binary file like images, can't resolve conflict
+            conflicted_file_status.push(fastn_core::sync_utils::FileStatus::Update {
+                path: dest_file_path.to_string(),
+                content: theirs_content_bytes,
+                version: dest_file_edit.version,
+                status: fastn_core::sync_utils::Status::Conflict(dest_file_edit.version),
+            });
+            continue;
+        };
+        let ours_content = String::from_utf8(ours_content_bytes)?;
+        let theirs_content = String::from_utf8(theirs_content_bytes.clone())?;
+
+        match fastn_core::ftd_merge::merge(
+            filename.as_str(),
+            &ancestor_content,
+            &ours_content,
+            &theirs_content,
+        ) {
+            Ok(data) => {
+                new_file_status.insert(
+                    dest_file_path.to_string(),
+                    fastn_core::sync_utils::FileStatus::Update {
+                        path: dest_file_path.to_string(),
+                        content: data.into_bytes(),
+                        version: dest_file_edit.version,
+                        status: fastn_core::sync_utils::Status::NoConflict,
+                    },
+                );
+                // the merged content has the changes of main up to the newer of the two
+                if src_track_info.version.gt(&dest_track_info.version) {
+                    let content = fastn_core::track::generate_tracking_info_content(&[
+                        fastn_core::track::TrackingInfo::new(
+                            filename.as_str(),
+                            src_track_info.version,
+                            dest_track_info.self_version,
+                        ),
+                    ])
+                    .into_bytes();
+                    new_file_status.insert(
+                        dest_track_file_path.to_string(),
+                        track_file_status(
+                            dest_track_file_path.as_str(),
+                            content,
+                            dest_track_manifest.get(&dest_track_file_path),
+                        ),
+                    );
+                }
+            }
+            Err(_) => {
+                // Can't resolve conflict
+                conflicted_file_status.push(fastn_core::sync_utils::FileStatus::Update {
+                    path: dest_file_path.to_string(),
+                    content: theirs_content_bytes,
+                    version: dest_file_edit.version,
+                    status: fastn_core::sync_utils::Status::Conflict(dest_file_edit.version),
+                });
+                continue;
+            }
+        }
+    }
+
+    conflicted_file_status
+        .iter()
+        .map(|v| fastn_core::commands::sync_status::print_status(v, false))
+        .collect_vec();
+
+    if conflicted_file_status.is_empty() {
+        let changed_files = new_file_status
+            .into_values()
+            .filter_map(|v| v.sync_request(None))
+            .collect_vec();
+        fastn_core::apis::sync2::do_sync(
+            config,
+            changed_files.as_slice(),
+            Some(format!("Merge CR #{} into CR #{}", src, dest).as_str()),
+            None,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// `split_cr_manifest()` returns the track files and the other files of the CR, by path.
+async fn split_cr_manifest(
+    config: &fastn_core::Config,
+    cr: usize,
+) -> fastn_core::Result<(
+    std::collections::HashMap<String, fastn_core::history::FileEdit>,
+    std::collections::HashMap<String, fastn_core::history::FileEdit>,
+)> {
+    Ok(config
+        .get_cr_manifest(cr)
+        .await?
+        .into_iter()
+        .partition(|(file, _)| file.starts_with(".tracks/")))
+}
+
+/// `get_cr_track_info()` returns the tracking info of the CR's copy of `filename`, `None` if
+/// the file was added in the CR.
+async fn get_cr_track_info(
+    config: &fastn_core::Config,
+    cr_track_manifest: &std::collections::HashMap<String, fastn_core::history::FileEdit>,
+    cr_file_path: &str,
+    filename: &str,
+) -> fastn_core::Result<Option<fastn_core::track::TrackingInfo>> {
+    let track_file_path_str =
+        config.path_without_root(&config.track_path(&config.root.join(cr_file_path)))?;
+    let track_file_edit = match cr_track_manifest.get(&track_file_path_str) {
+        Some(file_edit) if !file_edit.is_deleted() => file_edit,
+        _ => return Ok(None),
+    };
+    Ok(fastn_core::track::get_tracking_info_(
+        &config.history_path(track_file_path_str.as_str(), track_file_edit.version),
+    )
+    .await?
+    .into_iter()
+    .find(|v| v.filename.eq(filename)))
+}
+
+fn track_file_status(
+    path: &str,
+    content: Vec<u8>,
+    file_edit: Option<&fastn_core::history::FileEdit>,
+) -> fastn_core::sync_utils::FileStatus {
+    match file_edit.filter(|v| !v.is_deleted()) {
+        Some(file_edit) => fastn_core::sync_utils::FileStatus::Update {
+            path: path.to_string(),
+            content,
+            version: file_edit.version,
+            status: fastn_core::sync_utils::Status::NoConflict,
+        },
+        None => fastn_core::sync_utils::FileStatus::Add {
+            path: path.to_string(),
+            content,
+            status: fastn_core::sync_utils::Status::NoConflict,
+        },
+    }
 }
 
 async fn merge_main_into_cr(
@@ -275,10 +618,12 @@ async fn merge_main_into_cr(
 
         let ours_content = String::from_utf8(ours_content_bytes.clone())?;
 
-        match diffy::MergeOptions::new()
-            .set_conflict_style(diffy::ConflictStyle::Merge)
-            .merge(&ancestor_content, &ours_content, &theirs_content)
-        {
+        match fastn_core::ftd_merge::merge(
+            filename.as_str(),
+            &ancestor_content,
+            &ours_content,
+            &theirs_content,
+        ) {
             Ok(data) => {
                 new_file_status.insert(
                     cr_file_path.to_string(),
@@ -325,7 +670,13 @@ async fn merge_main_into_cr(
             .into_values()
             .filter_map(|v| v.sync_request(None))
             .collect_vec();
-        fastn_core::apis::sync2::do_sync(config, changed_files.as_slice()).await?;
+        fastn_core::apis::sync2::do_sync(
+            config,
+            changed_files.as_slice(),
+            Some(format!("Merge main into CR #{}", dest).as_str()),
+            None,
+        )
+        .await?;
     }
     Ok(())
 }
@@ -339,6 +690,7 @@ async fn merge_cr_into_main(
     use sha2::Digest;
 
     //TODO: check if cr is closed
+    fastn_core::cr_review::check_approvals(config, src).await?;
     let remote_manifest: std::collections::BTreeMap<String, fastn_core::history::FileEdit> = config
         .get_remote_manifest(true)
         .await?
@@ -614,10 +966,12 @@ async fn merge_cr_into_main(
 
         let ours_content = String::from_utf8(ours_content_bytes.clone())?;
 
-        match diffy::MergeOptions::new()
-            .set_conflict_style(diffy::ConflictStyle::Merge)
-            .merge(&ancestor_content, &ours_content, &theirs_content)
-        {
+        match fastn_core::ftd_merge::merge(
+            filename.as_str(),
+            &ancestor_content,
+            &ours_content,
+            &theirs_content,
+        ) {
             Ok(data) => {
                 new_file_status.insert(
                     filename.to_string(),
@@ -659,7 +1013,13 @@ async fn merge_cr_into_main(
                 sync_request_files.push(sync_req);
             }
         }
-        fastn_core::apis::sync2::do_sync(config, sync_request_files.as_slice()).await?;
+        fastn_core::apis::sync2::do_sync(
+            config,
+            sync_request_files.as_slice(),
+            Some(format!("Merge CR #{} into main", src).as_str()),
+            None,
+        )
+        .await?;
     }
 
     Ok(())
@@ -1,19 +1,24 @@
 pub mod abort_merge;
 pub mod add;
 pub mod build;
+pub mod checkout;
 pub mod clone;
 pub mod close_cr;
 pub mod create_cr;
 pub mod create_package;
 pub mod diff;
 pub mod edit;
+pub mod log;
 pub mod mark_resolved;
 pub mod mark_upto_date;
 pub mod merge;
 pub mod resolve_conflict;
 pub mod revert;
+pub mod review;
 pub mod rm;
+pub mod routes;
 pub mod serve;
+pub mod show;
 pub mod start_tracking;
 pub mod status;
 pub mod stop_tracking;
@@ -241,10 +241,12 @@ async fn get_conflict_data(
                 let ancestor_path = config.history_path(path, *version);
                 let ancestor_content = tokio::fs::read(ancestor_path).await?;
                 let ancestor_string = String::from_utf8(ancestor_content)?;
-                match diffy::MergeOptions::new()
-                    .set_conflict_style(diffy::ConflictStyle::Merge)
-                    .merge(&ancestor_string, &ours_string, &theirs_string)
-                {
+                match fastn_core::ftd_merge::merge(
+                    path,
+                    &ancestor_string,
+                    &ours_string,
+                    &theirs_string,
+                ) {
                     Ok(data) => {
                         // Not possible to reach here
                         tokio::fs::write(path, &data).await?;
@@ -0,0 +1,76 @@
+pub const COMMAND: &str = "review";
+
+pub fn command() -> clap::Command {
+    clap::Command::new(COMMAND)
+        .about("Show the reviews of a CR, which are made on its page, `/-/cr/<cr>/`")
+        .arg(clap::arg!(cr: <CR> "The CR to show the reviews of"))
+        .hide(true) // hidden since the feature is not being released yet.
+}
+
+pub async fn handle_command(matches: &clap::ArgMatches) -> fastn_core::Result<()> {
+    use fastn_core::utils::ValueOf;
+
+    let config = fastn_core::Config::read(None, true, None).await?;
+    let cr = matches.value_of_("cr").unwrap().parse::<usize>()?;
+    show_review(&config, cr).await
+}
+
+async fn show_review(config: &fastn_core::Config, cr: usize) -> fastn_core::Result<()> {
+    use colored::Colorize;
+
+    let cr_meta = fastn_core::cr::get_cr_meta(config, cr).await?;
+    let cr_review = fastn_core::cr_review::get_cr_review(config, cr).await?;
+
+    println!("{}", format!("CR#{}: {}", cr, cr_meta.title).bold());
+    if let Some(ref reviewers) = cr_meta.reviewers {
+        println!("Reviewers: {}", reviewers);
+    }
+    println!();
+    for review in cr_review.reviews.iter() {
+        let status = match review.status {
+            fastn_core::cr_review::ReviewStatus::Approved => "approved".green(),
+            fastn_core::cr_review::ReviewStatus::ChangesRequested => "requested changes".red(),
+        };
+        println!(
+            "{} {} on {}",
+            review.reviewer,
+            status,
+            fastn_core::history::time(review.timestamp as u128)
+        );
+        print_message(review.message.as_deref());
+    }
+    for comment in cr_review.comments.iter() {
+        let at = match (comment.line, comment.section.as_ref()) {
+            (Some(line), _) => format!("{}:{}", comment.file, line),
+            (None, Some(section)) => format!("{} ({})", comment.file, section),
+            (None, None) => comment.file.to_string(),
+        };
+        println!(
+            "{} commented on {} on {}",
+            comment.author,
+            at.yellow(),
+            fastn_core::history::time(comment.timestamp as u128)
+        );
+        print_message(Some(comment.message.as_str()));
+    }
+
+    let pending = fastn_core::cr_review::pending_approvals(config, cr).await?;
+    if pending.is_empty() {
+        println!("{}", "Approved".green());
+    } else {
+        println!("{}", "Pending approval:".red());
+        for file in pending {
+            println!("  {}", file);
+        }
+    }
+    Ok(())
+}
+
+fn print_message(message: Option<&str>) {
+    if let Some(message) = message.filter(|m| !m.trim().is_empty()) {
+        for line in message.trim().lines() {
+            println!("    {}", line);
+        }
+    }
+    println!();
+}
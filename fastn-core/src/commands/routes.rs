@@ -0,0 +1,46 @@
+pub const COMMAND: &str = "routes";
+
+pub fn command() -> clap::Command {
+    clap::Command::new(COMMAND)
+        .about("Show the urls this fastn package serves, in the order they are matched")
+}
+
+pub async fn handle_command(_matches: &clap::ArgMatches) -> fastn_core::Result<()> {
+    routes(&fastn_core::Config::read(None, true, None).await?).await
+}
+
+async fn routes(config: &fastn_core::Config) -> fastn_core::Result<()> {
+    use colored::Colorize;
+
+    let table = fastn_core::routes::RouteTable::build(config).await?;
+    let width = table
+        .routes
+        .iter()
+        .map(|route| route.url.len())
+        .max()
+        .unwrap_or_default();
+    for route in table.routes.iter() {
+        println!(
+            "{:width$}  {:8}  {}",
+            route.url,
+            route.kind.to_string(),
+            route.target,
+            width = width
+        );
+    }
+
+    for conflict in table.conflicts.iter() {
+        if conflict.error {
+            println!("{}: {}", "Error".red(), conflict.message);
+        } else {
+            println!("{}: {}", "Warning".yellow(), conflict.message);
+        }
+    }
+
+    if table.has_errors() {
+        return Err(fastn_core::Error::PackageError {
+            message: "conflicting routes".to_string(),
+        });
+    }
+    Ok(())
+}
@@ -156,6 +156,47 @@ async fn serve_cr_file(
     }
 }
 
+/// path: /-/<package-name>/backend.wasm
+///
+/// `backend.wasm` of a dependency package is downloaded in `.packages` the first time it is
+/// needed, after that `fastn_core::wasm` keeps the compiled module around.
+#[tracing::instrument(skip_all)]
+async fn serve_wasm(
+    config: &mut fastn_core::Config,
+    package: &fastn_core::Package,
+) -> fastn_core::http::Response {
+    let wasm_module_path = format!("-/{}/backend.wasm", package.name);
+    if let Err(e) = config
+        .get_file_and_package_by_id(wasm_module_path.as_str())
+        .await
+    {
+        tracing::error!(
+            msg = "backend.wasm not found",
+            package = package.name.as_str(),
+            error = %e
+        );
+        return fastn_core::not_found!(
+            "fastn-Error: backend.wasm not found for {}, {:?}",
+            package.name,
+            e
+        );
+    }
+    let wasm_module = config.get_root_for_package(package).join("backend.wasm");
+
+    let req = if let Some(ref r) = config.request {
+        r.clone()
+    } else {
+        return fastn_core::server_error!("request not set");
+    };
+
+    // limits of the package being served also apply to the backends of its dependencies
+    let limits = package
+        .backend_limits
+        .restrict(&config.package.backend_limits);
+
+    fastn_core::wasm::handle_wasm(req, package, limits, wasm_module).await
+}
+
 fn guess_mime_type(path: &str) -> mime_guess::Mime {
     mime_guess::from_path(path).first_or_octet_stream()
 }
@@ -213,9 +254,8 @@ pub async fn serve(
     let response = if path.eq(&favicon) {
         static_file(favicon).await
     } else if path.eq(&camino::Utf8PathBuf::new().join("FASTN.ftd")) {
-        let config = fastn_core::Config::read(None, false, Some(&req))
-            .await
-            .unwrap()
+        let config = fastn_core::config::cache::read(&req)
+            .await?
             .add_edition(edition)?
             .add_external_js(external_js)
             .add_inline_js(inline_js)
@@ -223,11 +263,9 @@ pub async fn serve(
             .add_inline_css(inline_css);
         serve_fastn_file(&config).await
     } else if path.eq(&camino::Utf8PathBuf::new().join("")) {
-        let mut config = fastn_core::Config::read(None, false, Some(&req))
-            .await
-            .unwrap()
+        let mut config = fastn_core::config::cache::read(&req)
+            .await?
             .add_edition(edition)?
-            .set_request(req)
             .add_external_js(external_js)
             .add_inline_js(inline_js)
             .add_external_css(external_css)
@@ -235,9 +273,8 @@ pub async fn serve(
 
         serve_file(&mut config, &path.join("/")).await
     } else if let Some(cr_number) = fastn_core::cr::get_cr_path_from_url(path.as_str()) {
-        let mut config = fastn_core::Config::read(None, false, Some(&req))
-            .await
-            .unwrap()
+        let mut config = fastn_core::config::cache::read(&req)
+            .await?
             .add_edition(edition)?
             .add_external_js(external_js)
             .add_inline_js(inline_js)
@@ -250,15 +287,23 @@ pub async fn serve(
 
         let req_method = req.method().to_string();
         let query_string = req.query_string().to_string();
-        let mut config = fastn_core::Config::read(None, false, Some(&req))
-            .await
-            .unwrap()
+        let mut config = fastn_core::config::cache::read(&req)
+            .await?
             .add_edition(edition)?
             .add_external_js(external_js)
             .add_inline_js(inline_js)
             .add_external_css(external_css)
-            .add_inline_css(inline_css)
-            .set_request(req);
+            .add_inline_css(inline_css);
+
+        // `sitemap.xml`, `robots.txt` and feeds, if the package does not have them
+        if req_method.as_str() == "GET" {
+            if let Some((content, content_type)) = fastn_core::seo::file(&config, path.as_str()) {
+                return Ok(fastn_core::http::ok_with_content_type(
+                    content.into_bytes(),
+                    content_type,
+                ));
+            }
+        }
 
         // if start with -/ and mount-point exists so send redirect to mount-point
         // We have to do -/<package-name>/remaining-url/ ==> (<package-name>, remaining-url) ==> (/config.package-name.mount-point/remaining-url/)
@@ -320,78 +365,140 @@ pub async fn serve(
             &path
         );
         if file_response.status() == actix_web::http::StatusCode::NOT_FOUND {
+            // Packages with `backend: true` handle everything that is not in their sitemap
+            // themselves, using their `backend.wasm`
+            let package = config.find_package_by_id(path.as_str()).await?.1;
+            if package.backend {
+                tracing::info!("executing wasm backend: path: {}", &path);
+                return Ok(serve_wasm(&mut config, &package).await);
+            }
+
             // TODO: Check if path exists in dynamic urls also, otherwise pass to endpoint
             // Already checked in the above method serve_file
             tracing::info!("executing proxy: path: {}", &path);
-            let (package_name, url, mut conf) =
-                fastn_core::config::utils::get_clean_url(&config, path.as_str())?;
-            let package_name = package_name.unwrap_or_else(|| config.package.name.to_string());
-
-            let host = if let Some(port) = url.port() {
-                format!("{}://{}:{}", url.scheme(), url.host_str().unwrap(), port)
-            } else {
-                format!("{}://{}", url.scheme(), url.host_str().unwrap())
+            let endpoint = match endpoint(&config, path.as_str()).await? {
+                Ok(endpoint) => endpoint,
+                Err(response) => return Ok(response),
             };
-            let req = if let Some(r) = config.request {
-                r
-            } else {
-                tracing::error!(msg = "request not set");
-                return Ok(fastn_core::server_error!("request not set"));
-            };
-
-            // TODO: read app config and send them to service as header
-            // Adjust x-fastn header from based on the platform and the requested field
-            if let Some(user_id) = conf.get("user-id") {
-                match user_id.split_once('-') {
-                    Some((platform, requested_field)) => {
-                        if let Some(user_data) = fastn_core::auth::get_user_data_from_cookies(
-                            platform,
-                            requested_field,
-                            req.cookies(),
-                        )
-                        .await?
-                        {
-                            conf.insert("X-FASTN-USER-ID".to_string(), user_data);
-                        }
-                    }
-                    _ => return Ok(fastn_core::unauthorised!("invalid user-id provided")),
-                }
-            }
 
             return fastn_core::proxy::get_out(
-                host.as_str(),
-                req,
-                url.path(),
-                package_name.as_str(),
-                &conf,
+                endpoint.host.as_str(),
+                endpoint.req,
+                endpoint.path.as_str(),
+                endpoint.mount_point.as_str(),
+                &endpoint.headers,
+                &endpoint.policy,
             )
             .await;
         }
 
-        // Fallback to WASM execution in case of no successful response
-        // TODO: This is hacky. Use the sitemap eventually.
-        // if file_response.status() == actix_web::http::StatusCode::NOT_FOUND {
-        //     let package = config.find_package_by_id(path.as_str()).await.unwrap().1;
-        //     let wasm_module = config.get_root_for_package(&package).join("backend.wasm");
-        //     // Set the temp path to and do `get_file_and_package_by_id` to retrieve the backend.wasm
-        //     // File for the particular dependency package
-        //     let wasm_module_path = format!("-/{}/backend.wasm", package.name,);
-        //     config
-        //         .get_file_and_package_by_id(wasm_module_path.as_str())
-        //         .await?;
-        //     let req = if let Some(r) = config.request {
-        //         r
-        //     } else {
-        //         return Ok(fastn_core::server_error!("request not set"));
-        //     };
-        //     fastn_core::wasm::handle_wasm(req, wasm_module, config.package.backend_headers).await
-        // }
-
         file_response
     };
     Ok(response)
 }
 
+/// Where a request that is not served by fastn is passed on to: the package or `fastn#app`
+/// endpoint under whose mount point `path` is.
+struct Endpoint {
+    host: String,
+    path: String,
+    /// Where the endpoint is on the fastn site, see `ResponseBuilder::from_reqwest()`
+    mount_point: String,
+    req: fastn_core::http::Request,
+    /// The headers sent to the endpoint along with the request ones, like `X-FASTN-USER-ID`.
+    headers: std::collections::HashMap<String, String>,
+    policy: fastn_core::package::EndpointPolicy,
+}
+
+/// `endpoint()` finds the endpoint for `path`, or the response to send back if the request
+/// can not be passed on, like when the user is not one of the readers of the app.
+async fn endpoint(
+    config: &fastn_core::Config,
+    path: &str,
+) -> fastn_core::Result<Result<Endpoint, fastn_core::http::Response>> {
+    let (package_name, url, mut conf) = fastn_core::config::utils::get_clean_url(config, path)?;
+    let package_name = package_name.unwrap_or_else(|| config.package.name.to_string());
+
+    let host = if let Some(port) = url.port() {
+        format!("{}://{}:{}", url.scheme(), url.host_str().unwrap(), port)
+    } else {
+        format!("{}://{}", url.scheme(), url.host_str().unwrap())
+    };
+    let req = if let Some(r) = config.request.clone() {
+        r
+    } else {
+        tracing::error!(msg = "request not set");
+        return Ok(Err(fastn_core::server_error!("request not set")));
+    };
+
+    if !fastn_core::package::app::can_read(config, path).await? {
+        return Ok(Err(fastn_core::unauthorised!(
+            "You are unauthorized to access: {}",
+            path
+        )));
+    }
+
+    // TODO: read app config and send them to service as header
+    // Adjust x-fastn header from based on the platform and the requested field
+    if let Some(user_id) = conf.get("user-id") {
+        match user_id.split_once('-') {
+            Some((platform, requested_field)) => {
+                if let Some(user_data) = fastn_core::auth::get_user_data_from_cookies(
+                    platform,
+                    requested_field,
+                    req.cookies(),
+                )
+                .await?
+                {
+                    conf.insert("X-FASTN-USER-ID".to_string(), user_data);
+                }
+            }
+            _ => return Ok(Err(fastn_core::unauthorised!("invalid user-id provided"))),
+        }
+    }
+
+    Ok(Ok(Endpoint {
+        host,
+        path: url.path().to_string(),
+        policy: fastn_core::config::utils::endpoint_policy(config, package_name.as_str()),
+        mount_point: fastn_core::config::utils::endpoint_mount_point(config, package_name.as_str()),
+        req,
+        headers: conf,
+    }))
+}
+
+/// `websocket()` tunnels a WebSocket connection to the endpoint of its path, the way `serve()`
+/// passes on other requests that fastn does not serve itself.
+#[tracing::instrument(skip_all)]
+async fn websocket(
+    req: actix_web::HttpRequest,
+    payload: actix_web::web::Payload,
+) -> fastn_core::Result<fastn_core::http::Response> {
+    let endpoint = {
+        let _lock = LOCK.read().await;
+        let request = fastn_core::http::Request::from_actix(req.clone(), Default::default());
+        let config = fastn_core::config::cache::read(&request).await?;
+        let path = req.path().trim_start_matches('/');
+        tracing::info!("executing websocket proxy: path: {}", path);
+        match endpoint(&config, path).await? {
+            Ok(endpoint) => endpoint,
+            Err(response) => return Ok(response),
+        }
+    };
+
+    fastn_core::proxy::websocket(
+        endpoint.host.as_str(),
+        endpoint.req,
+        endpoint.path.as_str(),
+        endpoint.mount_point.as_str(),
+        &endpoint.headers,
+        &endpoint.policy,
+        &req,
+        payload,
+    )
+    .await
+}
+
 pub(crate) async fn download_init_package(url: Option<String>) -> std::io::Result<()> {
     let mut package = fastn_core::Package::new("unknown-package");
     package.download_base_url = url;
@@ -411,24 +518,6 @@ pub(crate) async fn download_init_package(url: Option<String>) -> std::io::Resul
 pub async fn clear_cache(
     req: fastn_core::http::Request,
 ) -> fastn_core::Result<fastn_core::http::Response> {
-    fn is_login(req: &fastn_core::http::Request) -> bool {
-        // TODO: Need refactor not happy with this
-        req.cookie(fastn_core::auth::AuthProviders::GitHub.as_str())
-            .is_some()
-            || req
-                .cookie(fastn_core::auth::AuthProviders::TeleGram.as_str())
-                .is_some()
-            || req
-                .cookie(fastn_core::auth::AuthProviders::Discord.as_str())
-                .is_some()
-            || req
-                .cookie(fastn_core::auth::AuthProviders::Slack.as_str())
-                .is_some()
-            || req
-                .cookie(fastn_core::auth::AuthProviders::Google.as_str())
-                .is_some()
-    }
-
     // TODO: Remove After Demo, Need to think about refresh content from github
     #[derive(serde::Deserialize)]
     struct Temp {
@@ -437,11 +526,13 @@ pub async fn clear_cache(
     let from = actix_web::web::Query::<Temp>::from_query(req.query_string())?;
     if from.from.eq(&Some("temp-github".to_string())) {
         let _lock = LOCK.write().await;
-        return Ok(fastn_core::apis::cache::clear(&req).await);
+        let response = fastn_core::apis::cache::clear(&req).await;
+        fastn_core::config::cache::invalidate();
+        return Ok(response);
     }
     // TODO: Remove After Demo, till here
 
-    if !is_login(&req) {
+    if !fastn_core::auth::is_login(req.cookies()) {
         return Ok(actix_web::HttpResponse::Found()
             .append_header((
                 actix_web::http::header::LOCATION,
@@ -452,6 +543,7 @@ pub async fn clear_cache(
 
     let _lock = LOCK.write().await;
     fastn_core::apis::cache::clear(&req).await;
+    fastn_core::config::cache::invalidate();
     // TODO: Redirect to Referrer uri
     return Ok(actix_web::HttpResponse::Found()
         .append_header((actix_web::http::header::LOCATION, "/".to_string()))
@@ -518,6 +610,26 @@ pub async fn create_cr_page(
     fastn_core::apis::cr::create_cr_page(req).await
 }
 
+pub async fn cr_review(
+    req: fastn_core::http::Request,
+) -> fastn_core::Result<fastn_core::http::Response> {
+    // the review is made as the logged in user, so a page of another site must not post it
+    if !req.is_json() || !req.is_same_origin() {
+        return fastn_core::http::api_error(
+            "The review has to be posted as json, from the page of the CR",
+        );
+    }
+    let _lock = LOCK.write().await;
+    fastn_core::apis::cr::cr_review(&req, req.json()?).await
+}
+
+pub async fn cr_review_page(
+    req: fastn_core::http::Request,
+) -> fastn_core::Result<fastn_core::http::Response> {
+    let _lock = LOCK.read().await;
+    fastn_core::apis::cr::cr_review_page(req).await
+}
+
 struct AppData {
     edition: Option<String>,
     external_js: Vec<String>,
@@ -529,11 +641,20 @@ struct AppData {
 #[tracing::instrument(skip_all)]
 async fn route(
     req: actix_web::HttpRequest,
-    body: actix_web::web::Bytes,
+    payload: actix_web::web::Payload,
     app_data: actix_web::web::Data<AppData>,
 ) -> fastn_core::Result<fastn_core::http::Response> {
     tracing::info!(method = req.method().as_str(), uri = req.path());
-    if req.path().starts_with("/auth/") {
+    if fastn_core::proxy::is_websocket(&req) {
+        return websocket(req, payload).await;
+    }
+    let request = fastn_core::http::Request::from_actix_payload(req.clone(), payload).await?;
+    if req.path().starts_with("/auth/")
+        || req.path() == "/-/auth/login/"
+        || req.path() == "/-/auth/logout/"
+    {
+        // the auth providers declared in FASTN.ftd are registered when the config is read
+        fastn_core::config::cache::read(&request).await?;
         return fastn_core::auth::routes::handle_auth(
             req,
             app_data.edition.clone(),
@@ -544,7 +665,7 @@ async fn route(
         )
         .await;
     }
-    let req = fastn_core::http::Request::from_actix(req, body);
+    let req = request;
     match (req.method().to_lowercase().as_str(), req.path()) {
         ("post", "/-/sync/") if cfg!(feature = "remote") => sync(req).await,
         ("post", "/-/sync2/") if cfg!(feature = "remote") => sync2(req).await,
@@ -555,8 +676,13 @@ async fn route(
         ("get", "/-/editor-sync/") => editor_sync(req).await,
         ("post", "/-/create-cr/") => create_cr(req).await,
         ("get", "/-/create-cr-page/") => create_cr_page(req).await,
+        ("post", t) if t.starts_with("/-/cr/") && t.ends_with("/review/") => cr_review(req).await,
+        ("get", t) if t.starts_with("/-/cr/") => cr_review_page(req).await,
         ("get", "/-/clear-cache/") => clear_cache(req).await,
         ("get", "/-/poll/") => fastn_core::watcher::poll().await,
+        (_, "/-/auth/sessions/") => fastn_core::auth::session::handle(req).await,
+        (_, "/-/auth/link/") => fastn_core::auth::link::handle(req).await,
+        ("get", "/-/status/") => fastn_core::proxy::upstream::status(&req).await,
         (_, _) => {
             serve(
                 req,
@@ -624,6 +750,16 @@ You can try without providing port, it will automatically pick unused port."#,
 
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
 
+    // the watcher invalidates the config cached by `serve()`, so it has to run from the start
+    // and not only once the first `/-/poll/` request comes in
+    fastn_core::watcher::start();
+
+    fastn_core::auth::session::init(&fastn_core::Config::root().await?)?;
+
+    // conflicting routes are reported before serving anything, instead of on the requests
+    // that happen to hit them
+    fastn_core::routes::check(&fastn_core::config::cache::get().await?).await?;
+
     let app = move || {
         actix_web::App::new()
             .app_data(actix_web::web::Data::new(AppData {
@@ -0,0 +1,106 @@
+pub const COMMAND: &str = "show";
+
+pub fn command() -> clap::Command {
+    clap::Command::new(COMMAND)
+        .about("Show a version of a file from the history of this fastn package")
+        .arg(clap::arg!(file: <FILE> "The file to show"))
+        .arg(clap::arg!(version: [VERSION] "The version to show (the latest if empty), or `<FROM>..<TO>` to see the diff between two versions"))
+        .hide(true) // hidden since the feature is not being released yet.
+}
+
+pub async fn handle_command(matches: &clap::ArgMatches) -> fastn_core::Result<()> {
+    use fastn_core::utils::ValueOf;
+
+    let config = fastn_core::Config::read(None, true, None).await?;
+    let file = matches.value_of_("file").unwrap();
+    match matches.value_of_("version") {
+        Some(version) => match version.split_once("..") {
+            Some((from, to)) => diff(&config, file, from.parse()?, to.parse()?).await,
+            None => show(&config, file, Some(version.parse()?)).await,
+        },
+        None => show(&config, file, None).await,
+    }
+}
+
+async fn show(
+    config: &fastn_core::Config,
+    file: &str,
+    version: Option<i32>,
+) -> fastn_core::Result<()> {
+    use std::io::Write;
+
+    match read_version(config, file, version).await? {
+        Some(content) => std::io::stdout().write_all(content.as_slice())?,
+        None => return fastn_core::usage_error(format!("{} is deleted in this version", file)),
+    }
+    Ok(())
+}
+
+async fn diff(
+    config: &fastn_core::Config,
+    file: &str,
+    from: i32,
+    to: i32,
+) -> fastn_core::Result<()> {
+    let (from_content, to_content) = match (
+        String::from_utf8(
+            read_version(config, file, Some(from))
+                .await?
+                .unwrap_or_default(),
+        ),
+        String::from_utf8(
+            read_version(config, file, Some(to))
+                .await?
+                .unwrap_or_default(),
+        ),
+    ) {
+        (Ok(from_content), Ok(to_content)) => (from_content, to_content),
+        _ => {
+            return fastn_core::usage_error(format!(
+                "{} is a binary file, can't show the diff",
+                file
+            ))
+        }
+    };
+    let patch = diffy::create_patch(&from_content, &to_content);
+    let diff = diffy::PatchFormatter::new()
+        .with_color()
+        .fmt_patch(&patch)
+        .to_string();
+    println!("diff: {} v{}..v{}", file, from, to);
+    println!("{}", diff);
+    Ok(())
+}
+
+/// `read_version()` is the content of `version` of `file`, or of its latest version if `None`.
+/// `None` if the file is deleted in that version.
+async fn read_version(
+    config: &fastn_core::Config,
+    file: &str,
+    version: Option<i32>,
+) -> fastn_core::Result<Option<Vec<u8>>> {
+    let file_edit = match version {
+        Some(version) => config
+            .get_history()
+            .await?
+            .into_iter()
+            .find(|v| v.filename.eq(file))
+            .and_then(|v| v.file_edit(version).cloned()),
+        None => config.get_remote_manifest(true).await?.remove(file),
+    };
+    let file_edit = match file_edit {
+        Some(file_edit) => file_edit,
+        None => {
+            return fastn_core::usage_error(match version {
+                Some(version) => format!("{} has no version {}", file, version),
+                None => format!("{} has no history", file),
+            })
+        }
+    };
+    if file_edit.is_deleted() {
+        return Ok(None);
+    }
+    Ok(Some(
+        tokio::fs::read(config.history_path(file, file_edit.version)).await?,
+    ))
+}
@@ -1,9 +1,10 @@
 pub async fn sync2(
     config: &fastn_core::Config,
     files: Option<Vec<String>>,
+    message: Option<&str>,
     // cr_number: Option<&str>,
 ) -> fastn_core::Result<()> {
-    simple_sync(config, files).await
+    simple_sync(config, files, message).await
     /*if let Some(cr_number) = cr_number {
         let cr_number = cr_number.parse::<usize>()?;
         cr_sync(config, file, cr_number).await
@@ -38,6 +39,7 @@ pub async fn sync2(
 async fn simple_sync(
     config: &fastn_core::Config,
     files: Option<Vec<String>>,
+    message: Option<&str>,
 ) -> fastn_core::Result<()> {
     use itertools::Itertools;
 
@@ -51,12 +53,24 @@ async fn simple_sync(
         }
         changed_files
     };
+    for file in changed_files.iter() {
+        if let fastn_core::sync_utils::FileStatus::Add { path, content, .. }
+        | fastn_core::sync_utils::FileStatus::Update { path, content, .. } = file
+        {
+            if fastn_core::ftd_merge::has_conflict_markers(content) {
+                return fastn_core::usage_error(format!(
+                    "{} has unresolved conflicts, remove the conflict markers and sync again",
+                    path
+                ));
+            }
+        }
+    }
     let changed_files = changed_files
         .into_iter()
         .filter_map(|v| v.sync_request(None))
         .collect_vec();
 
-    sync_(config, changed_files, &mut workspace).await?;
+    sync_(config, changed_files, message, &mut workspace).await?;
     config
         .update_workspace(workspace.into_values().collect_vec())
         .await
@@ -65,6 +79,7 @@ async fn simple_sync(
 pub(crate) async fn sync_(
     config: &fastn_core::Config,
     request_files: Vec<fastn_core::apis::sync2::SyncRequestFile>,
+    message: Option<&str>,
     workspace: &mut std::collections::BTreeMap<String, fastn_core::workspace::WorkspaceEntry>,
 ) -> fastn_core::Result<()> {
     let history = tokio::fs::read_to_string(config.history_file()).await?;
@@ -72,6 +87,7 @@ pub(crate) async fn sync_(
         package_name: config.package.name.to_string(),
         files: request_files,
         history,
+        message: message.map(ToString::to_string),
     };
     let response = send_to_fastn_serve(&sync_request).await?;
     update_current_directory(config, &response).await?;
@@ -161,7 +177,9 @@ async fn update_current_directory(
                 } else if status.delete_edit_conflict() {
                     println!("CloneEditedRemoteDeleted: {}", path);
                 } else if status.edit_edit_conflict() {
+                    // the content has the conflicts marked, if it is not a binary file
                     println!("Conflict: {}", path);
+                    fastn_core::utils::update(&config.root.join(path), content).await?;
                 } else {
                     fastn_core::utils::update(&config.root.join(path), content).await?;
                 }
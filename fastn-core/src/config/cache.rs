@@ -0,0 +1,60 @@
+// `fastn serve` used to call `Config::read()` on every request, which re-parses FASTN.ftd,
+// rebuilds `global_ids`, parses the sitemap and dynamic urls and resolves every `fastn#app`.
+//
+// We now read the config once and keep it here. Every request gets its own copy of it, with
+// the per request fields (`request`, `named_parameters`, `current_document`) reset, see
+// `Config::for_request()`. The package is shared behind an `Arc`, so the copy is cheap. The
+// cached config is rebuilt lazily on the next request after `invalidate()` is called, which
+// `fastn_core::watcher` does whenever FASTN.ftd or any document of the package changes.
+//
+// Reading the config also registers the `fastn.auth-provider`s of the package, see
+// `fastn_core::auth::oidc::configure()`.
+
+static CONFIG: once_cell::sync::Lazy<async_lock::Mutex<Option<(usize, fastn_core::Config)>>> =
+    once_cell::sync::Lazy::new(|| async_lock::Mutex::new(None));
+
+// Bumped on every invalidation, the cached config is only valid for the generation it was
+// read in.
+static GENERATION: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+/// `invalidate()` marks the cached config as stale, it will be read again on the next request.
+/// The upstreams of the endpoints of the old config are dropped too.
+pub(crate) fn invalidate() {
+    GENERATION.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    fastn_core::proxy::upstream::evict();
+}
+
+/// `read()` returns the config for the current request, reading FASTN.ftd only if the cached
+/// config has been invalidated since it was last read.
+#[tracing::instrument(name = "config::cache::read", skip_all)]
+pub(crate) async fn read(
+    req: &fastn_core::http::Request,
+) -> fastn_core::Result<fastn_core::Config> {
+    with(|config| config.for_request(req)).await
+}
+
+/// `get()` returns a copy of the cached config, for use outside of a request, like the checks
+/// `fastn serve` does before it starts. The config read for them is then used by the first
+/// request.
+pub(crate) async fn get() -> fastn_core::Result<fastn_core::Config> {
+    with(Clone::clone).await
+}
+
+async fn with<T>(f: impl FnOnce(&fastn_core::Config) -> T) -> fastn_core::Result<T> {
+    let mut cached = CONFIG.lock().await;
+    let generation = GENERATION.load(std::sync::atomic::Ordering::SeqCst);
+
+    let config = match cached.take() {
+        Some((g, config)) if g == generation => config,
+        _ => {
+            tracing::info!(msg = "reading config", generation = generation);
+            let config = fastn_core::Config::read(None, false, None).await?;
+            fastn_core::auth::oidc::configure(&config.package.auth_providers);
+            config
+        }
+    };
+
+    let value = f(&config);
+    *cached = Some((generation, config));
+    Ok(value)
+}
@@ -1,6 +1,7 @@
 // Document: https://fastn_core.dev/crate/config/
 // Document: https://fastn_core.dev/crate/package/
 
+pub(crate) mod cache;
 pub(crate) mod utils;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -31,13 +32,17 @@ impl FTDEdition {
 #[derive(Debug, Clone)]
 pub struct Config {
     // Global Information
-    pub package: fastn_core::Package,
+    pub package: std::sync::Arc<fastn_core::Package>,
     pub root: camino::Utf8PathBuf,
     pub packages_root: camino::Utf8PathBuf,
     pub original_directory: camino::Utf8PathBuf,
-    pub all_packages: std::cell::RefCell<std::collections::BTreeMap<String, fastn_core::Package>>,
+    pub all_packages:
+        std::cell::RefCell<std::sync::Arc<std::collections::BTreeMap<String, fastn_core::Package>>>,
     pub downloaded_assets: std::collections::BTreeMap<String, String>,
-    pub global_ids: std::collections::HashMap<String, String>,
+    /// What rendering the current document read, for `fastn build` to skip the documents whose
+    /// inputs did not change, see `fastn_core::build_cache`
+    pub build_inputs: std::cell::RefCell<fastn_core::build_cache::Inputs>,
+    pub global_ids: std::sync::Arc<std::collections::HashMap<String, String>>,
     // Related to current request, or per request
     pub extra_data: serde_json::Map<String, serde_json::Value>,
     pub named_parameters: Vec<(String, ftd::Value)>,
@@ -115,6 +120,10 @@ impl Config {
         self.cr_path(cr_number).join("-/meta.ftd")
     }
 
+    pub fn cr_review_path(&self, cr_number: usize) -> camino::Utf8PathBuf {
+        self.cr_path(cr_number).join("-/review.ftd")
+    }
+
     pub(crate) fn package_info_package(&self) -> &str {
         match self
             .package
@@ -415,7 +424,12 @@ impl Config {
         // Vec<captured_id, line_number>
         let captured_global_ids: Vec<(String, usize)> = ftd::p1::parse_file_for_global_ids(data);
         for (captured_id, ln) in captured_global_ids.iter() {
-            update_id_map(&mut self.global_ids, captured_id.as_str(), doc_id, *ln)?;
+            update_id_map(
+                std::sync::Arc::make_mut(&mut self.global_ids),
+                captured_id.as_str(),
+                doc_id,
+                *ln,
+            )?;
         }
 
         Ok(())
@@ -758,7 +772,7 @@ impl Config {
                             (new_path, package, remaining_path)
                         }
                     }
-                    None => (path.to_string(), &self.package, path.to_string()),
+                    None => (path.to_string(), self.package.as_ref(), path.to_string()),
                 };
 
             // Getting `document` with dynamic parameters, if exists
@@ -1217,6 +1231,15 @@ impl Config {
         Ok(asset_documents)
     }
 
+    /// `root()` returns the root of the package in the current directory, the directory
+    /// containing FASTN.ftd.
+    pub(crate) async fn root() -> fastn_core::Result<camino::Utf8PathBuf> {
+        let current_dir: camino::Utf8PathBuf = tokio::fs::canonicalize(std::env::current_dir()?)
+            .await?
+            .try_into()?;
+        fastn_core::Config::get_root_path(&current_dir).await
+    }
+
     async fn get_root_path(
         directory: &camino::Utf8PathBuf,
     ) -> fastn_core::Result<camino::Utf8PathBuf> {
@@ -1327,7 +1350,7 @@ impl Config {
         let fastn_doc = utils::fastn_doc(&root.join("FASTN.ftd")).await?;
         let package = fastn_core::Package::from_fastn_doc(&root, &fastn_doc)?;
         let mut config = Config {
-            package: package.clone(),
+            package: std::sync::Arc::new(package.clone()),
             packages_root: root.clone().join(".packages"),
             root,
             original_directory,
@@ -1335,6 +1358,7 @@ impl Config {
             current_document: None,
             all_packages: Default::default(),
             downloaded_assets: Default::default(),
+            build_inputs: Default::default(),
             global_ids: Default::default(),
             request: req.map(ToOwned::to_owned),
             named_parameters: vec![],
@@ -1349,7 +1373,7 @@ impl Config {
         config.update_ids_from_package().await?;
 
         // TODO: Major refactor, while parsing sitemap of a package why do we need config in it?
-        config.package.sitemap = {
+        let sitemap = {
             let sitemap = match package.translation_of.as_ref() {
                 Some(translation) => translation,
                 None => &package,
@@ -1373,9 +1397,10 @@ impl Config {
                 None => None,
             }
         };
+        std::sync::Arc::make_mut(&mut config.package).sitemap = sitemap;
 
         // Handling of `-- fastn.dynamic-urls:`
-        config.package.dynamic_urls = {
+        std::sync::Arc::make_mut(&mut config.package).dynamic_urls = {
             match &package.dynamic_urls_temp {
                 Some(urls_temp) => Some(fastn_core::sitemap::DynamicUrls::parse(
                     &config.global_ids,
@@ -1388,8 +1413,11 @@ impl Config {
 
         config.add_package(&package);
 
+        // `<dependency>/<group-id>` groups used by the package
+        fastn_core::user_group::resolve_groups(&mut config).await?;
+
         // fastn installed Apps
-        config.package.apps = {
+        let apps = {
             let apps_temp: Vec<fastn_core::package::app::AppTemp> = fastn_doc.get("fastn#app")?;
             let mut apps = vec![];
             for app in apps_temp.into_iter() {
@@ -1397,6 +1425,7 @@ impl Config {
             }
             apps
         };
+        std::sync::Arc::make_mut(&mut config.package).apps = apps;
 
         Ok(config)
     }
@@ -1406,12 +1435,25 @@ impl Config {
         self
     }
 
+    /// `for_request()` returns a copy of this config to be used for serving `req`. Everything
+    /// that is specific to a single request is reset, so the copy can be handed out from the
+    /// config cached by `fastn serve`. The package, the other packages and `global_ids` are
+    /// shared with the cached config, they are only copied if the request changes them.
+    pub(crate) fn for_request(&self, req: &fastn_core::http::Request) -> Self {
+        let mut config = self.clone();
+        config.extra_data = Default::default();
+        config.named_parameters = vec![];
+        config.current_document = None;
+        config.request = Some(req.to_owned());
+        config
+    }
+
     pub(crate) async fn resolve_package(
         &self,
         package: &fastn_core::Package,
     ) -> fastn_core::Result<fastn_core::Package> {
         if self.package.name.eq(package.name.as_str()) {
-            return Ok(self.package.clone());
+            return Ok(self.package.as_ref().clone());
         }
 
         if let Some(package) = { self.all_packages.borrow().get(package.name.as_str()) } {
@@ -1427,8 +1469,7 @@ impl Config {
     }
 
     pub(crate) fn add_package(&self, package: &fastn_core::Package) {
-        self.all_packages
-            .borrow_mut()
+        std::sync::Arc::make_mut(&mut self.all_packages.borrow_mut())
             .insert(package.name.to_string(), package.to_owned());
     }
 
@@ -1489,7 +1530,7 @@ impl Config {
         use itertools::Itertools;
         let document_name = self.document_name_with_default(document_path);
         if let Some(sitemap) = &self.package.sitemap {
-            // TODO: This can be buggy in case of: if groups are used directly in sitemap are foreign groups
+            // foreign groups used in the sitemap are in `package.groups`, see `resolve_groups()`
             let (document_readers, confidential) =
                 sitemap.readers(document_name.as_str(), &self.package.groups);
 
@@ -1525,7 +1566,7 @@ impl Config {
         use itertools::Itertools;
         let document_name = self.document_name_with_default(document_path);
         if let Some(sitemap) = &self.package.sitemap {
-            // TODO: This can be buggy in case of: if groups are used directly in sitemap are foreign groups
+            // foreign groups used in the sitemap are in `package.groups`, see `resolve_groups()`
             let document_writers = sitemap.writers(document_name.as_str(), &self.package.groups);
             let access_identities =
                 fastn_core::user_group::access_identities(self, req, &document_name, false).await?;
@@ -75,6 +75,22 @@ pub fn get_clean_url(
             .unwrap_or_else(|| url.to_string()) // TODO: Error possibly, in that return 404 from proxy
     };
 
+    // Dependencies with an endpoint and a mount point, for `/<mount-point>/<remaining>/`
+    for (dep, ep, mp) in config.package.dep_with_ep_and_mp() {
+        if let Some(remaining_url) = url
+            .trim_start_matches('/')
+            .strip_prefix(mp.trim_start_matches('/'))
+        {
+            return Ok((
+                Some(dep.name.to_string()),
+                url::Url::parse(
+                    format!("{}/{}", ep.trim_end_matches('/'), remaining_url).as_str(),
+                )?,
+                std::collections::HashMap::new(),
+            ));
+        }
+    }
+
     // This is for current package
     if let Some(remaining_url) = trim_package_name(url.as_str(), config.package.name.as_str()) {
         let end_point = match config.package.endpoint.as_ref() {
@@ -111,11 +127,73 @@ pub fn get_clean_url(
         }
     }
 
+    // Dependencies with an endpoint, for `/-/<dependency>/<remaining>/`
+    for dep in config.package.dependencies.iter() {
+        if let Some(ep) = &dep.endpoint {
+            if let Some(remaining_url) = trim_package_name(url.as_str(), dep.package.name.as_str())
+            {
+                return Ok((
+                    Some(dep.package.name.to_string()),
+                    url::Url::parse(format!("{}{}", ep, remaining_url).as_str())?,
+                    std::collections::HashMap::new(),
+                ));
+            }
+        }
+    }
+
     let msg = format!("http-processor: end-point not found url: {}", url);
     tracing::error!(msg = msg);
     Err(fastn_core::Error::GenericError(msg))
 }
 
+/// `endpoint_policy()` is the policy of the endpoint `get_clean_url()` picked for the package
+/// `package_name`.
+pub(crate) fn endpoint_policy(
+    config: &fastn_core::Config,
+    package_name: &str,
+) -> fastn_core::package::EndpointPolicy {
+    if config.package.name.eq(package_name) {
+        return config.package.endpoint_policy.clone();
+    }
+    if let Some(app) = config
+        .package
+        .apps
+        .iter()
+        .find(|app| app.end_point.is_some() && app.package.name.eq(package_name))
+    {
+        return app.endpoint_policy.clone();
+    }
+    config
+        .package
+        .dependencies
+        .iter()
+        .find(|dep| dep.endpoint.is_some() && dep.package.name.eq(package_name))
+        .map(|dep| dep.endpoint_policy.clone())
+        .unwrap_or_default()
+}
+
+/// `endpoint_mount_point()` is where the endpoint `get_clean_url()` picked for the package
+/// `package_name` is on the fastn site: the mount point of its app or dependency, or
+/// `/-/<package-name>/`.
+pub(crate) fn endpoint_mount_point(config: &fastn_core::Config, package_name: &str) -> String {
+    config
+        .package
+        .apps
+        .iter()
+        .find(|app| app.end_point.is_some() && app.package.name.eq(package_name))
+        .map(|app| app.mount_point.as_str())
+        .or_else(|| {
+            config
+                .package
+                .dep_with_ep_and_mp()
+                .into_iter()
+                .find(|(dep, _, _)| dep.name.eq(package_name))
+                .map(|(_, _, mp)| mp)
+        })
+        .map(|mp| format!("/{}/", mp.trim().trim_matches('/')))
+        .unwrap_or_else(|| format!("/-/{}/", package_name.trim_matches('/')))
+}
+
 pub(crate) fn is_http_url(url: &str) -> bool {
     url.starts_with("http")
 }
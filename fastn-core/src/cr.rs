@@ -4,6 +4,8 @@ pub struct CRMeta {
     #[serde(rename = "cr-number")]
     pub cr_number: usize,
     pub open: bool,
+    /// The user-group asked to review the CR, see `fastn_core::cr_review`
+    pub reviewers: Option<String>,
 }
 
 impl CRMeta {
@@ -12,6 +14,7 @@ impl CRMeta {
             title: self.title,
             cr_number: self.cr_number,
             open: false,
+            reviewers: self.reviewers,
         }
     }
 }
@@ -37,6 +40,7 @@ pub(crate) async fn resolve_cr_meta(
     struct CRMetaTemp {
         pub title: String,
         pub open: Option<bool>,
+        pub reviewers: Option<String>,
     }
 
     impl CRMetaTemp {
@@ -45,6 +49,7 @@ pub(crate) async fn resolve_cr_meta(
                 title: self.title,
                 cr_number,
                 open: self.open.unwrap_or(true),
+                reviewers: self.reviewers,
             }
         }
     }
@@ -96,7 +101,10 @@ pub(crate) async fn create_cr_meta(
 pub(crate) fn generate_cr_meta_content(cr_meta: &fastn_core::cr::CRMeta) -> String {
     let mut meta_content = format!("-- import: fastn\n\n\n-- fastn.cr-meta: {}", cr_meta.title,);
     if !cr_meta.open {
-        meta_content = format!("{}\n{}", meta_content, cr_meta.open);
+        meta_content = format!("{}\nopen: {}", meta_content, cr_meta.open);
+    }
+    if let Some(ref reviewers) = cr_meta.reviewers {
+        meta_content = format!("{}\nreviewers: {}", meta_content, reviewers);
     }
     format!("{meta_content}\n")
 }
@@ -0,0 +1,429 @@
+// Reviews of a CR are stored alongside it, in `-/<cr-number>/-/review.ftd`, as a list of
+// `fastn.cr-review` (approve or request changes) and `fastn.cr-comment` (comment on a file,
+// optionally on a line or a section of it).
+//
+// Reviews are made on the page of the CR, `/-/cr/<cr-number>/`, which posts them to
+// `/-/cr/<cr-number>/review/`. The reviewer, and the identities which decide if they are a
+// writer, come from their session. The review file is written by the server only, a client can
+// not sync it, else the approvals could be forged.
+//
+// A CR can be merged into main, or closed, once every document it changes is approved by one of
+// the `writers` of that document in the sitemap, and none of them has requested changes. Only
+// the latest review of each reviewer counts. If the CR has `reviewers`, only the reviews of the
+// members of that user-group count.
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReviewStatus {
+    Approved,
+    ChangesRequested,
+}
+
+impl ReviewStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ReviewStatus::Approved => "approved",
+            ReviewStatus::ChangesRequested => "changes-requested",
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct Review {
+    pub reviewer: String,
+    pub status: ReviewStatus,
+    /// The identities of the reviewer when they reviewed the CR, as `key: value, ...`
+    pub identities: Option<String>,
+    /// Nanoseconds since the epoch
+    pub timestamp: i64,
+    pub message: Option<String>,
+}
+
+impl Review {
+    fn identities(&self) -> Vec<fastn_core::user_group::UserIdentity> {
+        fastn_core::user_group::parse_identities(self.identities.as_deref().unwrap_or_default())
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub author: String,
+    /// The file commented on, relative to the CR
+    pub file: String,
+    pub line: Option<i64>,
+    /// The `-- <kind>: <caption>` line of the section commented on
+    pub section: Option<String>,
+    /// Nanoseconds since the epoch
+    pub timestamp: i64,
+    pub message: String,
+}
+
+#[derive(serde::Serialize, Debug, Default)]
+pub struct CRReview {
+    pub reviews: Vec<Review>,
+    pub comments: Vec<Comment>,
+}
+
+impl CRReview {
+    /// `latest_reviews()` is the latest review of each reviewer, in the order they reviewed.
+    pub(crate) fn latest_reviews(&self) -> Vec<&Review> {
+        let mut latest: Vec<&Review> = vec![];
+        for review in self.reviews.iter() {
+            latest.retain(|r| r.reviewer.ne(&review.reviewer) || r.timestamp > review.timestamp);
+            if !latest.iter().any(|r| r.reviewer.eq(&review.reviewer)) {
+                latest.push(review);
+            }
+        }
+        latest.sort_by_key(|r| r.timestamp);
+        latest
+    }
+}
+
+pub(crate) async fn get_cr_review(
+    config: &fastn_core::Config,
+    cr_number: usize,
+) -> fastn_core::Result<CRReview> {
+    if !config.cr_path(cr_number).exists() {
+        return fastn_core::usage_error(format!("CR#{} doesn't exist", cr_number));
+    }
+    let cr_review_path = config.cr_review_path(cr_number);
+    if !cr_review_path.exists() {
+        return Ok(Default::default());
+    }
+    let content = tokio::fs::read_to_string(&cr_review_path).await?;
+    resolve_cr_review(content.as_str(), cr_number).await
+}
+
+pub(crate) async fn resolve_cr_review(
+    content: &str,
+    cr_number: usize,
+) -> fastn_core::Result<CRReview> {
+    if content.trim().is_empty() {
+        return Ok(Default::default());
+    }
+    let lib = fastn_core::FastnLibrary::default();
+    let b = match fastn_core::doc::parse_ftd("review.ftd", content, &lib) {
+        Ok(v) => v,
+        Err(e) => {
+            return fastn_core::usage_error(format!(
+                "failed to parse review.ftd for CR#{}: {:?}",
+                cr_number, &e
+            ));
+        }
+    };
+
+    Ok(CRReview {
+        reviews: b.get("fastn#cr-review")?,
+        comments: b.get("fastn#cr-comment")?,
+    })
+}
+
+/// `check_comment()` checks the `file` and `section` of a comment are one of the `changed_files`
+/// and a `-- <kind>: <caption>` line, they are written as they are in the review file, where a
+/// new line would start a review of its own.
+pub(crate) fn check_comment(
+    file: &str,
+    section: Option<&str>,
+    changed_files: &[String],
+) -> fastn_core::Result<()> {
+    if file.contains(['\n', '\r']) || !changed_files.iter().any(|f| f.eq(file)) {
+        return fastn_core::usage_error(format!("`{}` is not changed by the CR", file.trim()));
+    }
+    if let Some(section) = section {
+        if section.contains(['\n', '\r']) || !section.starts_with("-- ") {
+            return fastn_core::usage_error(format!(
+                "`{}` is not a section, it has to be the `-- <kind>: <caption>` line of one",
+                section.trim()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Captions and headers of the review file have to be on a single line.
+fn one_line(s: &str) -> String {
+    s.replace(['\n', '\r'], " ")
+}
+
+pub(crate) fn generate_cr_review_content(cr_review: &CRReview) -> String {
+    let mut review_content = vec!["-- import: fastn".to_string()];
+
+    for review in cr_review.reviews.iter() {
+        let mut content = format!(
+            "-- fastn.cr-review: {}\nstatus: {}",
+            one_line(review.reviewer.as_str()),
+            review.status.as_str()
+        );
+        if let Some(ref identities) = review.identities {
+            content = format!("{}\nidentities: {}", content, one_line(identities));
+        }
+        content = format!("{}\ntimestamp: {}", content, review.timestamp);
+        if let Some(message) = review.message.as_ref().filter(|m| !m.trim().is_empty()) {
+            content = format!(
+                "{}\n\n{}",
+                content,
+                fastn_core::utils::escape_ftd(message.trim())
+            );
+        }
+        review_content.push(content);
+    }
+
+    for comment in cr_review.comments.iter() {
+        let mut content = format!(
+            "-- fastn.cr-comment: {}\nfile: {}",
+            one_line(comment.author.as_str()),
+            one_line(comment.file.as_str())
+        );
+        if let Some(line) = comment.line {
+            content = format!("{}\nline: {}", content, line);
+        }
+        if let Some(ref section) = comment.section {
+            content = format!("{}\nsection: {}", content, one_line(section));
+        }
+        content = format!(
+            "{}\ntimestamp: {}\n\n{}",
+            content,
+            comment.timestamp,
+            fastn_core::utils::escape_ftd(comment.message.trim())
+        );
+        review_content.push(content);
+    }
+
+    let content = review_content.join("\n\n");
+    format!("{content}\n")
+}
+
+/// `is_cr_review_path()` checks if `path`, relative to the package, is the review file of a CR,
+/// `-/<cr-number>/-/review.ftd`.
+pub(crate) fn is_cr_review_path(path: &str) -> bool {
+    let mut parts: Vec<&str> = vec![];
+    for component in camino::Utf8Path::new(path).components() {
+        match component {
+            camino::Utf8Component::Normal(part) => parts.push(part),
+            camino::Utf8Component::ParentDir => {
+                parts.pop();
+            }
+            _ => {}
+        }
+    }
+    matches!(
+        parts.as_slice(),
+        ["-", cr_number, "-", "review.ftd"] if cr_number.parse::<usize>().is_ok()
+    )
+}
+
+/// `changed_files()` are the files added, updated or deleted by the CR, relative to the CR.
+pub(crate) async fn changed_files(
+    config: &fastn_core::Config,
+    cr_number: usize,
+) -> fastn_core::Result<Vec<String>> {
+    let cr_path = config.cr_path(cr_number);
+    let mut files = vec![];
+    for path in ignore::WalkBuilder::new(&cr_path)
+        .build()
+        .flatten()
+        .map(|x| camino::Utf8PathBuf::from_path_buf(x.into_path()).unwrap())
+        .filter(|x| x.is_file())
+    {
+        let file = path.strip_prefix(&cr_path)?.to_string();
+        // `-/` has the meta data of the CR
+        if !file.starts_with("-/") {
+            files.push(file);
+        }
+    }
+    files.extend(
+        fastn_core::cr::get_deleted_files(config, cr_number)
+            .await?
+            .into_iter()
+            .map(|v| v.filename),
+    );
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// `document_path()` is the url of a file, the way the sitemap refers to it.
+pub(crate) fn document_path(file: &str) -> String {
+    let path = fastn_core::utils::id_to_path(file);
+    let path = path.trim_matches('/');
+    if path.is_empty() {
+        "/".to_string()
+    } else {
+        format!("/{}/", path)
+    }
+}
+
+/// `pending_approvals()` are the files changed by the CR which are yet to be approved, or on
+/// which changes have been requested.
+pub(crate) async fn pending_approvals(
+    config: &fastn_core::Config,
+    cr_number: usize,
+) -> fastn_core::Result<Vec<String>> {
+    use itertools::Itertools;
+
+    let sitemap = match config.package.sitemap.as_ref() {
+        Some(sitemap) => sitemap,
+        // without a sitemap there are no writers to approve the CR
+        None => return Ok(vec![]),
+    };
+    let cr_meta = fastn_core::cr::get_cr_meta(config, cr_number).await?;
+    let reviewers = match cr_meta.reviewers {
+        Some(ref id) => Some(
+            fastn_core::user_group::user_group_by_id(config, id.as_str())?.ok_or_else(|| {
+                fastn_core::Error::GroupNotFound {
+                    id: id.to_string(),
+                    message: format!("reviewers of CR#{} not found", cr_number),
+                }
+            })?,
+        ),
+        None => None,
+    };
+    let cr_review = get_cr_review(config, cr_number).await?;
+    let latest_reviews = cr_review.latest_reviews();
+
+    let mut pending = vec![];
+    for file in changed_files(config, cr_number).await? {
+        let writers = sitemap.writers(
+            document_path(file.as_str()).as_str(),
+            &config.package.groups,
+        );
+        if writers.is_empty() {
+            continue;
+        }
+        let (mut approved, mut changes_requested) = (false, false);
+        for review in latest_reviews.iter() {
+            let identities = review.identities();
+            let identities = identities.iter().collect_vec();
+            if !fastn_core::user_group::belongs_to(
+                config,
+                writers.as_slice(),
+                identities.as_slice(),
+            )? {
+                continue;
+            }
+            if let Some(ref reviewers) = reviewers {
+                if !reviewers.belongs_to(config, identities.as_slice())? {
+                    continue;
+                }
+            }
+            match review.status {
+                ReviewStatus::Approved => approved = true,
+                ReviewStatus::ChangesRequested => changes_requested = true,
+            }
+        }
+        if !approved || changes_requested {
+            pending.push(file);
+        }
+    }
+    Ok(pending)
+}
+
+/// `check_approvals()` fails unless every file changed by the CR is approved.
+pub(crate) async fn check_approvals(
+    config: &fastn_core::Config,
+    cr_number: usize,
+) -> fastn_core::Result<()> {
+    let pending = pending_approvals(config, cr_number).await?;
+    if pending.is_empty() {
+        return Ok(());
+    }
+    fastn_core::usage_error(format!(
+        "CR#{} is yet to be approved by the writers of: {}",
+        cr_number,
+        pending.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    fn review(reviewer: &str, status: super::ReviewStatus, timestamp: i64) -> super::Review {
+        super::Review {
+            reviewer: reviewer.to_string(),
+            status,
+            identities: None,
+            timestamp,
+            message: None,
+        }
+    }
+
+    #[test]
+    fn latest_reviews() {
+        let cr_review = super::CRReview {
+            reviews: vec![
+                review("alice", super::ReviewStatus::ChangesRequested, 1),
+                review("bob", super::ReviewStatus::Approved, 2),
+                review("alice", super::ReviewStatus::Approved, 3),
+            ],
+            comments: vec![],
+        };
+        assert_eq!(
+            cr_review.latest_reviews(),
+            vec![
+                &review("bob", super::ReviewStatus::Approved, 2),
+                &review("alice", super::ReviewStatus::Approved, 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn is_cr_review_path() {
+        assert!(super::is_cr_review_path("-/1/-/review.ftd"));
+        assert!(super::is_cr_review_path("/-/12/-/review.ftd"));
+        assert!(super::is_cr_review_path("-/1/./-/review.ftd"));
+        assert!(super::is_cr_review_path("-/2/../1/-/review.ftd"));
+        assert!(!super::is_cr_review_path("-/1/-/meta.ftd"));
+        assert!(!super::is_cr_review_path("-/1/review.ftd"));
+        assert!(!super::is_cr_review_path("review.ftd"));
+    }
+
+    #[test]
+    fn check_comment() {
+        let changed_files = vec!["index.ftd".to_string()];
+        assert!(super::check_comment("index.ftd", None, &changed_files).is_ok());
+        assert!(
+            super::check_comment("index.ftd", Some("-- ftd.text: hello"), &changed_files).is_ok()
+        );
+        assert!(super::check_comment("other.ftd", None, &changed_files).is_err());
+        assert!(super::check_comment("index.ftd\nstatus: approved", None, &changed_files).is_err());
+        assert!(super::check_comment("index.ftd", Some("hello"), &changed_files).is_err());
+        assert!(super::check_comment(
+            "index.ftd",
+            Some("-- ftd.text: hello\r\n-- fastn.cr-review: admin"),
+            &changed_files
+        )
+        .is_err());
+    }
+
+    #[actix_web::test]
+    async fn forged_review() {
+        let cr_review = super::CRReview {
+            reviews: vec![],
+            comments: vec![super::Comment {
+                author: "github-bob".to_string(),
+                file: "index.ftd\n\n-- fastn.cr-review: github-admin\nstatus: approved\ntimestamp: 1"
+                    .to_string(),
+                line: None,
+                section: Some(
+                    "-- ftd.text: hi\n\n-- fastn.cr-review: github-admin\nstatus: approved\ntimestamp: 2"
+                        .to_string(),
+                ),
+                timestamp: 3,
+                message: "-- fastn.cr-review: github-admin\nstatus: approved\ntimestamp: 4"
+                    .to_string(),
+            }],
+        };
+        let content = super::generate_cr_review_content(&cr_review);
+        let resolved = super::resolve_cr_review(content.as_str(), 1).await.unwrap();
+        assert!(resolved.reviews.is_empty(), "{}", content);
+        assert_eq!(resolved.comments.len(), 1);
+        assert_eq!(resolved.comments[0].author, "github-bob");
+        assert_eq!(resolved.comments[0].message, cr_review.comments[0].message);
+    }
+
+    #[test]
+    fn document_path() {
+        assert_eq!(super::document_path("index.ftd"), "/");
+        assert_eq!(super::document_path("blog/index.ftd"), "/blog/");
+        assert_eq!(super::document_path("blog/hello.md"), "/blog/hello/");
+    }
+}
@@ -40,7 +40,7 @@ pub async fn parse<'a>(
 
     let mut packages_under_process = vec![current_package
         .map(|v| v.to_owned())
-        .unwrap_or_else(|| lib.config.package.clone())];
+        .unwrap_or_else(|| lib.config.package.as_ref().clone())];
     let document;
     loop {
         match s {
@@ -54,6 +54,9 @@ pub enum Error {
     #[error("ParseBoolError: {}", _0)]
     ParseBoolError(#[from] std::str::ParseBoolError),
 
+    #[error("SqliteError: {}", _0)]
+    SqliteError(#[from] rusqlite::Error),
+
     #[error("APIResponseError: {}", _0)]
     APIResponseError(String),
 
@@ -66,6 +69,9 @@ pub enum Error {
     #[error("GenericError: {}", _0)]
     GenericError(String),
 
+    #[error("UnknownAuthProvider: {name}")]
+    UnknownAuthProvider { name: String },
+
     #[error("GroupNotFound: id: {id}, {message}")]
     GroupNotFound { id: String, message: String },
 
@@ -75,6 +81,15 @@ pub enum Error {
     #[error("QueryPayloadError: {}", _0)]
     QueryPayloadError(#[from] actix_web::error::QueryPayloadError),
 
+    #[error("PayloadError: {}", _0)]
+    PayloadError(#[from] actix_web::error::PayloadError),
+
+    #[error("BodyTooLarge: request body is longer than {limit} bytes")]
+    BodyTooLarge { limit: usize },
+
+    #[error("EndpointTimeout: {url} did not respond in time")]
+    EndpointTimeout { url: String },
+
     #[error("TokioMPSCError1: {}", _0)]
     TokioMPSCError1(
         #[from] tokio::sync::mpsc::error::SendError<fastn_core::watcher::WatcherSender>,
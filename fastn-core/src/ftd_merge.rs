@@ -0,0 +1,381 @@
+// Three-way merge of the files edited on both sides of a sync or a merge.
+//
+// FTD documents are merged section by section, so edits to different sections never conflict,
+// even when they are on adjacent lines. A section starts at a `-- <kind>: <caption>` line and
+// runs up to the next one, and is identified by that line, so editing the caption of a section
+// is the same as removing it and adding a new one. When both sides edit the same section, its
+// headers are merged one by one, and the rest of it line by line. A document where that line is
+// repeated, like a list of `-- ds.h1: Item` sections, can not be merged section by section, as
+// there is no telling which of the repeated sections were edited, added or removed, so it is
+// merged line by line like any other text file.
+//
+// Other text files are merged line by line. What can not be merged is written with the standard
+// conflict markers:
+//
+//     <<<<<<< ours
+//     ...
+//     =======
+//     ...
+//     >>>>>>> theirs
+
+/// `merge()` returns the merged content, or, if there were conflicts, the content with the
+/// conflicts marked, like `diffy::merge()`.
+pub(crate) fn merge(
+    path: &str,
+    ancestor: &str,
+    ours: &str,
+    theirs: &str,
+) -> Result<String, String> {
+    if path.ends_with(".ftd") {
+        merge_ftd(ancestor, ours, theirs)
+    } else {
+        merge_lines(ancestor, ours, theirs)
+    }
+}
+
+fn merge_lines(ancestor: &str, ours: &str, theirs: &str) -> Result<String, String> {
+    diffy::MergeOptions::new()
+        .set_conflict_style(diffy::ConflictStyle::Merge)
+        .merge(ancestor, ours, theirs)
+}
+
+#[derive(Debug, PartialEq)]
+struct Section<'a> {
+    /// The `-- <kind>: <caption>` line, empty for the text before the first section.
+    key: &'a str,
+    text: &'a str,
+}
+
+fn sections(content: &str) -> Vec<Section> {
+    let mut sections: Vec<Section> = vec![];
+    let mut start = 0;
+    let mut key = "";
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        if line.starts_with("-- ") {
+            if offset > start {
+                sections.push(Section {
+                    key,
+                    text: &content[start..offset],
+                });
+            }
+            start = offset;
+            key = line.trim_end();
+        }
+        offset += line.len();
+    }
+    if offset > start {
+        sections.push(Section {
+            key,
+            text: &content[start..offset],
+        });
+    }
+    sections
+}
+
+/// `has_repeated_keys()` checks if two of the sections start with the same `-- ` line.
+fn has_repeated_keys(sections: &[Section]) -> bool {
+    let mut keys = std::collections::HashSet::new();
+    !sections.iter().all(|s| keys.insert(s.key))
+}
+
+fn merge_ftd(
+    ancestor_content: &str,
+    ours_content: &str,
+    theirs_content: &str,
+) -> Result<String, String> {
+    let (ancestor, ours, theirs) = (
+        sections(ancestor_content),
+        sections(ours_content),
+        sections(theirs_content),
+    );
+    if has_repeated_keys(&ancestor) || has_repeated_keys(&ours) || has_repeated_keys(&theirs) {
+        return merge_lines(ancestor_content, ours_content, theirs_content);
+    }
+    let find = |sections: &[Section<'_>], key: &str| {
+        sections
+            .iter()
+            .find(|s| s.key.eq(key))
+            .map(|s| s.text.to_string())
+    };
+
+    // the sections in our order, with the sections they added after the section before them
+    let mut keys = ours.iter().map(|s| s.key).collect::<Vec<_>>();
+    for (i, section) in theirs.iter().enumerate() {
+        if keys.contains(&section.key) {
+            continue;
+        }
+        let position = theirs[..i]
+            .iter()
+            .rev()
+            .find_map(|before| keys.iter().position(|k| k.eq(&before.key)))
+            .map(|p| p + 1)
+            .unwrap_or(0);
+        keys.insert(position, section.key);
+    }
+
+    let mut merged = String::new();
+    let mut conflicted = false;
+    for key in keys {
+        let a = find(&ancestor, key);
+        let o = find(&ours, key);
+        let t = find(&theirs, key);
+        match merge_section(a, o, t) {
+            Ok(text) => merged.push_str(text.as_str()),
+            Err(text) => {
+                conflicted = true;
+                merged.push_str(text.as_str());
+            }
+        }
+    }
+
+    if conflicted {
+        Err(merged)
+    } else {
+        Ok(merged)
+    }
+}
+
+/// `merge_section()` merges the versions of a section, `None` where it is not present.
+fn merge_section(
+    ancestor: Option<String>,
+    ours: Option<String>,
+    theirs: Option<String>,
+) -> Result<String, String> {
+    if ours.eq(&theirs) || theirs.eq(&ancestor) {
+        return Ok(ours.unwrap_or_default());
+    }
+    if ours.eq(&ancestor) {
+        return Ok(theirs.unwrap_or_default());
+    }
+    match (ancestor, ours, theirs) {
+        (ancestor, Some(ours), Some(theirs)) => {
+            let ancestor = ancestor.unwrap_or_default();
+            match merge_headers(ancestor.as_str(), ours.as_str(), theirs.as_str()) {
+                Some(merged) => Ok(merged),
+                None => merge_lines(ancestor.as_str(), ours.as_str(), theirs.as_str()),
+            }
+        }
+        // edited on one side and removed on the other
+        (_, ours, theirs) => Err(markers(
+            ours.unwrap_or_default().as_str(),
+            theirs.unwrap_or_default().as_str(),
+        )),
+    }
+}
+
+/// `merge_headers()` merges the headers of a section one by one, and the rest of it line by
+/// line. `None` if they can not be merged.
+fn merge_headers(ancestor: &str, ours: &str, theirs: &str) -> Option<String> {
+    let (a_line, a_headers, a_rest) = split_section(ancestor);
+    let (o_line, o_headers, o_rest) = split_section(ours);
+    let (t_line, t_headers, t_rest) = split_section(theirs);
+    if o_line.ne(t_line) || (!a_line.is_empty() && a_line.ne(o_line)) {
+        return None;
+    }
+
+    let value = |headers: &[(&str, &str)], name: &str| {
+        headers
+            .iter()
+            .find(|(n, _)| n.eq(&name))
+            .map(|(_, line)| line.to_string())
+    };
+    let mut names = o_headers.iter().map(|(n, _)| *n).collect::<Vec<_>>();
+    names.extend(
+        t_headers
+            .iter()
+            .map(|(n, _)| *n)
+            .filter(|n| !o_headers.iter().any(|(o, _)| o.eq(n))),
+    );
+
+    let mut merged = o_line.to_string();
+    for name in names {
+        merged.push_str(
+            merge_section(
+                value(&a_headers, name),
+                value(&o_headers, name),
+                value(&t_headers, name),
+            )
+            .ok()?
+            .as_str(),
+        );
+    }
+    merged.push_str(merge_lines(a_rest, o_rest, t_rest).ok()?.as_str());
+    Some(merged)
+}
+
+/// `split_section()` splits a section into its `-- ` line, its headers, as (name, line), and the
+/// rest of it, starting at the first line that is not a header.
+fn split_section(text: &str) -> (&str, Vec<(&str, &str)>, &str) {
+    let mut lines = text.split_inclusive('\n');
+    let line = match lines.next() {
+        Some(line) if line.starts_with("-- ") => line,
+        _ => return ("", vec![], text),
+    };
+    let mut headers = vec![];
+    let mut offset = line.len();
+    for l in lines {
+        let name = match l.split_once(':') {
+            Some((name, _))
+                if !name.is_empty() && !name.starts_with(' ') && !name.starts_with('-') =>
+            {
+                name.trim()
+            }
+            _ => break,
+        };
+        headers.push((name, l));
+        offset += l.len();
+    }
+    (line, headers, &text[offset..])
+}
+
+fn markers(ours: &str, theirs: &str) -> String {
+    let line = |text: &str| {
+        if text.is_empty() || text.ends_with('\n') {
+            text.to_string()
+        } else {
+            format!("{}\n", text)
+        }
+    };
+    format!(
+        "<<<<<<< ours\n{}=======\n{}>>>>>>> theirs\n",
+        line(ours),
+        line(theirs)
+    )
+}
+
+/// `has_conflict_markers()` checks if `content` has conflicts marked by `merge()`, which are yet
+/// to be resolved.
+pub(crate) fn has_conflict_markers(content: &[u8]) -> bool {
+    let content = match std::str::from_utf8(content) {
+        Ok(content) => content,
+        Err(_) => return false,
+    };
+    let mut lines = content.lines();
+    lines.any(|l| l.eq("<<<<<<< ours"))
+        && lines.any(|l| l.eq("======="))
+        && lines.any(|l| l.eq(">>>>>>> theirs"))
+}
+
+/// `keep_both_path()` is where the other side's copy of a binary file, which can not be merged,
+/// is kept: `image.png` is kept as `image.conflict.png`, or `image.conflict-2.png` and so on if
+/// `exists`.
+pub(crate) fn keep_both_path(path: &str, exists: impl Fn(&str) -> bool) -> String {
+    let (stem, ext) = match path.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() && !ext.contains('/') => (stem, format!(".{}", ext)),
+        _ => (path, "".to_string()),
+    };
+    let mut kept = format!("{}.conflict{}", stem, ext);
+    let mut i = 2;
+    while exists(kept.as_str()) {
+        kept = format!("{}.conflict-{}{}", stem, i, ext);
+        i += 1;
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    const ANCESTOR: &str = "-- import: lib\n\n-- ds.h1: Hello\n\nHello World\n\n\
+        -- ds.h1: About\nid: about\n\nAbout us\n";
+
+    #[test]
+    fn different_sections() {
+        let ours = "-- import: lib\n\n-- ds.h1: Hello\n\nHello There\n\n\
+            -- ds.h1: About\nid: about\n\nAbout us\n";
+        let theirs = "-- import: lib\n\n-- ds.h1: Hello\n\nHello World\n\n\
+            -- ds.h1: About\nid: about\n\nAbout them\n";
+        assert_eq!(
+            super::merge("index.ftd", ANCESTOR, ours, theirs),
+            Ok("-- import: lib\n\n-- ds.h1: Hello\n\nHello There\n\n\
+                -- ds.h1: About\nid: about\n\nAbout them\n"
+                .to_string())
+        );
+    }
+
+    #[test]
+    fn headers() {
+        let ours = "-- import: lib\n\n-- ds.h1: Hello\n\nHello World\n\n\
+            -- ds.h1: About\nid: about-us\n\nAbout us\n";
+        let theirs = "-- import: lib\n\n-- ds.h1: Hello\n\nHello World\n\n\
+            -- ds.h1: About\nid: about\nalign: center\n\nAbout us\n";
+        assert_eq!(
+            super::merge("index.ftd", ANCESTOR, ours, theirs),
+            Ok("-- import: lib\n\n-- ds.h1: Hello\n\nHello World\n\n\
+                -- ds.h1: About\nid: about-us\nalign: center\n\nAbout us\n"
+                .to_string())
+        );
+    }
+
+    #[test]
+    fn added_and_removed_sections() {
+        let ours = "-- import: lib\n\n-- ds.h1: Hello\n\nHello World\n\n\
+            -- ds.h1: Intro\n\nIntro\n\n-- ds.h1: About\nid: about\n\nAbout us\n";
+        let theirs = "-- import: lib\n\n-- ds.h1: About\nid: about\n\nAbout us\n\n\
+            -- ds.h1: Contact\n";
+        assert_eq!(
+            super::merge("index.ftd", ANCESTOR, ours, theirs),
+            Ok("-- import: lib\n\n-- ds.h1: Intro\n\nIntro\n\n\
+                -- ds.h1: About\nid: about\n\nAbout us\n\n-- ds.h1: Contact\n"
+                .to_string())
+        );
+    }
+
+    #[test]
+    fn conflict() {
+        let ours = "-- import: lib\n\n-- ds.h1: Hello\n\nHello There\n\n\
+            -- ds.h1: About\nid: about\n\nAbout us\n";
+        let theirs = "-- import: lib\n\n\
+            -- ds.h1: About\nid: about\n\nAbout us\n";
+        assert_eq!(
+            super::merge("index.ftd", ANCESTOR, ours, theirs),
+            Err(
+                "-- import: lib\n\n<<<<<<< ours\n-- ds.h1: Hello\n\nHello There\n\n\
+                =======\n>>>>>>> theirs\n-- ds.h1: About\nid: about\n\nAbout us\n"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn repeated_sections() {
+        let ancestor = "-- ds.h1: Item\n\nOne\n\n-- ds.h1: Item\n\nTwo\n";
+        let ours = "-- ds.h1: Item\n\nZero\n\n-- ds.h1: Item\n\nOne\n\n-- ds.h1: Item\n\nTwo\n";
+        let theirs = "-- ds.h1: Item\n\nOne\n\n-- ds.h1: Item\n\nTwo and a half\n";
+        assert_eq!(
+            super::merge("index.ftd", ancestor, ours, theirs),
+            Ok("-- ds.h1: Item\n\nZero\n\n-- ds.h1: Item\n\nOne\n\n\
+                -- ds.h1: Item\n\nTwo and a half\n"
+                .to_string())
+        );
+    }
+
+    #[test]
+    fn has_conflict_markers() {
+        let marked = super::merge(
+            "index.ftd",
+            ANCESTOR,
+            "-- import: lib\n",
+            "-- import: lib2\n",
+        )
+        .unwrap_err();
+        assert!(super::has_conflict_markers(marked.as_bytes()));
+        assert!(!super::has_conflict_markers(ANCESTOR.as_bytes()));
+    }
+
+    #[test]
+    fn keep_both_path() {
+        assert_eq!(
+            super::keep_both_path("static/logo.png", |_| false),
+            "static/logo.conflict.png"
+        );
+        assert_eq!(
+            super::keep_both_path("static/logo.png", |p| p.eq("static/logo.conflict.png")),
+            "static/logo.conflict-2.png"
+        );
+        assert_eq!(
+            super::keep_both_path("LICENSE", |_| false),
+            "LICENSE.conflict"
+        );
+    }
+}
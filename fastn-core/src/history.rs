@@ -29,6 +29,43 @@ impl FileEdit {
     pub(crate) fn is_deleted(&self) -> bool {
         self.operation.is_deleted()
     }
+
+    pub(crate) fn time(&self) -> String {
+        time(self.timestamp)
+    }
+}
+
+/// `time()` is a timestamp, in nanoseconds since the epoch, as `YYYY-MM-DD HH:MM:SS UTC`.
+pub(crate) fn time(timestamp: u128) -> String {
+    i64::try_from(timestamp / 1_000_000_000)
+        .ok()
+        .and_then(|seconds| chrono::DateTime::from_timestamp(seconds, 0))
+        .map(|time| time.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+/// `parse_time()` is the inverse of `time()`: `YYYY-MM-DD`, optionally followed by `HH:MM` or
+/// `HH:MM:SS`, and `UTC`, as nanoseconds since the epoch.
+pub(crate) fn parse_time(time: &str) -> Option<u128> {
+    let time = time
+        .trim()
+        .trim_end_matches("UTC")
+        .trim_end_matches('Z')
+        .trim();
+    let time = match chrono::NaiveDate::parse_from_str(time, "%Y-%m-%d") {
+        Ok(date) => date.and_hms_opt(0, 0, 0)?,
+        Err(_) => [
+            "%Y-%m-%d %H:%M:%S",
+            "%Y-%m-%dT%H:%M:%S",
+            "%Y-%m-%d %H:%M",
+            "%Y-%m-%dT%H:%M",
+        ]
+        .iter()
+        .find_map(|format| chrono::NaiveDateTime::parse_from_str(time, format).ok())?,
+    };
+    u128::try_from(time.and_utc().timestamp())
+        .ok()
+        .map(|s| s * 1_000_000_000)
 }
 
 #[derive(serde::Serialize, serde::Deserialize, std::fmt::Debug, PartialEq, Eq, Clone)]
@@ -104,8 +141,9 @@ impl fastn_core::Config {
             fastn_core::history::FileHistory::get_remote_manifest(history_list.as_slice(), true)?
                 .into_iter()
                 .filter(|(k, _)| {
-                    k.starts_with(cr_path_prefix.as_str())
-                        || k.starts_with(format!(".tracks/{}", cr_path_prefix).as_str())
+                    // `-/1/` and not `-/1`, which would also match the files of CR#10
+                    k.starts_with(format!("{}/", cr_path_prefix).as_str())
+                        || k.starts_with(format!(".tracks/{}/", cr_path_prefix).as_str())
                 })
                 .collect(),
         )
@@ -144,6 +182,23 @@ impl FileHistory {
             .collect())
     }
 
+    /// `file_edit_at()` is the edit of the file, which was the latest at `timestamp`, in the same
+    /// way as `get_remote_manifest()` picks the latest edit.
+    pub(crate) fn file_edit_at(&self, timestamp: u128) -> Option<&FileEdit> {
+        self.file_edit
+            .iter()
+            .filter(|file_edit| file_edit.timestamp <= timestamp)
+            .find(|file_edit| {
+                file_edit.operation.eq(&FileOperation::Merged) || file_edit.src_cr.is_none()
+            })
+    }
+
+    pub(crate) fn file_edit(&self, version: i32) -> Option<&FileEdit> {
+        self.file_edit
+            .iter()
+            .find(|file_edit| file_edit.version.eq(&version))
+    }
+
     fn get_latest_file_edit(&self, with_deleted: bool) -> Option<FileEdit> {
         for file_edit in self.file_edit.iter() {
             if file_edit.is_deleted() && !with_deleted {
@@ -264,3 +319,39 @@ pub(crate) async fn insert_into_history_(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn time() {
+        let file_edit = |timestamp| super::FileEdit {
+            message: None,
+            timestamp,
+            version: 1,
+            author: None,
+            src_cr: None,
+            operation: super::FileOperation::Added,
+        };
+        assert_eq!(file_edit(0).time(), "1970-01-01 00:00:00 UTC");
+        assert_eq!(
+            file_edit(1_709_210_096_123_456_789).time(),
+            "2024-02-29 12:34:56 UTC"
+        );
+    }
+
+    #[test]
+    fn parse_time() {
+        assert_eq!(super::parse_time("1970-01-01"), Some(0));
+        assert_eq!(
+            super::parse_time("2024-02-29 12:34:56 UTC"),
+            Some(1_709_210_096_000_000_000)
+        );
+        assert_eq!(
+            super::parse_time("2024-02-29T12:34"),
+            Some(1_709_210_040_000_000_000)
+        );
+        assert_eq!(super::parse_time("2024-13-01"), None);
+        assert_eq!(super::parse_time("2023-02-31"), None);
+        assert_eq!(super::parse_time("yesterday"), None);
+    }
+}
@@ -34,7 +34,22 @@ pub fn not_found_(msg: String) -> fastn_core::http::Response {
     actix_web::HttpResponse::NotFound().body(msg)
 }
 
-impl actix_web::ResponseError for fastn_core::Error {}
+impl actix_web::ResponseError for fastn_core::Error {
+    fn status_code(&self) -> actix_web::http::StatusCode {
+        match self {
+            fastn_core::Error::UnknownAuthProvider { .. } => {
+                actix_web::http::StatusCode::BAD_REQUEST
+            }
+            fastn_core::Error::BodyTooLarge { .. } => {
+                actix_web::http::StatusCode::PAYLOAD_TOO_LARGE
+            }
+            fastn_core::Error::EndpointTimeout { .. } => {
+                actix_web::http::StatusCode::GATEWAY_TIMEOUT
+            }
+            _ => actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
 
 pub type Response = actix_web::HttpResponse;
 
@@ -61,12 +76,67 @@ pub struct Request {
     headers: reqwest::header::HeaderMap,
     query: std::collections::HashMap<String, serde_json::Value>,
     body: actix_web::web::Bytes,
+    /// The rest of the body, if it is longer than `BODY_BUFFER_LIMIT`, see `from_actix_payload()`
+    body_stream: Option<BodyStream>,
     ip: Option<String>,
     scheme: String,
     host: String,
     // path_params: Vec<(String, )>
 }
 
+/// Request bodies up to this long are read into memory, like actix does by default. A longer
+/// body is only accepted by endpoints, which get it streamed.
+const BODY_BUFFER_LIMIT: usize = 256 * 1024;
+
+/// How many chunks of a streamed request body are read ahead of the endpoint. Reading stops
+/// while the endpoint is not taking them, which in turn slows down the client.
+const BODY_STREAM_CAPACITY: usize = 16;
+
+/// The unread part of a request body. It is read from the client by a task of its own, as
+/// the actix payload can not be kept in the `Request`, which has to be `Send`.
+#[derive(Clone)]
+struct BodyStream(std::sync::Arc<antidote::Mutex<Option<BodyReceiver>>>);
+
+impl BodyStream {
+    fn new(mut payload: actix_web::web::Payload) -> BodyStream {
+        let (sender, receiver) = tokio::sync::mpsc::channel(BODY_STREAM_CAPACITY);
+        actix_web::rt::spawn(async move {
+            while let Some(chunk) = futures::StreamExt::next(&mut payload).await {
+                let chunk = chunk
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()));
+                if sender.send(chunk).await.is_err() {
+                    // nobody is reading the body anymore
+                    break;
+                }
+            }
+        });
+        BodyStream(std::sync::Arc::new(antidote::Mutex::new(Some(
+            BodyReceiver(receiver),
+        ))))
+    }
+}
+
+impl std::fmt::Debug for BodyStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("BodyStream")
+    }
+}
+
+pub(crate) struct BodyReceiver(
+    tokio::sync::mpsc::Receiver<Result<actix_web::web::Bytes, std::io::Error>>,
+);
+
+impl futures::Stream for BodyReceiver {
+    type Item = Result<actix_web::web::Bytes, std::io::Error>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        self.0.poll_recv(cx)
+    }
+}
+
 impl Request {
     //pub fn get_named_params() -> {}
     pub fn from_actix(req: actix_web::HttpRequest, body: actix_web::web::Bytes) -> Self {
@@ -81,6 +151,7 @@ impl Request {
         return Request {
             cookies: get_cookies(&headers),
             body,
+            body_stream: None,
             method: req.method().to_string(),
             uri: req.uri().to_string(),
             path: req.path().to_string(),
@@ -117,8 +188,45 @@ impl Request {
         }
     }
 
+    /// `from_actix_payload()` reads up to `BODY_BUFFER_LIMIT` bytes of the body into `body()`.
+    /// The rest of a longer body is not read till the request is proxied to an endpoint, which
+    /// gets it as a stream, see `body_stream()`.
+    pub async fn from_actix_payload(
+        req: actix_web::HttpRequest,
+        mut payload: actix_web::web::Payload,
+    ) -> fastn_core::Result<Self> {
+        let mut body = actix_web::web::BytesMut::new();
+        while body.len() < BODY_BUFFER_LIMIT {
+            match futures::StreamExt::next(&mut payload).await {
+                Some(chunk) => body.extend_from_slice(&chunk?),
+                None => return Ok(Request::from_actix(req, body.freeze())),
+            }
+        }
+
+        let mut request = Request::from_actix(req, body.freeze());
+        request.body_stream = Some(BodyStream::new(payload));
+        Ok(request)
+    }
+
+    /// `whole_body()` is `body()`, for the handlers that need all of it at once. It fails if
+    /// the body is too long to be read into memory.
+    pub fn whole_body(&self) -> fastn_core::Result<&[u8]> {
+        if self.body_stream.is_some() {
+            return Err(fastn_core::Error::BodyTooLarge {
+                limit: BODY_BUFFER_LIMIT,
+            });
+        }
+        Ok(&self.body)
+    }
+
+    /// `body_stream()` returns the part of the body after `body()`, the first time it is called
+    /// on a request with a body longer than `BODY_BUFFER_LIMIT`.
+    pub(crate) fn body_stream(&self) -> Option<BodyReceiver> {
+        self.body_stream.as_ref().and_then(|s| s.0.lock().take())
+    }
+
     pub fn json<T: serde::de::DeserializeOwned>(&self) -> fastn_core::Result<T> {
-        Ok(serde_json::from_slice(&self.body)?)
+        Ok(serde_json::from_slice(self.whole_body()?)?)
     }
 
     pub fn body_as_json(
@@ -136,7 +244,7 @@ impl Request {
                 ),
             });
         }
-        Ok(Some(serde_json::from_slice(&self.body)?))
+        Ok(Some(serde_json::from_slice(self.whole_body()?)?))
     }
 
     pub fn content_type(&self) -> Option<mime_guess::Mime> {
@@ -150,6 +258,10 @@ impl Request {
         &self.body
     }
 
+    pub(crate) fn body_bytes(&self) -> actix_web::web::Bytes {
+        self.body.clone()
+    }
+
     pub fn method(&self) -> &str {
         self.method.as_str()
     }
@@ -213,6 +325,47 @@ impl Request {
     pub fn scheme(&self) -> String {
         self.scheme.to_string()
     }
+
+    /// `is_xhr()` checks if the request is made by javascript, with `XMLHttpRequest` or `fetch()`,
+    /// and not by the browser navigating to a page.
+    pub fn is_xhr(&self) -> bool {
+        let header = |name: &str| {
+            self.headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_lowercase())
+        };
+        if header("x-requested-with").as_deref() == Some("xmlhttprequest") {
+            return true;
+        }
+        // https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Sec-Fetch-Mode
+        if let Some(mode) = header("sec-fetch-mode") {
+            return mode != "navigate";
+        }
+        // older browsers: the `http` function of ftd asks for json, pages ask for html
+        header("accept")
+            .map(|accept| accept.contains("application/json") && !accept.contains("text/html"))
+            .unwrap_or(false)
+    }
+
+    /// `is_same_origin()` checks if the request is made by a page of this site, from its
+    /// `Origin` header, which browsers send with every `POST`.
+    pub fn is_same_origin(&self) -> bool {
+        self.headers
+            .get("origin")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|origin| origin.split_once("://"))
+            .map(|(_, host)| host.eq_ignore_ascii_case(self.host.as_str()))
+            .unwrap_or(false)
+    }
+
+    /// `is_json()` checks if the body is json, which, unlike the body of a form, a page of
+    /// another site can not post without the consent of this one.
+    pub fn is_json(&self) -> bool {
+        self.content_type()
+            .map(|v| v.essence_str().eq("application/json"))
+            .unwrap_or(false)
+    }
 }
 
 pub(crate) struct ResponseBuilder {
@@ -221,56 +374,95 @@ pub(crate) struct ResponseBuilder {
     // remaining
 }
 
-// We will no do stream, data is going to less from services
 impl ResponseBuilder {
     // chain implementation
     // .build
     // response from string, json, bytes etc
 
-    pub async fn from_reqwest(
+    /// The body of the response is streamed to the client as the service sends it, so
+    /// downloads and event streams are not held in memory.
+    ///
+    /// Redirects of the endpoint are moved under its `mount_point`, see `redirect_location()`.
+    /// For `xhr` requests they are sent as `{"redirect": <location>}`, with status 200, for the
+    /// `http` function of ftd to navigate to, as `XMLHttpRequest` and `fetch()` follow redirects
+    /// on their own.
+    pub fn from_reqwest(
         response: reqwest::Response,
-        package_name: &str,
+        mount_point: &str,
+        xhr: bool,
+        read_timeout: Option<std::time::Duration>,
     ) -> fastn_core::http::Response {
         let status = response.status();
 
-        // Remove `Connection` as per
+        // Remove the hop-by-hop headers, like `Connection`, as per
         // https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Connection#Directives
         let mut response_builder = actix_web::HttpResponse::build(status);
         for header in response
             .headers()
             .iter()
-            .filter(|(h, _)| *h != "connection")
+            .filter(|(h, _)| !fastn_core::proxy::is_hop_by_hop(h.as_str()))
         {
             response_builder.insert_header(header);
         }
-        if status == actix_web::http::StatusCode::FOUND {
-            response_builder.status(actix_web::http::StatusCode::OK);
-            if let Some(location) = response.headers().get(actix_web::http::header::LOCATION) {
-                let redirect = location.to_str().unwrap();
-                let path = if redirect.trim_matches('/').is_empty() {
-                    format!("/-/{}/", package_name)
-                } else {
-                    // if it contains query-params so url should not end with /
-                    if redirect.contains('?') {
-                        format!("/-/{}/{}", package_name, redirect.trim_matches('/'))
-                    } else {
-                        format!("/-/{}/{}/", package_name, redirect.trim_matches('/'))
+        if status.is_redirection() {
+            if let Some(location) = response
+                .headers()
+                .get(actix_web::http::header::LOCATION)
+                .and_then(|l| l.to_str().ok())
+            {
+                let location = redirect_location(location, response.url(), mount_point);
+                if xhr {
+                    let t = serde_json::json!({ "redirect": location.as_str() }).to_string();
+                    let mut response = response_builder
+                        .status(actix_web::http::StatusCode::OK)
+                        .content_type(actix_web::http::header::ContentType::json())
+                        .body(t);
+                    // the headers of the redirect body of the endpoint
+                    for header in [
+                        actix_web::http::header::LOCATION,
+                        actix_web::http::header::CONTENT_LENGTH,
+                        actix_web::http::header::CONTENT_ENCODING,
+                    ] {
+                        response.headers_mut().remove(header);
                     }
-                };
-                let t = serde_json::json!({"redirect": path.as_str()}).to_string();
-                return response_builder.body(t);
+                    return response;
+                }
+                response_builder.insert_header((actix_web::http::header::LOCATION, location));
             }
         }
 
-        let content = match response.bytes().await {
-            Ok(b) => b,
-            Err(e) => {
-                return actix_web::HttpResponse::from(actix_web::error::ErrorInternalServerError(
-                    fastn_core::Error::HttpError(e),
-                ))
-            }
-        };
-        response_builder.body(content)
+        match read_timeout {
+            Some(read_timeout) => response_builder.streaming(fastn_core::proxy::with_read_timeout(
+                response.bytes_stream(),
+                read_timeout,
+            )),
+            None => response_builder.streaming(response.bytes_stream()),
+        }
+    }
+}
+
+/// `redirect_location()` is where the redirect of an endpoint to `location` goes on the fastn
+/// site: paths of the endpoint, and urls on the endpoint itself, are moved under the
+/// `mount_point` of the endpoint. Urls of other sites, and paths relative to the current page,
+/// which already is under the mount point, are left as they are.
+fn redirect_location(location: &str, endpoint: &url::Url, mount_point: &str) -> String {
+    let path = match url::Url::parse(location) {
+        Ok(url) if url.origin() == endpoint.origin() => {
+            url[url::Position::BeforePath..].to_string()
+        }
+        Ok(_) => return location.to_string(),
+        Err(_) if location.starts_with('/') && !location.starts_with("//") => location.to_string(),
+        Err(_) => return location.to_string(),
+    };
+
+    let mount_point = mount_point.trim().trim_matches('/');
+    if path.trim_matches('/').is_empty() {
+        format!("/{}/", mount_point)
+    } else if path.contains('?') || path.contains('#') {
+        // if it contains query-params so url should not end with /
+        format!("/{}/{}", mount_point, path.trim_start_matches('/'))
+    } else {
+        format!("/{}/{}/", mount_point, path.trim_matches('/'))
     }
 }
 
@@ -531,3 +723,49 @@ pub(crate) fn get_available_port(
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn redirect_location() {
+        let endpoint = url::Url::parse("http://127.0.0.1:8000/api/add-todo/").unwrap();
+        let location = |l: &str| super::redirect_location(l, &endpoint, "/todos/");
+        assert_eq!(location("/"), "/todos/");
+        assert_eq!(location("/list"), "/todos/list/");
+        assert_eq!(location("/list/?page=2"), "/todos/list/?page=2");
+        assert_eq!(location("http://127.0.0.1:8000/list/"), "/todos/list/");
+        assert_eq!(
+            location("https://github.com/login/"),
+            "https://github.com/login/"
+        );
+        assert_eq!(location("//github.com/login/"), "//github.com/login/");
+        assert_eq!(location("done/"), "done/");
+    }
+
+    #[test]
+    fn is_xhr() {
+        let is_xhr = |headers: &[(&str, &str)]| {
+            let mut req = actix_web::test::TestRequest::get();
+            for header in headers {
+                req = req.insert_header(*header);
+            }
+            super::Request::from_actix(req.to_http_request(), Default::default()).is_xhr()
+        };
+        assert!(is_xhr(&[("X-Requested-With", "XMLHttpRequest")]));
+        assert!(is_xhr(&[
+            ("x-requested-with", "xmlhttprequest"),
+            ("sec-fetch-mode", "navigate")
+        ]));
+        assert!(is_xhr(&[("Sec-Fetch-Mode", "cors")]));
+        assert!(!is_xhr(&[("Sec-Fetch-Mode", "navigate")]));
+        // `Sec-Fetch-Mode` is trusted over `Accept`
+        assert!(!is_xhr(&[
+            ("sec-fetch-mode", "navigate"),
+            ("accept", "application/json")
+        ]));
+        assert!(is_xhr(&[("Accept", "application/json")]));
+        assert!(!is_xhr(&[("Accept", "text/html,application/json;q=0.9")]));
+        assert!(!is_xhr(&[("Accept", "*/*")]));
+        assert!(!is_xhr(&[]));
+    }
+}
@@ -4,14 +4,17 @@ extern crate self as fastn_core;
 pub mod utils;
 mod apis;
 mod auto_import;
+mod build_cache;
 mod cache;
 pub mod commands;
 mod config;
 mod controller;
 mod cr;
+mod cr_review;
 mod doc;
 mod file;
 mod font;
+mod ftd_merge;
 mod history;
 mod package;
 pub(crate) mod watcher;
@@ -22,8 +25,11 @@ mod ds;
 mod error;
 mod i18n;
 pub mod library;
+mod library2022;
 mod proxy;
 mod render;
+mod routes;
+mod seo;
 pub mod sitemap;
 mod snapshot;
 mod sync_utils;
@@ -31,8 +37,7 @@ mod track;
 mod tracker;
 mod translation;
 mod version;
-// mod wasm;
-mod library2022;
+mod wasm;
 mod workspace;
 
 pub(crate) use auto_import::AutoImport;
@@ -242,6 +247,58 @@ fn package_info_create_cr(config: &fastn_core::Config) -> fastn_core::Result<Str
     })
 }
 
+/// `package_info_cr_review()` is the page of a CR, with its reviews, as the `fastn.cr-review` and
+/// `fastn.cr-comment` lists, and the files `pending` approval.
+fn package_info_cr_review(
+    config: &fastn_core::Config,
+    cr_meta: &fastn_core::cr::CRMeta,
+    cr_review: &fastn_core::cr_review::CRReview,
+    pending: &[String],
+) -> fastn_core::Result<String> {
+    let body_prefix = match config.package.generate_prefix_string(false) {
+        Some(bp) => bp,
+        None => String::new(),
+    };
+    let reviewers = cr_meta
+        .reviewers
+        .as_ref()
+        .map(|v| format!("\nreviewers: {}", v))
+        .unwrap_or_default();
+    let mut cr_review_ftd = indoc::formatdoc! {"
+            {body_prefix}
+
+            -- import: {package_info_package}/cr-review as pi
+            -- import: fastn
+
+
+            -- pi.cr-review: {title}
+            cr-number: {cr_number}
+            open: {open}{reviewers}
+            pending: $pending
+            reviews: $fastn.cr-review
+            comments: $fastn.cr-comment
+
+            -- string list pending:
+        ",
+        body_prefix = body_prefix,
+        package_info_package = config.package_info_package(),
+        title = cr_meta.title,
+        cr_number = cr_meta.cr_number,
+        open = cr_meta.open,
+        reviewers = reviewers,
+    };
+    for file in pending {
+        cr_review_ftd = format!("{}\n-- pending: {}\n", cr_review_ftd, file);
+    }
+    let reviews = fastn_core::cr_review::generate_cr_review_content(cr_review);
+    cr_review_ftd = format!(
+        "{}\n\n{}",
+        cr_review_ftd,
+        reviews.trim_start_matches("-- import: fastn").trim_start()
+    );
+    Ok(cr_review_ftd)
+}
+
 fn package_info_code(
     config: &fastn_core::Config,
     file_name: &str,
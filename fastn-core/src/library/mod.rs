@@ -282,9 +282,7 @@ impl Library2 {
                     line_number: 0,
                 })?;
 
-        self.config
-            .all_packages
-            .borrow_mut()
+        std::sync::Arc::make_mut(&mut self.config.all_packages.borrow_mut())
             .insert(package.name.to_string(), package);
         Ok(())
     }
@@ -157,6 +157,11 @@ impl Library2022 {
             if !file_path.ends_with(".ftd") {
                 return None;
             }
+            lib.config.build_inputs.borrow_mut().file(
+                &lib.config.root,
+                &lib.config.get_root_for_package(package).join(file_path),
+                &data,
+            );
             String::from_utf8(data).ok().map(|body| {
                 let body_with_prefix = package.get_prefixed_body(body.as_str(), name, true);
                 let line_number = body_with_prefix.split('\n').count() - body.split('\n').count();
@@ -193,9 +198,7 @@ impl Library2022 {
                     line_number: 0,
                 })?;
 
-        self.config
-            .all_packages
-            .borrow_mut()
+        std::sync::Arc::make_mut(&mut self.config.all_packages.borrow_mut())
             .insert(package.name.to_string(), package);
         Ok(())
     }
@@ -215,6 +218,10 @@ impl Library2022 {
         );
         let line_number = ast.line_number();
         let (_processor, value, kind) = get_processor_data(ast, doc)?;
+        // their output can change without any file changing
+        if matches!(processor.as_str(), "http" | "package-tree") {
+            self.config.build_inputs.borrow_mut().volatile = true;
+        }
         match processor.as_str() {
             "figma-cs-token" => {
                 processor::figma_tokens::process_figma_tokens(value, kind, doc, &self.config)
@@ -23,13 +23,18 @@ pub async fn fetch_files<'a>(
             line_number: value.line_number(),
         })?;
 
-    Ok(ftd::interpreter2::Value::String {
-        text: tokio::fs::read_to_string(config.root.join(path))
-            .await
-            .map_err(|v| ftd::interpreter2::Error::ParseError {
-                message: v.to_string(),
-                doc_id: doc.name.to_string(),
-                line_number: value.line_number(),
-            })?,
-    })
+    let path = config.root.join(path);
+    let text = tokio::fs::read_to_string(path.as_path())
+        .await
+        .map_err(|v| ftd::interpreter2::Error::ParseError {
+            message: v.to_string(),
+            doc_id: doc.name.to_string(),
+            line_number: value.line_number(),
+        })?;
+    config
+        .build_inputs
+        .borrow_mut()
+        .file(&config.root, &path, text.as_bytes());
+
+    Ok(ftd::interpreter2::Value::String { text })
 }
@@ -115,6 +115,11 @@ pub fn process(
                 line_number,
             }
         })?;
+        config.build_inputs.borrow_mut().file(
+            &config.root,
+            camino::Utf8Path::new(path.as_str()),
+            file.as_bytes(),
+        );
         return doc.from_json(
             &serde_json::from_str::<serde_json::Value>(&file)?,
             &kind,
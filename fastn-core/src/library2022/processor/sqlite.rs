@@ -50,6 +50,12 @@ pub async fn processor_<'a>(
     // select * from users where
 
     dbg!(&sqlite_database_path);
+    // a database can be large, it is only read again if it changed, see
+    // `fastn_core::build_cache::hash_file()`
+    let _ = config
+        .build_inputs
+        .borrow_mut()
+        .path(&config.root, &sqlite_database_path);
 
     let query = match &body {
         Some(b) => &b.value,
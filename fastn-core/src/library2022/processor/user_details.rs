@@ -4,24 +4,28 @@ pub fn process(
     doc: &ftd::interpreter2::TDoc,
     config: &fastn_core::Config,
 ) -> ftd::interpreter2::Result<ftd::interpreter2::Value> {
-    let mut found_cookie = false;
-    let is_login = match &config.request {
-        Some(req) => {
-            for auth_provider in fastn_core::auth::AuthProviders::AUTH_ITER.iter() {
-                if req.cookie(auth_provider.as_str()).is_some() {
-                    found_cookie = true;
+    let (is_login, user) = match &config.request {
+        Some(req) => (
+            fastn_core::auth::is_login(req.cookies()),
+            fastn_core::auth::link::user(req.cookies()).map_err(|e| {
+                ftd::interpreter2::Error::ParseError {
+                    message: e.to_string(),
+                    doc_id: doc.name.to_string(),
+                    line_number: value.line_number(),
                 }
-            }
-            found_cookie
-        }
-        None => false,
+            })?,
+        ),
+        None => (false, Default::default()),
     };
 
     #[derive(Debug, serde::Serialize)]
     struct UserDetails {
         #[serde(rename = "is-login")]
         is_login: bool,
+        // the provider identities of the user, linked ones included
+        #[serde(flatten)]
+        user: fastn_core::auth::link::User,
     }
-    let ud = UserDetails { is_login };
+    let ud = UserDetails { is_login, user };
     doc.from_json(&ud, &kind, value.line_number())
 }
@@ -5,6 +5,7 @@ pub struct App {
     pub package: fastn_core::Package,
     pub mount_point: String,
     pub end_point: Option<String>,
+    pub endpoint_policy: fastn_core::package::EndpointPolicy,
     pub user_id: Option<String>,
     pub config: std::collections::HashMap<String, String>,
     pub readers: Vec<String>,
@@ -19,6 +20,8 @@ pub struct AppTemp {
     pub mount_point: String,
     #[serde(rename = "end-point")]
     pub end_point: Option<String>,
+    #[serde(flatten)]
+    pub endpoint_policy: fastn_core::package::EndpointPolicyTemp,
     #[serde(rename = "user-id")]
     pub user_id: Option<String>,
     pub config: Vec<String>,
@@ -27,7 +30,7 @@ pub struct AppTemp {
 }
 
 impl AppTemp {
-    fn parse_config(
+    pub(crate) fn parse_config(
         config: &[String],
     ) -> fastn_core::Result<std::collections::HashMap<String, String>> {
         let mut hm = std::collections::HashMap::new();
@@ -85,6 +88,7 @@ impl AppTemp {
             package,
             mount_point: self.mount_point,
             end_point: self.end_point,
+            endpoint_policy: self.endpoint_policy.into_policy()?,
             user_id: self.user_id,
             config: Self::parse_config(&self.config)?,
             readers: self.readers,
@@ -6,6 +6,7 @@ pub struct Dependency {
     pub alias: Option<String>,
     pub implements: Vec<String>,
     pub endpoint: Option<String>,
+    pub endpoint_policy: fastn_core::package::EndpointPolicy,
     pub mountpoint: Option<String>,
 }
 
@@ -40,6 +41,8 @@ pub(crate) struct DependencyTemp {
     pub endpoint: Option<String>,
     #[serde(rename = "mount-point")]
     pub mountpoint: Option<String>,
+    #[serde(flatten)]
+    pub endpoint_policy: fastn_core::package::EndpointPolicyTemp,
 }
 
 impl DependencyTemp {
@@ -55,6 +58,7 @@ impl DependencyTemp {
             alias,
             implements: self.implements,
             endpoint: self.endpoint,
+            endpoint_policy: self.endpoint_policy.into_policy()?,
             mountpoint: match self.mountpoint {
                 Some(mp) => {
                     let trimmed_mountpoint = mp.trim_start_matches('/').trim_end_matches('/');
@@ -144,7 +148,7 @@ impl fastn_core::Package {
 
                 ftd_document
                     .get::<fastn_core::package::PackageTemp>("fastn#package")?
-                    .into_package()
+                    .into_package()?
                     .zip
                     .ok_or(fastn_core::Error::UsageError {
                         message: format!(
@@ -489,7 +493,7 @@ impl fastn_core::Package {
         let mut package = {
             let temp_package: fastn_core::package::PackageTemp =
                 ftd_document.get("fastn#package")?;
-            temp_package.into_package()
+            temp_package.into_package()?
         };
 
         package.translation_status_summary =
@@ -608,7 +612,7 @@ impl fastn_core::Package {
         let mut package = {
             let temp_package: fastn_core::package::PackageTemp =
                 ftd_document.get("fastn#package")?;
-            temp_package.into_package()
+            temp_package.into_package()?
         };
 
         package.translation_status_summary =
@@ -55,15 +55,27 @@ pub struct Package {
     /// endpoint for proxy service
     pub endpoint: Option<String>,
 
+    /// How requests are passed on to the endpoint
+    pub endpoint_policy: EndpointPolicy,
+
     /// Attribute to define the usage of a WASM backend
     pub backend: bool,
 
     /// Headers for the WASM backend
     pub backend_headers: Option<Vec<BackendHeader>>,
 
+    /// Timeouts and allowed hosts for the `http` calls made by the WASM backend
+    pub backend_http: BackendHttp,
+
+    /// Fuel, memory and time the WASM backend can use for a single request
+    pub backend_limits: BackendLimits,
+
     /// Installed Apps
     pub apps: Vec<app::App>,
 
+    /// OpenID Connect login providers declared with `fastn.auth-provider`
+    pub auth_providers: Vec<fastn_core::auth::oidc::OidcProvider>,
+
     /// Package Icon
     pub icon: Option<ftd::ImageSrc>,
 }
@@ -94,9 +106,13 @@ impl Package {
             dynamic_urls_temp: None,
             favicon: None,
             endpoint: None,
+            endpoint_policy: Default::default(),
             backend: false,
             backend_headers: None,
+            backend_http: Default::default(),
+            backend_limits: Default::default(),
             apps: vec![],
+            auth_providers: vec![],
             icon: None,
         }
     }
@@ -487,7 +503,7 @@ impl Package {
         };
         let mut package = {
             let temp_package: PackageTemp = fastn_document.get("fastn#package")?;
-            temp_package.into_package()
+            temp_package.into_package()?
         };
         package.translation_status_summary =
             fastn_document.get("fastn#translation-status-summary")?;
@@ -543,7 +559,7 @@ impl Package {
         let temp_package: Option<PackageTemp> = fastn_doc.get("fastn#package")?;
 
         let mut package = match temp_package {
-            Some(v) => v.into_package(),
+            Some(v) => v.into_package()?,
             None => {
                 return Err(fastn_core::Error::PackageError {
                     message: "FASTN.ftd does not contain package definition".to_string(),
@@ -576,6 +592,7 @@ impl Package {
                 alias: None,
                 implements: Vec::new(),
                 endpoint: None,
+                endpoint_policy: Default::default(),
                 mountpoint: None,
             });
         };
@@ -594,14 +611,26 @@ impl Package {
         package.sitemap_temp = fastn_doc.get("fastn#sitemap")?;
         package.dynamic_urls_temp = fastn_doc.get("fastn#dynamic-urls")?;
 
-        // TODO: resolve group dependent packages, there may be imported group from foreign package
-        //   We need to make sure to resolve that package as well before moving ahead
-        //   Because in `UserGroup::get_identities` we have to resolve identities of a group
+        // groups imported from other packages, `<dependency>/<group-id>`, are resolved once the
+        // dependencies are known, by `fastn_core::user_group::resolve_groups()`
         let user_groups: Vec<crate::user_group::UserGroupTemp> =
             fastn_doc.get("fastn#user-group")?;
         let groups = crate::user_group::UserGroupTemp::user_groups(user_groups)?;
         package.groups = groups;
 
+        package.auth_providers = fastn_doc.get("fastn#auth-provider")?;
+        for (i, provider) in package.auth_providers.iter().enumerate() {
+            provider.validate()?;
+            if package.auth_providers[..i]
+                .iter()
+                .any(|p| p.name.eq(&provider.name))
+            {
+                return Err(fastn_core::Error::PackageError {
+                    message: format!("fastn.auth-provider `{}` is declared twice", provider.name),
+                });
+            }
+        }
+
         // validation logic TODO: It should be ordered
         fastn_core::utils::validate_base_url(&package)?;
 
@@ -669,6 +698,266 @@ pub struct BackendHeader {
     #[serde(rename = "header-value")]
     pub header_value: String,
 }
+/// BackendHttp configures the `http` capability the host gives to the WASM backend of a
+/// package:
+///
+/// ```ftd
+/// -- fastn.package: www.amitu.com
+/// backend: true
+/// backend-http-timeout: 30
+/// backend-http-connect-timeout: 10
+/// backend-allowed-hosts: api.github.com
+/// backend-allowed-hosts: *.supabase.co
+/// ```
+///
+/// Timeouts are in seconds. The backend can only call, or be redirected to, the hosts listed in
+/// `backend-allowed-hosts`, `*.<domain>` allows all the subdomains of `<domain>` and `*` allows
+/// every host. Hosts are matched case insensitively.
+#[derive(Debug, Clone)]
+pub struct BackendHttp {
+    pub timeout: std::time::Duration,
+    pub connect_timeout: std::time::Duration,
+    pub allowed_hosts: Vec<String>,
+}
+
+impl Default for BackendHttp {
+    fn default() -> Self {
+        BackendHttp {
+            timeout: std::time::Duration::from_secs(30),
+            connect_timeout: std::time::Duration::from_secs(150),
+            allowed_hosts: vec![],
+        }
+    }
+}
+
+impl BackendHttp {
+    pub fn is_allowed(&self, host: &str) -> bool {
+        // host names are case insensitive
+        let host = host.to_ascii_lowercase();
+        self.allowed_hosts.iter().any(|allowed| {
+            let allowed = allowed.trim().to_ascii_lowercase();
+            if allowed == "*" {
+                return true;
+            }
+            match allowed.strip_prefix("*.") {
+                Some(domain) => host
+                    .strip_suffix(domain)
+                    .map(|sub| sub.ends_with('.'))
+                    .unwrap_or(false),
+                None => allowed.eq(&host),
+            }
+        })
+    }
+}
+
+/// BackendLimits are the resources the WASM backend of a package can use while handling a
+/// single request:
+///
+/// ```ftd
+/// -- fastn.package: www.amitu.com
+/// backend: true
+/// backend-fuel: 100000000
+/// backend-max-memory: 64
+/// backend-timeout: 10
+/// ```
+///
+/// `backend-max-memory` is in MiB and `backend-timeout` is in seconds, it covers the `http`
+/// calls of the backend too. Limits of the package being served also apply to the backends of
+/// its dependencies and apps, which can only lower them, see `BackendLimits::restrict()`.
+#[derive(Debug, Clone, Default)]
+pub struct BackendLimits {
+    pub fuel: Option<u64>,
+    /// in bytes
+    pub max_memory: Option<usize>,
+    pub timeout: Option<std::time::Duration>,
+}
+
+impl BackendLimits {
+    /// `restrict()` returns the stricter of `self` and `other` for every resource.
+    pub fn restrict(&self, other: &BackendLimits) -> BackendLimits {
+        fn min<T: Ord + Copy>(a: Option<T>, b: Option<T>) -> Option<T> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            }
+        }
+
+        BackendLimits {
+            fuel: min(self.fuel, other.fuel),
+            max_memory: min(self.max_memory, other.max_memory),
+            timeout: min(self.timeout, other.timeout),
+        }
+    }
+}
+
+/// EndpointPolicy is how fastn passes requests on to the endpoint of a package, dependency or
+/// app, set with the `endpoint-*` fields of `fastn.package`, `fastn.dependency` and `fastn.app`:
+///
+/// ```ftd
+/// -- fastn.app: Todos
+/// package: todos.fifthtry.site
+/// mount-point: /todos/
+/// end-point: http://127.0.0.1:8000
+/// endpoint-connect-timeout: 5
+/// endpoint-read-timeout: 30
+/// endpoint-retries: 2
+/// endpoint-strip-headers: referer
+/// endpoint-add-headers: x-api-key=$ENV.TODOS_API_KEY
+/// endpoint-forward-cookies: false
+/// endpoint-upstreams: http://127.0.0.1:8001
+/// endpoint-upstreams: http://127.0.0.1:8002
+/// endpoint-balance: least-connections
+/// endpoint-health-check: /health/
+/// ```
+///
+/// Timeouts are in seconds. The read timeout is how long the endpoint can take to start the
+/// response, and then to send each part of its body, without it the endpoint can take as long as
+/// it wants. Only idempotent requests, whose body fastn has fully read, are retried, and only if
+/// the endpoint could not be reached or did not start the response in time.
+///
+/// `endpoint-upstreams` are replicas of the endpoint, only their scheme, host and port are used,
+/// requests are spread over them and the endpoint with `endpoint-balance`. See
+/// `fastn_core::proxy::upstream` for the health checks.
+#[derive(Debug, Clone)]
+pub struct EndpointPolicy {
+    pub connect_timeout: std::time::Duration,
+    pub read_timeout: Option<std::time::Duration>,
+    pub retries: u32,
+    /// Request headers not sent to the endpoint
+    pub strip_headers: Vec<String>,
+    /// Headers sent to the endpoint, as `<key>=<value>`, the value can be `$ENV.<env_var_name>`
+    pub add_headers: Vec<String>,
+    pub forward_cookies: bool,
+    /// The other replicas of the endpoint
+    pub upstreams: Vec<String>,
+    pub balance: Balance,
+    /// Path on every upstream that is requested every `health_check_interval`
+    pub health_check: Option<String>,
+    pub health_check_interval: std::time::Duration,
+    /// Failures in a row after which an upstream is not used for `fail_timeout`
+    pub max_fails: u32,
+    pub fail_timeout: std::time::Duration,
+}
+
+/// How requests are spread over the upstreams of an endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Balance {
+    /// Each upstream in turn
+    #[default]
+    RoundRobin,
+    /// The upstream with the least requests in progress
+    LeastConnections,
+}
+
+impl std::str::FromStr for Balance {
+    type Err = fastn_core::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "round-robin" => Ok(Balance::RoundRobin),
+            "least-connections" => Ok(Balance::LeastConnections),
+            t => Err(fastn_core::Error::PackageError {
+                message: format!(
+                    "unknown endpoint-balance: {}, expected round-robin or least-connections",
+                    t
+                ),
+            }),
+        }
+    }
+}
+
+impl Default for EndpointPolicy {
+    fn default() -> Self {
+        EndpointPolicy {
+            connect_timeout: std::time::Duration::from_secs(150),
+            read_timeout: None,
+            retries: 0,
+            strip_headers: vec![],
+            add_headers: vec![],
+            forward_cookies: true,
+            upstreams: vec![],
+            balance: Default::default(),
+            health_check: None,
+            health_check_interval: std::time::Duration::from_secs(10),
+            max_fails: 3,
+            fail_timeout: std::time::Duration::from_secs(30),
+        }
+    }
+}
+
+impl EndpointPolicy {
+    /// `add_headers()` reads `endpoint-add-headers`, the environment variables are read now so
+    /// they are only needed once a request is passed on to the endpoint.
+    pub fn add_headers(&self) -> fastn_core::Result<std::collections::HashMap<String, String>> {
+        app::AppTemp::parse_config(&self.add_headers)
+    }
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub(crate) struct EndpointPolicyTemp {
+    #[serde(rename = "endpoint-connect-timeout")]
+    pub connect_timeout: Option<i64>,
+    #[serde(rename = "endpoint-read-timeout")]
+    pub read_timeout: Option<i64>,
+    #[serde(rename = "endpoint-retries")]
+    pub retries: Option<i64>,
+    #[serde(rename = "endpoint-strip-headers")]
+    pub strip_headers: Vec<String>,
+    #[serde(rename = "endpoint-add-headers")]
+    pub add_headers: Vec<String>,
+    #[serde(rename = "endpoint-forward-cookies")]
+    pub forward_cookies: bool,
+    #[serde(rename = "endpoint-upstreams")]
+    pub upstreams: Vec<String>,
+    #[serde(rename = "endpoint-balance")]
+    pub balance: Option<String>,
+    #[serde(rename = "endpoint-health-check")]
+    pub health_check: Option<String>,
+    #[serde(rename = "endpoint-health-check-interval")]
+    pub health_check_interval: Option<i64>,
+    #[serde(rename = "endpoint-max-fails")]
+    pub max_fails: Option<i64>,
+    #[serde(rename = "endpoint-fail-timeout")]
+    pub fail_timeout: Option<i64>,
+}
+
+impl EndpointPolicyTemp {
+    pub(crate) fn into_policy(self) -> fastn_core::Result<EndpointPolicy> {
+        let default = EndpointPolicy::default();
+        let seconds = |s: i64| std::time::Duration::from_secs(s.max(0) as u64);
+        Ok(EndpointPolicy {
+            connect_timeout: self
+                .connect_timeout
+                .map(seconds)
+                .unwrap_or(default.connect_timeout),
+            read_timeout: self.read_timeout.map(seconds),
+            retries: self.retries.map(|r| r.max(0) as u32).unwrap_or_default(),
+            strip_headers: self.strip_headers,
+            add_headers: self.add_headers,
+            forward_cookies: self.forward_cookies,
+            upstreams: self.upstreams,
+            balance: match self.balance {
+                Some(balance) => balance.parse()?,
+                None => default.balance,
+            },
+            health_check: self.health_check,
+            health_check_interval: self
+                .health_check_interval
+                .map(|s| seconds(s.max(1)))
+                .unwrap_or(default.health_check_interval),
+            max_fails: self
+                .max_fails
+                .map(|f| f.max(1) as u32)
+                .unwrap_or(default.max_fails),
+            fail_timeout: self
+                .fail_timeout
+                .map(seconds)
+                .unwrap_or(default.fail_timeout),
+        })
+    }
+}
+
 /// PackageTemp is a struct that is used for mapping the `fastn.package` data in FASTN.ftd file. It is
 /// not used elsewhere in program, it is immediately converted to `fastn_core::Package` struct during
 /// deserialization process
@@ -698,15 +987,28 @@ pub(crate) struct PackageTemp {
     pub backend: bool,
     #[serde(rename = "backend-headers")]
     pub backend_headers: Option<Vec<BackendHeader>>,
+    #[serde(rename = "backend-http-timeout")]
+    pub backend_http_timeout: Option<i64>,
+    #[serde(rename = "backend-http-connect-timeout")]
+    pub backend_http_connect_timeout: Option<i64>,
+    #[serde(rename = "backend-allowed-hosts")]
+    pub backend_allowed_hosts: Vec<String>,
+    #[serde(rename = "backend-fuel")]
+    pub backend_fuel: Option<i64>,
+    #[serde(rename = "backend-max-memory")]
+    pub backend_max_memory: Option<i64>,
+    #[serde(rename = "backend-timeout")]
+    pub backend_timeout: Option<i64>,
+    #[serde(flatten)]
+    pub endpoint_policy: EndpointPolicyTemp,
     #[serde(rename = "icon")]
     pub icon: Option<ftd::ImageSrc>,
 }
 
 impl PackageTemp {
-    pub fn into_package(self) -> Package {
-        // TODO: change this method to: `validate(self) -> fastn_core::Result<fastn_core::Package>` and do all
-        //       validations in it. Like a package must not have both translation-of and
-        //       `translations` set.
+    pub fn into_package(self) -> fastn_core::Result<Package> {
+        // TODO: do all validations in this method. Like a package must not have both
+        //       translation-of and `translations` set.
         let translation_of = self
             .translation_of
             .as_ref()
@@ -718,7 +1020,33 @@ impl PackageTemp {
             .map(|v| Package::new(&v))
             .collect::<Vec<Package>>();
 
-        Package {
+        let backend_http = {
+            let default = BackendHttp::default();
+            let seconds = |s: i64| std::time::Duration::from_secs(s.max(0) as u64);
+            BackendHttp {
+                timeout: self
+                    .backend_http_timeout
+                    .map(seconds)
+                    .unwrap_or(default.timeout),
+                connect_timeout: self
+                    .backend_http_connect_timeout
+                    .map(seconds)
+                    .unwrap_or(default.connect_timeout),
+                allowed_hosts: self.backend_allowed_hosts,
+            }
+        };
+
+        let backend_limits = BackendLimits {
+            fuel: self.backend_fuel.map(|f| f.max(0) as u64),
+            max_memory: self
+                .backend_max_memory
+                .map(|m| (m.max(0) as usize).saturating_mul(1024 * 1024)),
+            timeout: self
+                .backend_timeout
+                .map(|s| std::time::Duration::from_secs(s.max(0) as u64)),
+        };
+
+        Ok(Package {
             name: self.name,
             versioned: self.versioned,
             translation_of: Box::new(translation_of),
@@ -742,10 +1070,67 @@ impl PackageTemp {
             dynamic_urls_temp: None,
             favicon: self.favicon,
             endpoint: self.endpoint,
+            endpoint_policy: self.endpoint_policy.into_policy()?,
             backend: self.backend,
             backend_headers: self.backend_headers,
+            backend_http,
+            backend_limits,
             apps: vec![],
+            auth_providers: vec![],
             icon: self.icon,
-        }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn backend_allowed_hosts() {
+        let http = super::BackendHttp {
+            allowed_hosts: vec!["*.supabase.co".to_string(), "API.github.com".to_string()],
+            ..Default::default()
+        };
+        assert!(http.is_allowed("foo.supabase.co"));
+        assert!(http.is_allowed("a.b.supabase.co"));
+        assert!(http.is_allowed("Foo.SUPABASE.co"));
+        assert!(http.is_allowed("api.github.com"));
+        assert!(http.is_allowed("api.GitHub.com"));
+        // `*.` only allows the subdomains
+        assert!(!http.is_allowed("supabase.co"));
+        assert!(!http.is_allowed("evilsupabase.co"));
+        assert!(!http.is_allowed("supabase.co.evil.com"));
+        assert!(!http.is_allowed("github.com"));
+        assert!(!http.is_allowed(""));
+
+        let http = super::BackendHttp {
+            allowed_hosts: vec!["*".to_string()],
+            ..Default::default()
+        };
+        assert!(http.is_allowed("example.com"));
+        assert!(!super::BackendHttp::default().is_allowed("example.com"));
+    }
+
+    #[test]
+    fn backend_limits_restrict() {
+        let package = super::BackendLimits {
+            fuel: Some(100),
+            max_memory: Some(64),
+            timeout: None,
+        };
+        let dependency = super::BackendLimits {
+            fuel: Some(1000),
+            max_memory: None,
+            timeout: Some(std::time::Duration::from_secs(10)),
+        };
+        let limits = dependency.restrict(&package);
+        assert_eq!(limits.fuel, Some(100));
+        assert_eq!(limits.max_memory, Some(64));
+        assert_eq!(limits.timeout, Some(std::time::Duration::from_secs(10)));
+        assert_eq!(package.restrict(&dependency).fuel, Some(100));
+
+        let none = super::BackendLimits::default().restrict(&Default::default());
+        assert_eq!(none.fuel, None);
+        assert_eq!(none.max_memory, None);
+        assert_eq!(none.timeout, None);
     }
 }
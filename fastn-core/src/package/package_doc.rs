@@ -361,7 +361,7 @@ pub(crate) async fn read_ftd_2022(
     let mut all_packages = config.all_packages.borrow_mut();
     let current_package = all_packages
         .get(main.package_name.as_str())
-        .unwrap_or(&config.package);
+        .unwrap_or(config.package.as_ref());
 
     let mut lib = fastn_core::Library2022 {
         config: lib_config,
@@ -401,12 +401,16 @@ pub(crate) async fn read_ftd_2022(
     let node = ftd::node::NodeData::from_rt(executor);
     let html_ui = ftd::html1::HtmlUI::from_node_data(node, "main")?;
 
-    all_packages.extend(lib.config.all_packages.into_inner());
+    // `lib` started with a copy of `all_packages`, so it has every package in it
+    *all_packages = lib.config.all_packages.into_inner();
     drop(all_packages);
 
     config
         .downloaded_assets
         .extend(lib.config.downloaded_assets);
+    config
+        .build_inputs
+        .replace(lib.config.build_inputs.into_inner());
 
     let font_style = config.get_font_style();
     let file_content = fastn_core::utils::replace_markers_2022(
@@ -433,7 +437,7 @@ pub(crate) async fn read_ftd_2021(
     let mut all_packages = config.all_packages.borrow_mut();
     let current_package = all_packages
         .get(main.package_name.as_str())
-        .unwrap_or(&config.package);
+        .unwrap_or(config.package.as_ref());
 
     let mut lib = fastn_core::Library2 {
         config: lib_config,
@@ -467,7 +471,8 @@ pub(crate) async fn read_ftd_2021(
         }
     };
 
-    all_packages.extend(lib.config.all_packages.into_inner());
+    // `lib` started with a copy of `all_packages`, so it has every package in it
+    *all_packages = lib.config.all_packages.into_inner();
     drop(all_packages);
 
     config
@@ -492,6 +497,21 @@ pub(crate) async fn read_ftd_2021(
     Ok(file_content.into())
 }
 
+/// `output_path()` is where `process_ftd()` writes the document `id` in `.build`.
+pub(crate) fn output_path(id: &str) -> String {
+    if id.eq("FASTN.ftd") {
+        return output_path("-.ftd");
+    }
+    if id.contains("index.ftd") {
+        id.replace("index.ftd", "index.html")
+    } else {
+        id.replace(
+            ".ftd",
+            format!("{}index.html", std::path::MAIN_SEPARATOR).as_str(),
+        )
+    }
+}
+
 pub(crate) async fn process_ftd(
     config: &mut fastn_core::Config,
     main: &fastn_core::Document,
@@ -523,19 +543,11 @@ pub(crate) async fn process_ftd(
         main
     };
 
-    let file_rel_path = if main.id.contains("index.ftd") {
-        main.id.replace("index.ftd", "index.html")
-    } else {
-        main.id.replace(
-            ".ftd",
-            format!("{}index.html", std::path::MAIN_SEPARATOR).as_str(),
-        )
-    };
+    let file_rel_path = output_path(main.id.as_str());
 
     let response = read_ftd(config, &main, base_url, !no_static).await?;
-    fastn_core::utils::write(
-        &config.build_dir(),
-        file_rel_path.as_str(),
+    fastn_core::utils::update(
+        config.build_dir().join(file_rel_path.as_str()),
         response.as_slice(),
     )
     .await?;
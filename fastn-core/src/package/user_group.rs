@@ -154,6 +154,12 @@ pub struct UserGroupTemp {
     pub twitter_retweet: Vec<String>,
     #[serde(rename = "-twitter-retweet")]
     pub excluded_twitter_retweet: Vec<String>,
+    /// Any identity as `<key>: <value>`, e.g. `identity: oidc-email: foo@bar.com` for the
+    /// identities of `fastn.auth-provider`s
+    #[serde(rename = "identity")]
+    pub identity: Vec<String>,
+    #[serde(rename = "-identity")]
+    pub excluded_identity: Vec<String>,
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -192,6 +198,29 @@ impl UserGroup {
         }
     }
 
+    /// `qualified()` returns the group `package/<id>` imported from `package`. Its child
+    /// groups are prefixed with `package` as well, so they keep referring to the groups of
+    /// `package` and not to the groups of the importing package.
+    fn qualified(&self, package: &str) -> UserGroup {
+        let qualify = |ids: &[String]| -> Vec<String> {
+            ids.iter()
+                .map(|id| {
+                    if id.contains('/') {
+                        id.to_string()
+                    } else {
+                        format!("{}/{}", package, id)
+                    }
+                })
+                .collect()
+        };
+        UserGroup {
+            id: format!("{}/{}", package, self.id),
+            groups: qualify(self.groups.as_slice()),
+            excluded_groups: qualify(self.excluded_groups.as_slice()),
+            ..self.clone()
+        }
+    }
+
     // TODO: Need to handle excluded_identities and excluded_groups
     // Maybe Logic: group.identities + (For all group.groups(g.group - g.excluded_group)).identities
     //              - group.excluded_identities
@@ -421,6 +450,17 @@ impl UserGroupTemp {
             "-twitter-retweet",
             self.excluded_twitter_retweet,
         ));
+        identities.extend(
+            self.identity
+                .iter()
+                .flat_map(|i| parse_identities(i.as_str())),
+        );
+        excluded_identities.extend(
+            self.excluded_identity
+                .iter()
+                .flat_map(|i| parse_identities(i.as_str()))
+                .map(|i| UserIdentity::from(format!("-{}", i.key).as_str(), i.value.as_str())),
+        );
 
         Ok(UserGroup {
             id: self.id,
@@ -466,23 +506,125 @@ pub fn get_identities(
     Ok(identities)
 }
 
-// TODO Doc: group-id should not contain / in it
-pub fn user_groups_by_package(
-    config: &fastn_core::Config,
-    package: &str,
-) -> fastn_core::Result<Vec<UserGroup>> {
-    // TODO: Need to fix it, It should not read groups from individual FASTN.ftd file
-    // IT should read groups from package.groups
+/// `resolve_groups()` imports the groups of other packages, referred to as
+/// `<dependency>/<group-id>` by the groups or the sitemap of the package, into
+/// `config.package.groups`, under the same `<dependency>/<group-id>` key. It fails if a
+/// referred group does not exist, or if a group ends up including itself.
+pub(crate) async fn resolve_groups(config: &mut fastn_core::Config) -> fastn_core::Result<()> {
+    // (group id, who refers to it, referred from a dependency)
+    let mut pending: Vec<(String, String, bool)> = vec![];
+    for group in config.package.groups.values() {
+        for id in group.groups.iter().chain(group.excluded_groups.iter()) {
+            pending.push((id.to_string(), format!("user-group `{}`", group.id), false));
+        }
+    }
+    if let Some(sitemap) = config.package.sitemap.as_ref() {
+        for id in sitemap.group_ids() {
+            pending.push((id, "the sitemap".to_string(), false));
+        }
+    }
+
+    while let Some((id, referred_by, from_dependency)) = pending.pop() {
+        if config.package.groups.contains_key(id.as_str()) {
+            continue;
+        }
+        // TODO Doc: group-id should not contain / in it
+        let (package_name, group_id) = match id.rsplit_once('/') {
+            Some(ids) => ids,
+            None => {
+                return Err(fastn_core::Error::GroupNotFound {
+                    id: id.to_string(),
+                    message: format!("user-group referred by {} is not defined", referred_by),
+                })
+            }
+        };
 
-    // let package = config.find_package_by_name(package).await?;
-    // Ok(package.groups.into_values().collect_vec())
+        let package = if package_name.eq(config.package.name.as_str()) {
+            config.package.as_ref().clone()
+        } else {
+            let dependency = match config.package.dependencies.iter().find(|d| {
+                d.package.name.eq(package_name) || d.alias.as_deref() == Some(package_name)
+            }) {
+                Some(dependency) => dependency.package.clone(),
+                // the groups of a dependency can refer to the packages it depends on
+                None if from_dependency => fastn_core::Package::new(package_name),
+                None => {
+                    return Err(fastn_core::Error::GroupNotFound {
+                        id: id.to_string(),
+                        message: format!(
+                            "`{}`, referred by {}, is not a dependency of {}",
+                            package_name, referred_by, config.package.name
+                        ),
+                    })
+                }
+            };
+            config.resolve_package(&dependency).await.map_err(|e| {
+                fastn_core::Error::GroupNotFound {
+                    id: id.to_string(),
+                    message: format!("could not resolve package `{}`: {}", package_name, e),
+                }
+            })?
+        };
 
-    let fastn_document = config.get_fastn_document(package)?;
-    fastn_document
-        .get::<Vec<UserGroupTemp>>("fastn#user-group")?
-        .into_iter()
-        .map(|g| g.to_user_group())
-        .collect()
+        let group = package
+            .groups
+            .get(group_id)
+            .ok_or_else(|| fastn_core::Error::GroupNotFound {
+                id: id.to_string(),
+                message: format!(
+                    "package `{}` has no user-group `{}`, referred by {}",
+                    package.name, group_id, referred_by
+                ),
+            })?
+            .qualified(package.name.as_str());
+        for child in group.groups.iter().chain(group.excluded_groups.iter()) {
+            pending.push((child.to_string(), format!("user-group `{}`", id), true));
+        }
+        std::sync::Arc::make_mut(&mut config.package)
+            .groups
+            .insert(id, group);
+    }
+
+    check_cycles(&config.package.groups)
+}
+
+/// `check_cycles()` fails if any of `groups` includes itself, directly or through other
+/// groups.
+fn check_cycles(groups: &std::collections::BTreeMap<String, UserGroup>) -> fastn_core::Result<()> {
+    fn visit(
+        id: &str,
+        groups: &std::collections::BTreeMap<String, UserGroup>,
+        path: &mut Vec<String>,
+        checked: &mut std::collections::HashSet<String>,
+    ) -> fastn_core::Result<()> {
+        if checked.contains(id) {
+            return Ok(());
+        }
+        if let Some(start) = path.iter().position(|p| p.eq(id)) {
+            return Err(fastn_core::Error::PackageError {
+                message: format!(
+                    "user-group includes itself: {} -> {}",
+                    path[start..].join(" -> "),
+                    id
+                ),
+            });
+        }
+        path.push(id.to_string());
+        if let Some(group) = groups.get(id) {
+            for child in group.groups.iter().chain(group.excluded_groups.iter()) {
+                visit(child.as_str(), groups, path, checked)?;
+            }
+        }
+        path.pop();
+        checked.insert(id.to_string());
+        Ok(())
+    }
+
+    let mut checked = std::collections::HashSet::new();
+    for id in groups.keys() {
+        visit(id.as_str(), groups, &mut vec![], &mut checked)?;
+    }
+    Ok(())
 }
 
 /// group_id: "<package_name>/<group_id>" or "<group_id>"
@@ -490,14 +632,24 @@ pub fn user_group_by_id(
     config: &fastn_core::Config,
     group_id: &str,
 ) -> fastn_core::Result<Option<UserGroup>> {
-    // If group `id` does not contain `/` then it is current package group_id
-    let (package, group_id) = group_id
-        .rsplit_once('/')
-        .unwrap_or((&config.package.name, group_id));
+    // groups of other packages used by this package are imported by `resolve_groups()`
+    if let Some(group) = config.package.groups.get(group_id) {
+        return Ok(Some(group.clone()));
+    }
+    let (package, group_id) = match group_id.rsplit_once('/') {
+        Some(ids) => ids,
+        None => return Ok(None),
+    };
+    if package.eq(config.package.name.as_str()) {
+        return Ok(config.package.groups.get(group_id).cloned());
+    }
 
-    Ok(user_groups_by_package(config, package)?
-        .into_iter()
-        .find(|g| g.id.as_str() == group_id))
+    Ok(config
+        .all_packages
+        .borrow()
+        .get(package)
+        .and_then(|p| p.groups.get(group_id))
+        .map(|g| g.qualified(package)))
 }
 
 /// return true if: any input identity is match with any input group's identity.
@@ -662,4 +814,50 @@ mod tests {
     // TODO:
     #[test]
     fn get_identities() {}
+
+    fn group(id: &str, groups: &[&str]) -> (String, super::UserGroup) {
+        (
+            id.to_string(),
+            super::UserGroup {
+                title: None,
+                id: id.to_string(),
+                identities: vec![],
+                excluded_identities: vec![],
+                groups: groups.iter().map(|g| g.to_string()).collect(),
+                excluded_groups: vec![],
+                description: None,
+            },
+        )
+    }
+
+    #[test]
+    fn check_cycles() {
+        let groups = std::collections::BTreeMap::from([
+            group("admins", &["dep.com/admins"]),
+            group("dep.com/admins", &["dep.com/owners"]),
+            group("dep.com/owners", &[]),
+        ]);
+        assert!(super::check_cycles(&groups).is_ok());
+
+        let groups = std::collections::BTreeMap::from([
+            group("admins", &["dep.com/admins"]),
+            group("dep.com/admins", &["dep.com/owners"]),
+            group("dep.com/owners", &["dep.com/admins"]),
+        ]);
+        match super::check_cycles(&groups) {
+            Err(fastn_core::Error::PackageError { message }) => assert_eq!(
+                message,
+                "user-group includes itself: dep.com/admins -> dep.com/owners -> dep.com/admins"
+            ),
+            r => panic!("expected a cycle, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn qualified() {
+        let (_, g) = group("admins", &["owners", "other.com/staff"]);
+        let g = g.qualified("dep.com");
+        assert_eq!(g.id, "dep.com/admins");
+        assert_eq!(g.groups, vec!["dep.com/owners", "other.com/staff"]);
+    }
 }
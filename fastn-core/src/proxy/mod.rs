@@ -0,0 +1,519 @@
+pub(crate) mod upstream;
+
+fn client_builder(connect_timeout: std::time::Duration) -> reqwest::Client {
+    // TODO: Connection Pool, It by default holds the connection pool internally
+    reqwest::ClientBuilder::new()
+        .http2_adaptive_window(true)
+        .tcp_keepalive(std::time::Duration::new(150, 0))
+        .tcp_nodelay(true)
+        .connect_timeout(connect_timeout)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap()
+}
+
+/// One client for every connect timeout used by the endpoints, as the connect timeout is set
+/// on the client and not on its requests.
+static CLIENTS: once_cell::sync::Lazy<
+    antidote::Mutex<
+        std::collections::HashMap<std::time::Duration, std::sync::Arc<reqwest::Client>>,
+    >,
+> = once_cell::sync::Lazy::new(|| antidote::Mutex::new(std::collections::HashMap::new()));
+
+fn client(connect_timeout: std::time::Duration) -> std::sync::Arc<reqwest::Client> {
+    CLIENTS
+        .lock()
+        .entry(connect_timeout)
+        .or_insert_with(|| std::sync::Arc::new(client_builder(connect_timeout)))
+        .clone()
+}
+
+/// Headers that only apply to a single connection, so are not passed on by the proxy, see
+/// https://www.rfc-editor.org/rfc/rfc9110#section-7.6.1
+const HOP_BY_HOP_HEADERS: [&str; 9] = [
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+pub(crate) fn is_hop_by_hop(header: &str) -> bool {
+    HOP_BY_HOP_HEADERS
+        .iter()
+        .any(|h| h.eq_ignore_ascii_case(header))
+}
+
+fn url(host: &str, path: &str, query_string: &str) -> String {
+    format!(
+        "{}/{}{}",
+        host.trim_end_matches('/'),
+        path.trim_start_matches('/'),
+        if query_string.is_empty() {
+            "".to_string()
+        } else {
+            format!("?{}", query_string)
+        }
+    )
+}
+
+/// The headers of `req` to send on to the endpoint, along with `req_headers`, the headers
+/// from the endpoint config, like `X-FASTN-USER-ID`.
+fn headers(
+    req: &fastn_core::http::Request,
+    req_headers: &std::collections::HashMap<String, String>,
+    policy: &fastn_core::package::EndpointPolicy,
+) -> fastn_core::Result<reqwest::header::HeaderMap> {
+    let mut headers = req.headers().to_owned();
+
+    // TODO: Some extra headers, possibly Authentication header
+    // Authentication header can come from system environment variable
+    // env file path set in FASTN.ftd file
+    // We can get the data from request parameter
+    // Flow will be
+    // 1. Add Movie ftd page
+    // 2. fastn will forward request to microservice and that service will redirect to /movie/?id=5
+    // fastn will send back this response to browser
+    // browser will request now /movie/?id=5
+    // on this movie.ftd page we will call a processor: `request-data` which will give the
+    // `id` from the request query parameter. Than, we will use processor: `http` to call http api
+    // `/api/movie/?id=<id>` of movie-db service, this will happen while fastn is converting ftd code
+    // to html, so all this happening on server side. So we can say server side rendering.
+
+    // The endpoint can only trust the identity and forwarding headers set by fastn, so the ones
+    // sent by the client are dropped
+    for header in fastn_core::utils::ignore_headers()
+        .into_iter()
+        .chain(HOP_BY_HOP_HEADERS)
+        .chain([
+            "forwarded",
+            "x-fastn-user-id",
+            "x-forwarded-host",
+            "x-forwarded-proto",
+        ])
+        .chain(policy.strip_headers.iter().map(|h| h.trim()))
+    {
+        headers.remove(header);
+    }
+
+    // headers
+
+    for (header_key, header_value) in policy.add_headers()?.iter().chain(req_headers) {
+        headers.insert(
+            reqwest::header::HeaderName::from_bytes(header_key.as_bytes()).map_err(|e| {
+                fastn_core::Error::PackageError {
+                    message: format!("invalid endpoint header {}: {}", header_key, e),
+                }
+            })?,
+            reqwest::header::HeaderValue::from_str(header_value.as_str()).map_err(|e| {
+                fastn_core::Error::PackageError {
+                    message: format!("invalid value of endpoint header {}: {}", header_key, e),
+                }
+            })?,
+        );
+    }
+
+    headers.insert(
+        reqwest::header::USER_AGENT,
+        reqwest::header::HeaderValue::from_static("fastn"),
+    );
+
+    // https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/X-Forwarded-For
+    if let Some(ip) = req.get_ip() {
+        let forwarded_for = match headers.get("x-forwarded-for").and_then(|v| v.to_str().ok()) {
+            Some(forwarded_for) => format!("{}, {}", forwarded_for, ip),
+            None => ip,
+        };
+        if let Ok(value) = reqwest::header::HeaderValue::from_str(forwarded_for.as_str()) {
+            headers.insert("x-forwarded-for", value);
+        }
+    }
+    if let Ok(value) = reqwest::header::HeaderValue::from_str(req.scheme().as_str()) {
+        headers.insert("x-forwarded-proto", value);
+    }
+    if let Ok(value) = reqwest::header::HeaderValue::from_str(req.host().as_str()) {
+        headers.insert("x-forwarded-host", value);
+    }
+
+    // The login session cookies of fastn are never sent, the endpoint gets the user from
+    // `X-FASTN-USER-ID` instead
+    headers.remove(reqwest::header::COOKIE);
+    if policy.forward_cookies {
+        let session_cookies: Vec<String> = fastn_core::auth::providers()
+            .iter()
+            .map(|provider| provider.cookie_name().to_string())
+            .collect();
+        let cookies = req
+            .cookies()
+            .iter()
+            .filter(|(k, _)| !session_cookies.contains(k))
+            .map(|(k, v)| format!("{}={}", k, v).replace(';', "%3B"))
+            .collect::<Vec<_>>()
+            .join(";");
+        if !cookies.is_empty() {
+            if let Ok(value) = reqwest::header::HeaderValue::from_str(cookies.as_str()) {
+                headers.insert(reqwest::header::COOKIE, value);
+            }
+        }
+    }
+
+    Ok(headers)
+}
+
+fn is_idempotent(method: &reqwest::Method) -> bool {
+    matches!(
+        *method,
+        reqwest::Method::GET
+            | reqwest::Method::HEAD
+            | reqwest::Method::PUT
+            | reqwest::Method::DELETE
+            | reqwest::Method::OPTIONS
+            | reqwest::Method::TRACE
+    )
+}
+
+/// Sends `request`, waiting at most `read_timeout` for the endpoint to start the response.
+async fn send(
+    client: &reqwest::Client,
+    request: reqwest::Request,
+    read_timeout: Option<std::time::Duration>,
+) -> fastn_core::Result<reqwest::Response> {
+    let url = request.url().to_string();
+    let response = client.execute(request);
+    Ok(match read_timeout {
+        Some(read_timeout) => tokio::time::timeout(read_timeout, response)
+            .await
+            .map_err(|_| fastn_core::Error::EndpointTimeout { url })??,
+        None => response.await?,
+    })
+}
+
+/// `with_read_timeout()` ends `body` with an error if the endpoint takes longer than
+/// `read_timeout` to send the next part of it.
+pub(crate) fn with_read_timeout<S>(
+    body: S,
+    read_timeout: std::time::Duration,
+) -> impl futures::Stream<Item = Result<actix_web::web::Bytes, std::io::Error>>
+where
+    S: futures::Stream<Item = reqwest::Result<actix_web::web::Bytes>> + 'static,
+{
+    futures::stream::unfold(Some(Box::pin(body)), move |body| async move {
+        let mut body = body?;
+        match tokio::time::timeout(read_timeout, futures::StreamExt::next(&mut body)).await {
+            Ok(Some(Ok(bytes))) => Some((Ok(bytes), Some(body))),
+            Ok(Some(Err(e))) => {
+                Some((Err(std::io::Error::new(std::io::ErrorKind::Other, e)), None))
+            }
+            Ok(None) => None,
+            Err(_) => Some((
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "endpoint did not send the response in time",
+                )),
+                None,
+            )),
+        }
+    })
+}
+
+// This method will connect client request to the out of the world
+#[tracing::instrument(skip_all)]
+pub(crate) async fn get_out(
+    host: &str,
+    req: fastn_core::http::Request,
+    path: &str,
+    mount_point: &str,
+    req_headers: &std::collections::HashMap<String, String>,
+    policy: &fastn_core::package::EndpointPolicy,
+) -> fastn_core::Result<fastn_core::http::Response> {
+    // TODO: It should be part of fastn_core::Request::uri()
+    // let path = &req.uri().to_string()[1..];
+
+    tracing::info!("proxy_request: {} {} {}", req.method(), path, host);
+
+    let mut proxy_request = reqwest::Request::new(
+        match req.method() {
+            "GET" => reqwest::Method::GET,
+            "POST" => reqwest::Method::POST,
+            "PUT" => reqwest::Method::PUT,
+            "DELETE" => reqwest::Method::DELETE,
+            "PATCH" => reqwest::Method::PATCH,
+            "HEAD" => reqwest::Method::HEAD,
+            "OPTIONS" => reqwest::Method::OPTIONS,
+            "TRACE" => reqwest::Method::TRACE,
+            "CONNECT" => reqwest::Method::CONNECT,
+            _ => reqwest::Method::GET,
+        },
+        // the upstream the request goes to is set below
+        reqwest::Url::parse(url(host, path, req.query_string()).as_str())?,
+    );
+
+    *proxy_request.headers_mut() = headers(&req, req_headers, policy)?;
+
+    // A long body is sent on as it comes from the client: the part already read, followed by
+    // the rest, so the service can start on it before the upload is complete.
+    *proxy_request.body_mut() = Some(match req.body_stream() {
+        Some(rest) => reqwest::Body::wrap_stream(futures::StreamExt::chain(
+            futures::stream::once(futures::future::ready(Ok(req.body_bytes()))),
+            rest,
+        )),
+        None => req.body_bytes().into(),
+    });
+
+    let client = client(policy.connect_timeout);
+    let mut retries = if is_idempotent(proxy_request.method()) {
+        policy.retries
+    } else {
+        0
+    };
+    let mut proxy_request = Some(proxy_request);
+    let response = loop {
+        let upstream = upstream::connect(host, mount_point, policy);
+        // a streamed body can not be sent again, so such requests are only tried once
+        let mut request = match proxy_request.as_ref().and_then(|r| r.try_clone()) {
+            Some(request) if retries > 0 => request,
+            _ => {
+                retries = 0;
+                proxy_request
+                    .take()
+                    .expect("the request is only taken on the last try")
+            }
+        };
+        *request.url_mut() =
+            reqwest::Url::parse(url(upstream.host(), path, req.query_string()).as_str())?;
+
+        let response = send(&client, request, policy.read_timeout).await;
+        match response {
+            Ok(ref response) if !upstream::is_failure(response.status()) => upstream.succeeded(),
+            _ => upstream.failed(),
+        }
+        match response {
+            Err(e) if retries > 0 => {
+                tracing::info!("proxy_request: retrying {} {}: {}", req.method(), path, e);
+                retries -= 1;
+            }
+            response => break response?,
+        }
+    };
+
+    Ok(fastn_core::http::ResponseBuilder::from_reqwest(
+        response,
+        mount_point,
+        req.is_xhr(),
+        policy.read_timeout,
+    ))
+}
+
+/// `is_websocket()` checks if `req` asks to upgrade the connection to a WebSocket.
+pub(crate) fn is_websocket(req: &actix_web::HttpRequest) -> bool {
+    req.headers()
+        .get(actix_web::http::header::UPGRADE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.eq_ignore_ascii_case("websocket"))
+        .unwrap_or(false)
+}
+
+/// The WebSocket handshake headers, set by the WebSocket client for the endpoint connection.
+const WEBSOCKET_HANDSHAKE_HEADERS: [&str; 3] = [
+    "sec-websocket-key",
+    "sec-websocket-version",
+    "sec-websocket-extensions",
+];
+
+// WebSocket counterpart of `get_out()`: connects to the endpoint, and then upgrades the client
+// connection and passes the messages between the two till either side closes.
+#[tracing::instrument(skip_all)]
+pub(crate) async fn websocket(
+    host: &str,
+    req: fastn_core::http::Request,
+    path: &str,
+    mount_point: &str,
+    req_headers: &std::collections::HashMap<String, String>,
+    policy: &fastn_core::package::EndpointPolicy,
+    actix_req: &actix_web::HttpRequest,
+    payload: actix_web::web::Payload,
+) -> fastn_core::Result<fastn_core::http::Response> {
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+    let upstream = upstream::connect(host, mount_point, policy);
+    let url = url(upstream.host(), path, req.query_string()).replacen("http", "ws", 1);
+    tracing::info!("proxy_websocket: {}", url);
+
+    let mut endpoint_request = url
+        .as_str()
+        .into_client_request()
+        .map_err(|e| fastn_core::Error::generic(format!("websocket {}: {}", url, e)))?;
+    for (name, value) in headers(&req, req_headers, policy)?.iter() {
+        if WEBSOCKET_HANDSHAKE_HEADERS.contains(&name.as_str())
+            || endpoint_request.headers().contains_key(name)
+        {
+            continue;
+        }
+        endpoint_request
+            .headers_mut()
+            .insert(name.clone(), value.clone());
+    }
+
+    // the endpoint is connected to first, so if it refuses the connection the client gets a
+    // plain http error
+    let connect = tokio::time::timeout(
+        policy.connect_timeout,
+        tokio_tungstenite::connect_async(endpoint_request),
+    );
+    let (endpoint, endpoint_response) = match connect.await {
+        Ok(Ok(connected)) => {
+            upstream.succeeded();
+            connected
+        }
+        Ok(Err(tokio_tungstenite::tungstenite::Error::Http(response))) => {
+            if upstream::is_failure(response.status()) {
+                upstream.failed();
+            }
+            let mut response_builder = actix_web::HttpResponse::build(response.status());
+            return Ok(match response.into_body() {
+                Some(body) => response_builder.body(body),
+                None => response_builder.finish(),
+            });
+        }
+        Ok(Err(e)) => {
+            upstream.failed();
+            return Ok(fastn_core::server_error!(
+                "websocket endpoint {} failed: {}",
+                url,
+                e
+            ));
+        }
+        Err(_) => {
+            upstream.failed();
+            return Err(fastn_core::Error::EndpointTimeout { url });
+        }
+    };
+
+    let (mut response, session, messages) = actix_ws::handle(actix_req, payload)
+        .map_err(|e| fastn_core::Error::generic(e.to_string()))?;
+    // the sub-protocol picked by the endpoint
+    if let Some(protocol) = endpoint_response
+        .headers()
+        .get(actix_web::http::header::SEC_WEBSOCKET_PROTOCOL)
+    {
+        response.headers_mut().insert(
+            actix_web::http::header::SEC_WEBSOCKET_PROTOCOL,
+            protocol.clone(),
+        );
+    }
+
+    actix_web::rt::spawn(tunnel(session, messages, endpoint, upstream));
+    Ok(response)
+}
+
+type EndpointStream =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// Passes the messages between the client and the endpoint till either of them closes the
+/// connection, the `upstream` connection is in progress till then.
+async fn tunnel(
+    mut session: actix_ws::Session,
+    mut messages: actix_ws::MessageStream,
+    endpoint: EndpointStream,
+    _upstream: upstream::Connection,
+) {
+    use futures::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let (mut to_endpoint, mut from_endpoint) = endpoint.split();
+    // a message the client is sending in parts, sent on once complete
+    let mut continuation: Option<(bool, Vec<u8>)> = None;
+
+    loop {
+        tokio::select! {
+            message = messages.next() => {
+                let message = match message {
+                    Some(Ok(message)) => message,
+                    _ => break,
+                };
+                let message = match message {
+                    actix_ws::Message::Text(text) => Message::Text(text.to_string()),
+                    actix_ws::Message::Binary(bytes) => Message::Binary(bytes.to_vec()),
+                    actix_ws::Message::Ping(bytes) => Message::Ping(bytes.to_vec()),
+                    actix_ws::Message::Pong(bytes) => Message::Pong(bytes.to_vec()),
+                    actix_ws::Message::Close(reason) => {
+                        let _ = to_endpoint
+                            .send(Message::Close(reason.map(|r| {
+                                tokio_tungstenite::tungstenite::protocol::CloseFrame {
+                                    code: u16::from(r.code).into(),
+                                    reason: r.description.unwrap_or_default().into(),
+                                }
+                            })))
+                            .await;
+                        break;
+                    }
+                    actix_ws::Message::Continuation(item) => {
+                        match item {
+                            actix_ws::Item::FirstText(bytes) => {
+                                continuation = Some((true, bytes.to_vec()));
+                            }
+                            actix_ws::Item::FirstBinary(bytes) => {
+                                continuation = Some((false, bytes.to_vec()));
+                            }
+                            actix_ws::Item::Continue(bytes) => {
+                                if let Some((_, ref mut data)) = continuation {
+                                    data.extend_from_slice(&bytes);
+                                }
+                            }
+                            actix_ws::Item::Last(bytes) => {
+                                if let Some((is_text, mut data)) = continuation.take() {
+                                    data.extend_from_slice(&bytes);
+                                    let message = if is_text {
+                                        Message::Text(String::from_utf8_lossy(&data).to_string())
+                                    } else {
+                                        Message::Binary(data)
+                                    };
+                                    if to_endpoint.send(message).await.is_err() {
+                                        break;
+                                    }
+                                }
+                            }
+                        }
+                        continue;
+                    }
+                    actix_ws::Message::Nop => continue,
+                };
+                if to_endpoint.send(message).await.is_err() {
+                    break;
+                }
+            }
+            message = from_endpoint.next() => {
+                let message = match message {
+                    Some(Ok(message)) => message,
+                    _ => break,
+                };
+                let sent = match message {
+                    Message::Text(text) => session.text(text).await,
+                    Message::Binary(bytes) => session.binary(bytes).await,
+                    Message::Ping(bytes) => session.ping(&bytes).await,
+                    Message::Pong(bytes) => session.pong(&bytes).await,
+                    Message::Close(frame) => {
+                        let _ = session
+                            .close(frame.map(|f| actix_ws::CloseReason {
+                                code: u16::from(f.code).into(),
+                                description: Some(f.reason.to_string()),
+                            }))
+                            .await;
+                        return;
+                    }
+                    Message::Frame(_) => Ok(()),
+                };
+                if sent.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    let _ = to_endpoint.close().await;
+    let _ = session.close(None).await;
+}
@@ -0,0 +1,438 @@
+// Upstreams are the replicas an endpoint runs on: the endpoint itself and its
+// `endpoint-upstreams`, see `fastn_core::package::EndpointPolicy`.
+//
+// Every request to the endpoint goes to one of them, picked with `endpoint-balance`, skipping
+// the ones that are down:
+//
+// - Active health checks: with `endpoint-health-check`, the path is requested on every upstream
+//   every `endpoint-health-check-interval` seconds, an upstream is down while the check fails.
+// - Passive ejection: an upstream that fails `endpoint-max-fails` requests in a row, can not be
+//   reached or answers with 502, 503 or 504, is not used for `endpoint-fail-timeout` seconds.
+//
+// If all the upstreams are down, requests are still sent to them, so the endpoint gets a chance
+// to come back. `/-/status/` shows the upstreams of the endpoints fastn has passed requests to,
+// to the writers of the package and on the machine fastn runs on.
+//
+// The upstreams are forgotten when the config is read again, see `evict()`, so changes to the
+// endpoints in FASTN.ftd are picked up and the health checks of removed endpoints stop.
+
+/// The upstreams of an endpoint, keyed by the upstream hosts and the policy, so endpoints with the
+/// same upstreams and policy share them.
+static POOLS: once_cell::sync::Lazy<
+    antidote::Mutex<std::collections::BTreeMap<String, std::sync::Arc<Pool>>>,
+> = once_cell::sync::Lazy::new(|| antidote::Mutex::new(std::collections::BTreeMap::new()));
+
+struct Pool {
+    /// Where the endpoint is on the fastn site
+    mount_point: String,
+    policy: fastn_core::package::EndpointPolicy,
+    upstreams: Vec<Upstream>,
+    next: std::sync::atomic::AtomicUsize,
+}
+
+struct Upstream {
+    host: String,
+    /// Requests in progress
+    active: std::sync::atomic::AtomicUsize,
+    /// Failures in a row
+    fails: std::sync::atomic::AtomicU32,
+    ejected_until: antidote::Mutex<Option<std::time::Instant>>,
+    /// Result of the last health check
+    healthy: std::sync::atomic::AtomicBool,
+    requests: std::sync::atomic::AtomicU64,
+    failures: std::sync::atomic::AtomicU64,
+}
+
+impl Pool {
+    fn new(
+        mount_point: &str,
+        policy: &fastn_core::package::EndpointPolicy,
+        hosts: Vec<String>,
+    ) -> Pool {
+        Pool {
+            mount_point: mount_point.to_string(),
+            policy: policy.clone(),
+            upstreams: hosts.into_iter().map(Upstream::new).collect(),
+            next: Default::default(),
+        }
+    }
+
+    /// `connect()` picks the upstream the next request goes to.
+    fn connect(self: &std::sync::Arc<Self>) -> Connection {
+        let mut up = (0..self.upstreams.len())
+            .filter(|i| self.upstreams[*i].is_up())
+            .collect::<Vec<_>>();
+        if up.is_empty() {
+            up = (0..self.upstreams.len()).collect();
+        }
+        let index = match self.policy.balance {
+            fastn_core::package::Balance::RoundRobin => {
+                up[self.next.fetch_add(1, std::sync::atomic::Ordering::Relaxed) % up.len()]
+            }
+            fastn_core::package::Balance::LeastConnections => *up
+                .iter()
+                .min_by_key(|i| {
+                    self.upstreams[**i]
+                        .active
+                        .load(std::sync::atomic::Ordering::Relaxed)
+                })
+                .expect("a pool has at least one upstream"),
+        };
+        let upstream = &self.upstreams[index];
+        upstream
+            .active
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        upstream
+            .requests
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Connection {
+            pool: self.clone(),
+            index,
+        }
+    }
+}
+
+impl Upstream {
+    fn new(host: String) -> Upstream {
+        Upstream {
+            host,
+            active: Default::default(),
+            fails: Default::default(),
+            ejected_until: antidote::Mutex::new(None),
+            healthy: std::sync::atomic::AtomicBool::new(true),
+            requests: Default::default(),
+            failures: Default::default(),
+        }
+    }
+
+    fn is_ejected(&self) -> bool {
+        matches!(*self.ejected_until.lock(), Some(until) if until > std::time::Instant::now())
+    }
+
+    fn is_up(&self) -> bool {
+        self.healthy.load(std::sync::atomic::Ordering::Relaxed) && !self.is_ejected()
+    }
+}
+
+/// The scheme, host and port of `url`, the part of an upstream that is used.
+fn origin(url: &str) -> String {
+    match url::Url::parse(url.trim()) {
+        Ok(url) => url.origin().ascii_serialization(),
+        Err(_) => url.trim().trim_end_matches('/').to_string(),
+    }
+}
+
+fn pool(
+    host: &str,
+    mount_point: &str,
+    policy: &fastn_core::package::EndpointPolicy,
+) -> std::sync::Arc<Pool> {
+    let mut hosts = vec![origin(host)];
+    for upstream in policy.upstreams.iter().map(|u| origin(u)) {
+        if !hosts.contains(&upstream) {
+            hosts.push(upstream);
+        }
+    }
+    let key = format!("{} {:?}", hosts.join(" "), policy);
+
+    let mut pools = POOLS.lock();
+    if let Some(pool) = pools.get(&key) {
+        return pool.clone();
+    }
+    let pool = std::sync::Arc::new(Pool::new(mount_point, policy, hosts));
+    if let Some(path) = policy.health_check.as_ref() {
+        tokio::spawn(health_check(
+            std::sync::Arc::downgrade(&pool),
+            path.to_string(),
+        ));
+    }
+    pools.insert(key, pool.clone());
+    pool
+}
+
+/// `evict()` forgets the upstreams of all the endpoints, the health checks stop once the requests
+/// in progress to them are done. `fastn_core::config::cache::invalidate()` calls it.
+pub(crate) fn evict() {
+    POOLS.lock().clear();
+}
+
+async fn health_check(pool: std::sync::Weak<Pool>, path: String) {
+    loop {
+        let pool = match pool.upgrade() {
+            Some(pool) => pool,
+            None => return,
+        };
+        let client = super::client(pool.policy.connect_timeout);
+        for upstream in pool.upstreams.iter() {
+            let url = super::url(upstream.host.as_str(), path.as_str(), "");
+            let healthy = match client
+                .get(url.as_str())
+                .timeout(pool.policy.health_check_interval)
+                .send()
+                .await
+            {
+                Ok(response) => {
+                    response.status().is_success() || response.status().is_redirection()
+                }
+                Err(_) => false,
+            };
+            if upstream
+                .healthy
+                .swap(healthy, std::sync::atomic::Ordering::Relaxed)
+                != healthy
+            {
+                fastn_core::warning!(
+                    "endpoint {} is {}",
+                    upstream.host,
+                    if healthy { "up" } else { "down" }
+                );
+            }
+        }
+        let interval = pool.policy.health_check_interval;
+        drop(pool);
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// A request in progress to an upstream.
+pub(crate) struct Connection {
+    pool: std::sync::Arc<Pool>,
+    index: usize,
+}
+
+impl Connection {
+    fn upstream(&self) -> &Upstream {
+        &self.pool.upstreams[self.index]
+    }
+
+    pub(crate) fn host(&self) -> &str {
+        self.upstream().host.as_str()
+    }
+
+    pub(crate) fn succeeded(&self) {
+        self.upstream()
+            .fails
+            .store(0, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub(crate) fn failed(&self) {
+        let upstream = self.upstream();
+        upstream
+            .failures
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let fails = upstream
+            .fails
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+            + 1;
+        if fails >= self.pool.policy.max_fails {
+            upstream
+                .fails
+                .store(0, std::sync::atomic::Ordering::Relaxed);
+            *upstream.ejected_until.lock() =
+                Some(std::time::Instant::now() + self.pool.policy.fail_timeout);
+            fastn_core::warning!(
+                "endpoint {} failed {} times, not used for {}s",
+                upstream.host,
+                fails,
+                self.pool.policy.fail_timeout.as_secs()
+            );
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.upstream()
+            .active
+            .fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+/// `connect()` picks the upstream of the endpoint at `host` the next request goes to.
+pub(crate) fn connect(
+    host: &str,
+    mount_point: &str,
+    policy: &fastn_core::package::EndpointPolicy,
+) -> Connection {
+    pool(host, mount_point, policy).connect()
+}
+
+/// `is_failure()` checks if the endpoint answered with `status` because it could not handle the
+/// request at all, like when it is overloaded or restarting.
+pub(crate) fn is_failure(status: reqwest::StatusCode) -> bool {
+    matches!(
+        status,
+        reqwest::StatusCode::BAD_GATEWAY
+            | reqwest::StatusCode::SERVICE_UNAVAILABLE
+            | reqwest::StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+#[derive(serde::Serialize)]
+struct PoolStatus {
+    #[serde(rename = "mount-point")]
+    mount_point: String,
+    balance: fastn_core::package::Balance,
+    #[serde(rename = "health-check")]
+    health_check: Option<String>,
+    upstreams: Vec<UpstreamStatus>,
+}
+
+#[derive(serde::Serialize)]
+struct UpstreamStatus {
+    host: String,
+    up: bool,
+    healthy: bool,
+    ejected: bool,
+    active: usize,
+    requests: u64,
+    failures: u64,
+}
+
+// route: /-/status/
+pub(crate) async fn status(
+    req: &fastn_core::http::Request,
+) -> fastn_core::Result<fastn_core::http::Response> {
+    let is_local = req
+        .get_ip()
+        .and_then(|ip| ip.parse::<std::net::IpAddr>().ok())
+        .map(|ip| ip.is_loopback())
+        .unwrap_or(false);
+    if !is_local {
+        let config = fastn_core::config::cache::read(req).await?;
+        if !config.can_write(req, "/").await? {
+            return Ok(fastn_core::unauthorised!(
+                "only the writers of the package can see the status of the endpoints"
+            ));
+        }
+    }
+
+    let pools = POOLS
+        .lock()
+        .values()
+        .map(|pool| PoolStatus {
+            mount_point: pool.mount_point.clone(),
+            balance: pool.policy.balance,
+            health_check: pool.policy.health_check.clone(),
+            upstreams: pool
+                .upstreams
+                .iter()
+                .map(|upstream| UpstreamStatus {
+                    host: upstream.host.clone(),
+                    up: upstream.is_up(),
+                    healthy: upstream.healthy.load(std::sync::atomic::Ordering::Relaxed),
+                    ejected: upstream.is_ejected(),
+                    active: upstream.active.load(std::sync::atomic::Ordering::Relaxed),
+                    requests: upstream.requests.load(std::sync::atomic::Ordering::Relaxed),
+                    failures: upstream.failures.load(std::sync::atomic::Ordering::Relaxed),
+                })
+                .collect(),
+        })
+        .collect::<Vec<_>>();
+    fastn_core::http::api_ok(pools)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn origin() {
+        assert_eq!(
+            super::origin("http://127.0.0.1:8001/api/"),
+            "http://127.0.0.1:8001"
+        );
+        assert_eq!(super::origin("https://example.com"), "https://example.com");
+    }
+
+    fn pool(
+        balance: fastn_core::package::Balance,
+        fail_timeout: std::time::Duration,
+    ) -> std::sync::Arc<super::Pool> {
+        let policy = fastn_core::package::EndpointPolicy {
+            balance,
+            max_fails: 2,
+            fail_timeout,
+            ..Default::default()
+        };
+        std::sync::Arc::new(super::Pool::new(
+            "/api/",
+            &policy,
+            vec![
+                "http://a".to_string(),
+                "http://b".to_string(),
+                "http://c".to_string(),
+            ],
+        ))
+    }
+
+    #[test]
+    fn round_robin() {
+        let pool = pool(
+            fastn_core::package::Balance::RoundRobin,
+            std::time::Duration::from_secs(30),
+        );
+        let hosts = (0..6)
+            .map(|_| pool.connect().host().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            hosts,
+            vec!["http://a", "http://b", "http://c", "http://a", "http://b", "http://c"]
+        );
+    }
+
+    #[test]
+    fn least_connections() {
+        let pool = pool(
+            fastn_core::package::Balance::LeastConnections,
+            std::time::Duration::from_secs(30),
+        );
+        let a = pool.connect();
+        let b = pool.connect();
+        assert_eq!((a.host(), b.host()), ("http://a", "http://b"));
+        // the finished request frees `b`, `a` is still busy
+        drop(b);
+        assert_eq!(pool.connect().host(), "http://b");
+        let b = pool.connect();
+        assert_eq!(b.host(), "http://b");
+        assert_eq!(pool.connect().host(), "http://c");
+        drop((a, b));
+    }
+
+    #[test]
+    fn ejection_and_recovery() {
+        let pool = pool(
+            fastn_core::package::Balance::RoundRobin,
+            std::time::Duration::from_millis(200),
+        );
+        let a = pool.connect();
+        assert_eq!(a.host(), "http://a");
+        a.failed();
+        assert!(pool.upstreams[0].is_up());
+        a.failed();
+        assert!(!pool.upstreams[0].is_up());
+        drop(a);
+
+        for _ in 0..4 {
+            assert_ne!(pool.connect().host(), "http://a");
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(250));
+        assert!(pool.upstreams[0].is_up());
+        assert!((0..3).any(|_| pool.connect().host() == "http://a"));
+    }
+
+    #[test]
+    fn all_down() {
+        let pool = pool(
+            fastn_core::package::Balance::RoundRobin,
+            std::time::Duration::from_secs(30),
+        );
+        for upstream in pool.upstreams.iter() {
+            upstream
+                .healthy
+                .store(false, std::sync::atomic::Ordering::Relaxed);
+        }
+        // the requests still go to the upstreams, so they can come back
+        assert_eq!(pool.connect().host(), "http://a");
+        assert_eq!(pool.connect().host(), "http://b");
+    }
+}
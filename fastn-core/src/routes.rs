@@ -0,0 +1,519 @@
+// The route table of a package lists every url the package serves, in the order `fastn serve`
+// tries them for a request:
+//
+// 1. `/favicon.ico`, `/FASTN.ftd` and `/-/...`: fastn's own urls, and the `/-/<package>/` urls
+//    of the package, its apps and its dependencies.
+// 2. Seo files: `sitemap.xml`, `robots.txt` and the feeds, if the package does not have them,
+//    see `fastn_core::seo`.
+// 3. Apps: `/<mount-point>/...` is served by the app, from its own sitemap, dynamic urls and
+//    files, and then from its `end-point`.
+// 4. Sitemap: urls listed in `fastn.sitemap` with a `document`.
+// 5. Dynamic urls: the patterns in `fastn.dynamic-urls`, the first one listed that matches the
+//    url is used.
+// 6. Files: `foo.ftd`, `foo/index.ftd`, `foo.md`, `foo/README.md` or `foo/index.md` at `/foo/`.
+// 7. Backends: the `backend.wasm` of the package, if it has `backend: true`, for the urls none
+//    of the above serve, and the ones of its dependencies for their `/-/<dependency>/` urls.
+// 8. Endpoints: `/<mount-point>/...` of a dependency with an `endpoint` and a `mount-point`,
+//    and `/-/<package>/...` of the package and the dependencies with an `endpoint`.
+//
+// The table is built from the same config and functions `serve()` uses to pick the route.
+//
+// Static files, like images, are always served on their own path.
+//
+// A conflict is a url more than one route claims. If the precedence above does not decide which
+// one serves it, like `foo.ftd` and `foo/index.ftd`, or two apps on the same mount point, the
+// conflict is an error and `fastn serve` and `fastn build` refuse to start. Otherwise it is a
+// warning, as a route it shadows can never be reached.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteKind {
+    Seo,
+    App,
+    Sitemap,
+    Dynamic,
+    File,
+    Backend,
+    Endpoint,
+}
+
+impl std::fmt::Display for RouteKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RouteKind::Seo => "seo",
+            RouteKind::App => "app",
+            RouteKind::Sitemap => "sitemap",
+            RouteKind::Dynamic => "dynamic",
+            RouteKind::File => "file",
+            RouteKind::Backend => "backend",
+            RouteKind::Endpoint => "endpoint",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Route {
+    pub kind: RouteKind,
+    /// `/foo/`, `/blog/<slug:post>/`, or `/todos/*` for mount points
+    pub url: String,
+    /// The document, app package or endpoint serving the url
+    pub target: String,
+    path_parameters: Vec<fastn_core::sitemap::PathParams>,
+}
+
+impl Route {
+    fn new(kind: RouteKind, url: String, target: String) -> Route {
+        Route {
+            kind,
+            url,
+            target,
+            path_parameters: vec![],
+        }
+    }
+
+    /// The mount point of an app, backend or endpoint route, without the slashes.
+    fn mount_point(&self) -> Option<&str> {
+        self.url
+            .strip_suffix('*')
+            .map(|mp| mp.trim_matches('/'))
+            .filter(|_| {
+                matches!(
+                    self.kind,
+                    RouteKind::App | RouteKind::Backend | RouteKind::Endpoint
+                )
+            })
+    }
+
+    /// `matches()` checks if the route serves `url`, a `/foo/` style url.
+    fn matches(&self, url: &str) -> bool {
+        match self.kind {
+            // same as `Config::get_mountpoint_sanitized_path()`
+            RouteKind::App | RouteKind::Backend | RouteKind::Endpoint => url
+                .trim_start_matches('/')
+                .starts_with(self.mount_point().unwrap_or_default()),
+            RouteKind::Dynamic => {
+                fastn_core::sitemap::utils::url_match(url, self.path_parameters.as_slice())
+                    .map(|(matched, _)| matched)
+                    .unwrap_or(false)
+            }
+            RouteKind::Seo | RouteKind::Sitemap | RouteKind::File => {
+                fastn_core::utils::ids_matches(&self.url, url)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub error: bool,
+    pub message: String,
+}
+
+impl Conflict {
+    fn error(message: String) -> Conflict {
+        Conflict {
+            error: true,
+            message,
+        }
+    }
+
+    fn warning(message: String) -> Conflict {
+        Conflict {
+            error: false,
+            message,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RouteTable {
+    /// In the order of precedence
+    pub routes: Vec<Route>,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl RouteTable {
+    pub async fn build(config: &fastn_core::Config) -> fastn_core::Result<RouteTable> {
+        let package = &config.package;
+        let mut routes = vec![];
+
+        for (path, _, _) in fastn_core::seo::files(config) {
+            routes.push(Route::new(
+                RouteKind::Seo,
+                format!("/{}", path),
+                package.name.to_string(),
+            ));
+        }
+
+        for app in package.apps.iter() {
+            routes.push(Route::new(
+                RouteKind::App,
+                mount_point_url(app.mount_point.as_str()),
+                match app.end_point.as_ref() {
+                    Some(ep) => format!("{} ({})", app.package.name, ep),
+                    None => app.package.name.to_string(),
+                },
+            ));
+        }
+
+        if let Some(sitemap) = package.sitemap.as_ref() {
+            for item in fastn_core::sitemap::items(&sitemap.sections) {
+                if let Some(document) = item.document {
+                    if !fastn_core::config::utils::is_http_url(item.id) {
+                        routes.push(Route::new(
+                            RouteKind::Sitemap,
+                            url(item.id),
+                            document.to_string(),
+                        ));
+                    }
+                }
+            }
+        }
+
+        if let Some(dynamic_urls) = package.dynamic_urls.as_ref() {
+            for item in fastn_core::sitemap::items(&dynamic_urls.sections) {
+                if item.path_parameters.iter().any(|p| p.is_named_param()) {
+                    routes.push(Route {
+                        path_parameters: item.path_parameters.to_vec(),
+                        ..Route::new(
+                            RouteKind::Dynamic,
+                            url(item.id),
+                            item.document.map(ToString::to_string).unwrap_or_default(),
+                        )
+                    });
+                }
+            }
+        }
+
+        for file in config.get_files(package).await? {
+            if let fastn_core::File::Ftd(_) | fastn_core::File::Markdown(_) = file {
+                let id = file.get_id();
+                if id.eq("FASTN.ftd") {
+                    continue;
+                }
+                routes.push(Route::new(
+                    RouteKind::File,
+                    url(fastn_core::utils::id_to_path(id.as_str()).as_str()),
+                    id,
+                ));
+            }
+        }
+
+        // `serve()` uses the backend of the package `Config::find_package_by_id()` finds
+        if package.backend {
+            routes.push(Route::new(
+                RouteKind::Backend,
+                "/*".to_string(),
+                format!("{} (backend.wasm)", package.name),
+            ));
+        }
+        for dep in package.dependencies.iter().filter(|d| d.package.backend) {
+            routes.push(Route::new(
+                RouteKind::Backend,
+                package_url(dep.package.name.as_str()),
+                format!("{} (backend.wasm)", dep.package.name),
+            ));
+        }
+
+        // in the order `fastn_core::config::utils::get_clean_url()` looks for them, the
+        // endpoints of the apps are in their app routes
+        for (dep, ep, mp) in package.dep_with_ep_and_mp() {
+            routes.push(Route::new(
+                RouteKind::Endpoint,
+                mount_point_url(mp),
+                format!("{} ({})", dep.name, ep),
+            ));
+        }
+        if let Some(ep) = package.endpoint.as_ref() {
+            routes.push(Route::new(
+                RouteKind::Endpoint,
+                package_url(package.name.as_str()),
+                format!("{} ({})", package.name, ep),
+            ));
+        }
+        for dep in package.dependencies.iter() {
+            if let Some(ep) = dep.endpoint.as_ref() {
+                routes.push(Route::new(
+                    RouteKind::Endpoint,
+                    package_url(dep.package.name.as_str()),
+                    format!("{} ({})", dep.package.name, ep),
+                ));
+            }
+        }
+
+        let conflicts = conflicts(routes.as_slice());
+        Ok(RouteTable { routes, conflicts })
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.conflicts.iter().any(|c| c.error)
+    }
+}
+
+/// `check()` builds the route table of the package and fails if it has conflicting routes. The
+/// other conflicts are printed as warnings.
+pub(crate) async fn check(config: &fastn_core::Config) -> fastn_core::Result<()> {
+    let table = RouteTable::build(config).await?;
+    for conflict in table.conflicts.iter().filter(|c| !c.error) {
+        fastn_core::warning!("{}", conflict.message);
+    }
+    if table.has_errors() {
+        return Err(fastn_core::Error::PackageError {
+            message: format!(
+                "conflicting routes, see `fastn routes`:\n{}",
+                table
+                    .conflicts
+                    .iter()
+                    .filter(|c| c.error)
+                    .map(|c| c.message.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ),
+        });
+    }
+    Ok(())
+}
+
+fn url(id: &str) -> String {
+    let id = id.split('#').next().unwrap_or_default();
+    let id = id.trim().trim_matches('/');
+    if id.is_empty() {
+        "/".to_string()
+    } else {
+        format!("/{}/", id)
+    }
+}
+
+fn mount_point_url(mount_point: &str) -> String {
+    format!("{}*", url(mount_point))
+}
+
+/// The `/-/<package>/` urls of `package`.
+fn package_url(package: &str) -> String {
+    mount_point_url(format!("-/{}", package.trim_matches('/')).as_str())
+}
+
+fn conflicts(routes: &[Route]) -> Vec<Conflict> {
+    let mut conflicts = vec![];
+    for (i, route) in routes.iter().enumerate() {
+        for earlier in routes[..i].iter() {
+            if let Some(conflict) = conflict(earlier, route) {
+                conflicts.push(conflict);
+            }
+        }
+    }
+    conflicts
+}
+
+/// `conflict()` checks if `route` and the route `earlier` in the table claim the same urls.
+fn conflict(earlier: &Route, route: &Route) -> Option<Conflict> {
+    match (earlier.kind, route.kind) {
+        (RouteKind::App, RouteKind::App)
+        | (RouteKind::App, RouteKind::Endpoint)
+        | (RouteKind::Endpoint, RouteKind::Endpoint) => {
+            // `todos` and `todos/api` overlap, `todos` and `todos-api` do not
+            let (a, b) = (
+                format!("{}/", earlier.mount_point()?),
+                format!("{}/", route.mount_point()?),
+            );
+            (a.starts_with(b.as_str()) || b.starts_with(a.as_str())).then(|| {
+                Conflict::error(format!(
+                    "{} and {} are mounted on overlapping urls: {} and {}",
+                    earlier.target, route.target, earlier.url, route.url
+                ))
+            })
+        }
+        (RouteKind::App, _) => earlier.matches(&route.url).then(|| {
+            Conflict::warning(format!(
+                "{} {} is never served, {} is mounted on {}",
+                route.kind, route.url, earlier.target, earlier.url
+            ))
+        }),
+        (RouteKind::Sitemap, RouteKind::Sitemap) => {
+            (earlier.url.eq(&route.url) && earlier.target.ne(&route.target)).then(|| {
+                Conflict::error(format!(
+                    "{} is in the sitemap for both {} and {}",
+                    route.url, earlier.target, route.target
+                ))
+            })
+        }
+        (RouteKind::Dynamic, RouteKind::Dynamic) => {
+            if earlier.url.eq(&route.url) {
+                Some(Conflict::error(format!(
+                    "{} is in the dynamic urls for both {} and {}",
+                    route.url, earlier.target, route.target
+                )))
+            } else {
+                overlap(&earlier.path_parameters, &route.path_parameters).then(|| {
+                    Conflict::warning(format!(
+                        "dynamic urls {} and {} match the same urls, {} is used for them",
+                        earlier.url, route.url, earlier.url
+                    ))
+                })
+            }
+        }
+        (RouteKind::Dynamic, RouteKind::File) => earlier.matches(&route.url).then(|| {
+            Conflict::warning(format!(
+                "{} is never served at {}, it matches the dynamic url {}",
+                route.target, route.url, earlier.url
+            ))
+        }),
+        // both `foo.ftd` and `foo/index.ftd` are served at `/foo/`
+        (RouteKind::File, RouteKind::File) => earlier.url.eq(&route.url).then(|| {
+            Conflict::error(format!(
+                "{} and {} are both served at {}, remove one of them",
+                earlier.target, route.target, route.url
+            ))
+        }),
+        _ => None,
+    }
+}
+
+/// `overlap()` checks if there is a url both `a` and `b` match. It assumes `regex(..)`
+/// parameters can match any part.
+fn overlap(a: &[fastn_core::sitemap::PathParams], b: &[fastn_core::sitemap::PathParams]) -> bool {
+    use fastn_core::sitemap::{ParamType, PathParams};
+
+    /// The params without and with the first one, if it is optional.
+    fn optional_first(params: &[PathParams]) -> Option<(Vec<PathParams>, Vec<PathParams>)> {
+        match params.split_first()? {
+            (
+                PathParams::NamedParm {
+                    index,
+                    name,
+                    param_type,
+                    optional: true,
+                },
+                rest,
+            ) => {
+                let mut with = vec![PathParams::named(
+                    *index,
+                    name.to_string(),
+                    param_type.clone(),
+                )];
+                with.extend_from_slice(rest);
+                Some((rest.to_vec(), with))
+            }
+            _ => None,
+        }
+    }
+
+    fn is_path(param: &PathParams) -> bool {
+        matches!(
+            param,
+            PathParams::NamedParm {
+                param_type: ParamType::Path,
+                ..
+            }
+        )
+    }
+
+    fn part_overlap(a: &PathParams, b: &PathParams) -> bool {
+        match (a, b) {
+            (PathParams::ValueParam { value: a, .. }, PathParams::ValueParam { value: b, .. }) => {
+                a.eq(b)
+            }
+            (PathParams::ValueParam { value, .. }, PathParams::NamedParm { param_type, .. })
+            | (PathParams::NamedParm { param_type, .. }, PathParams::ValueParam { value, .. }) => {
+                fastn_core::sitemap::utils::get_value_type(value, param_type).is_some()
+            }
+            (
+                PathParams::NamedParm { param_type: a, .. },
+                PathParams::NamedParm { param_type: b, .. },
+            ) => match (a, b) {
+                (ParamType::Enum(values), t) | (t, ParamType::Enum(values)) => values
+                    .iter()
+                    .any(|v| fastn_core::sitemap::utils::get_value_type(v, t).is_some()),
+                (ParamType::Boolean, t) | (t, ParamType::Boolean) => {
+                    !matches!(t, ParamType::Integer | ParamType::Decimal | ParamType::Date)
+                }
+                (ParamType::Date, ParamType::Integer | ParamType::Decimal)
+                | (ParamType::Integer | ParamType::Decimal, ParamType::Date) => false,
+                _ => true,
+            },
+        }
+    }
+
+    if let Some((without, with)) = optional_first(a) {
+        return overlap(&without, b) || overlap(&with, b);
+    }
+    if let Some((without, with)) = optional_first(b) {
+        return overlap(a, &without) || overlap(a, &with);
+    }
+
+    let ((first_a, rest_a), (first_b, rest_b)) = match (a.split_first(), b.split_first()) {
+        (None, None) => return true,
+        (Some(a), Some(b)) => (a, b),
+        // an url with more parts, optional ones are already expanded above
+        _ => return false,
+    };
+    if is_path(first_a) || is_path(first_b) {
+        return true;
+    }
+
+    part_overlap(first_a, first_b) && overlap(rest_a, rest_b)
+}
+
+#[cfg(test)]
+mod tests {
+    fn dynamic(url: &str) -> super::Route {
+        super::Route {
+            path_parameters: fastn_core::sitemap::utils::parse_named_params(url).unwrap(),
+            ..super::Route::new(super::RouteKind::Dynamic, url.to_string(), "".to_string())
+        }
+    }
+
+    fn file(id: &str) -> super::Route {
+        super::Route::new(
+            super::RouteKind::File,
+            super::url(fastn_core::utils::id_to_path(id).as_str()),
+            id.to_string(),
+        )
+    }
+
+    #[test]
+    fn index_conflict() {
+        let conflicts =
+            super::conflicts(&[file("foo.ftd"), file("bar.ftd"), file("foo/index.ftd")]);
+        assert_eq!(
+            conflicts,
+            vec![super::Conflict::error(
+                "foo.ftd and foo/index.ftd are both served at /foo/, remove one of them"
+                    .to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn overlap() {
+        let overlap = |a: &str, b: &str| {
+            super::overlap(&dynamic(a).path_parameters, &dynamic(b).path_parameters)
+        };
+        assert!(overlap("/a/<string:x>/", "/a/<integer:y>/"));
+        assert!(!overlap("/a/<integer:x>/", "/a/<date:y>/"));
+        assert!(!overlap("/a/<enum(x|y):x>/", "/a/<integer:y>/"));
+        assert!(overlap("/a/<string:x?>/", "/a/"));
+        assert!(overlap("/a/<path:x>/", "/a/b/<string:y>/"));
+        assert!(!overlap("/a/<string:x>/b/", "/a/<string:x>/c/"));
+    }
+
+    #[test]
+    fn mount_points() {
+        let endpoint = |url: &str| {
+            super::Route::new(super::RouteKind::Endpoint, url.to_string(), url.to_string())
+        };
+        let conflicts = super::conflicts(&[
+            endpoint("/todos/*"),
+            endpoint("/todos-api/*"),
+            endpoint("/todos/api/*"),
+        ]);
+        assert_eq!(conflicts.len(), 1);
+        assert!(conflicts[0].message.contains("/todos/* and /todos/api/*"));
+    }
+
+    #[test]
+    fn dynamic_shadows_file() {
+        let conflicts = super::conflicts(&[dynamic("/blog/<slug:post>/"), file("blog/about.ftd")]);
+        assert_eq!(conflicts.len(), 1);
+        assert!(!conflicts[0].error);
+    }
+}
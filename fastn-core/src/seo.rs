@@ -0,0 +1,432 @@
+// Files for search engines and feed readers, generated from the sitemap of the package, unless
+// the package has a file of the same name:
+//
+// - `/sitemap.xml`: the public urls of the sitemap.
+// - `/robots.txt`: allows everything but fastn's own `/-/` urls, and points to `/sitemap.xml`.
+//   The urls only readers can see are not listed, as anyone can read robots.txt, they are left
+//   out of `/sitemap.xml` and need a login anyway.
+// - `/<section>/feed.xml`: an RSS or Atom feed of the pages of a sitemap section with
+//   `feed: rss` or `feed: atom`, newest first if the pages have a `date`:
+//
+//   # Blog: /blog/
+//     feed: atom
+//
+//   ## Hello World: /blog/hello-world/
+//     date: 2023-01-10
+//     description: Our first post
+//
+// Urls in them are made absolute with the `canonical-url` of the package, or
+// `https://<package-name>/`.
+//
+// A url is public if it is not `skip`ped, and either has no readers or is not `confidential`,
+// the same as `Config::can_read()`.
+
+/// `file()` is the generated file at `path`, a url without the leading `/`, and its content type.
+pub(crate) fn file(config: &fastn_core::Config, path: &str) -> Option<(String, mime_guess::Mime)> {
+    let path = path.trim_start_matches('/');
+    if !matches!(path, "sitemap.xml" | "robots.txt") && !path.ends_with("feed.xml") {
+        return None;
+    }
+    files(config)
+        .into_iter()
+        .find(|(p, _, _)| p.eq(path))
+        .map(|(_, content, mime)| (content, mime))
+}
+
+/// `files()` generates all the files, as (path, content, content type).
+pub(crate) fn files(config: &fastn_core::Config) -> Vec<(String, String, mime_guess::Mime)> {
+    let sitemap = match config.package.sitemap.as_ref() {
+        Some(sitemap) => sitemap,
+        None => return vec![],
+    };
+    // files in the package are used as they are
+    generate(
+        sitemap,
+        &config.package.groups,
+        base_url(&config.package).as_str(),
+    )
+    .into_iter()
+    .filter(|(path, _, _)| !config.root.join(path).exists())
+    .collect()
+}
+
+/// `generate()` is `files()` for the package `sitemap` and `groups`, before the files in the
+/// package are taken out.
+fn generate(
+    sitemap: &fastn_core::sitemap::Sitemap,
+    groups: &Groups,
+    base: &str,
+) -> Vec<(String, String, mime_guess::Mime)> {
+    let xml: mime_guess::Mime = "application/xml".parse().unwrap();
+    let mut files = vec![
+        (
+            "sitemap.xml".to_string(),
+            sitemap_xml(sitemap, groups, base),
+            xml.clone(),
+        ),
+        (
+            "robots.txt".to_string(),
+            robots_txt(base),
+            mime_guess::mime::TEXT_PLAIN,
+        ),
+    ];
+    for section in sitemap.sections.iter() {
+        let kind = match section.extra_data.get("feed") {
+            Some(kind) => kind.as_str(),
+            None => continue,
+        };
+        let path = format!("{}feed.xml", path(section.id.as_str()));
+        let content = match kind {
+            "rss" => rss(sitemap, groups, section, base),
+            "atom" => atom(sitemap, groups, section, base, path.as_str()),
+            kind => {
+                fastn_core::warning!(
+                    "section {}: unknown feed {}, expected rss or atom",
+                    section.id,
+                    kind
+                );
+                continue;
+            }
+        };
+        files.push((path, content, xml.clone()));
+    }
+    files
+}
+
+type Groups = std::collections::BTreeMap<String, fastn_core::user_group::UserGroup>;
+
+fn base_url(package: &fastn_core::Package) -> String {
+    let url = package
+        .canonical_url
+        .clone()
+        .unwrap_or_else(|| format!("https://{}/", package.name.trim_matches('/')));
+    format!("{}/", url.trim_end_matches('/'))
+}
+
+/// The path of the url `id`, like `blog/hello-world/`, without the leading `/`.
+fn path(id: &str) -> String {
+    let id = id.split('#').next().unwrap_or_default().trim_matches('/');
+    if id.is_empty() {
+        "".to_string()
+    } else {
+        format!("{}/", id)
+    }
+}
+
+fn is_public(
+    sitemap: &fastn_core::sitemap::Sitemap,
+    groups: &Groups,
+    item: &fastn_core::sitemap::Item,
+) -> bool {
+    if item.skip || fastn_core::config::utils::is_http_url(item.id) {
+        return false;
+    }
+    let (readers, confidential) = sitemap.readers(item.id, groups);
+    readers.is_empty() || !confidential
+}
+
+fn sitemap_xml(sitemap: &fastn_core::sitemap::Sitemap, groups: &Groups, base: &str) -> String {
+    let mut urls = vec![];
+    let mut xml = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n"
+        .to_string();
+    for item in fastn_core::sitemap::items(&sitemap.sections) {
+        let url = format!("{}{}", base, path(item.id));
+        if !is_public(sitemap, groups, &item) || urls.contains(&url) {
+            continue;
+        }
+        xml.push_str(format!("  <url>\n    <loc>{}</loc>\n", escape(url.as_str())).as_str());
+        if let Some(date) = date(&item) {
+            xml.push_str(format!("    <lastmod>{}</lastmod>\n", date).as_str());
+        }
+        xml.push_str("  </url>\n");
+        urls.push(url);
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+fn robots_txt(base: &str) -> String {
+    format!(
+        "User-agent: *\nDisallow: /-/\n\nSitemap: {}sitemap.xml\n",
+        base
+    )
+}
+
+/// The public pages of a feed section, with the newest first if they have dates.
+fn feed_items<'a>(
+    sitemap: &fastn_core::sitemap::Sitemap,
+    groups: &Groups,
+    section: &'a fastn_core::sitemap::section::Section,
+) -> Vec<fastn_core::sitemap::Item<'a>> {
+    let mut items = fastn_core::sitemap::items(std::slice::from_ref(section))
+        .into_iter()
+        .skip(1)
+        .filter(|item| is_public(sitemap, groups, item))
+        .collect::<Vec<_>>();
+    // stable, so the pages without a date stay in the sitemap order
+    items.sort_by(|a, b| date(b).cmp(&date(a)));
+    items
+}
+
+/// The `date` of the page, `YYYY-MM-DD`.
+fn date<'a>(item: &fastn_core::sitemap::Item<'a>) -> Option<&'a str> {
+    item.extra_data
+        .get("date")
+        .map(|d| d.as_str())
+        .filter(|d| fastn_core::sitemap::utils::is_date(d))
+}
+
+fn rss(
+    sitemap: &fastn_core::sitemap::Sitemap,
+    groups: &Groups,
+    section: &fastn_core::sitemap::section::Section,
+    base: &str,
+) -> String {
+    let title = section.title.as_deref().unwrap_or(section.id.as_str());
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <rss version=\"2.0\">\n<channel>\n  <title>{}</title>\n  <link>{}</link>\n  \
+        <description>{}</description>\n",
+        escape(title),
+        escape(format!("{}{}", base, path(section.id.as_str())).as_str()),
+        escape(
+            section
+                .extra_data
+                .get("description")
+                .map(|d| d.as_str())
+                .unwrap_or(title)
+        ),
+    );
+    for item in feed_items(sitemap, groups, section) {
+        let url = escape(format!("{}{}", base, path(item.id)).as_str());
+        xml.push_str(
+            format!(
+                "  <item>\n    <title>{}</title>\n    <link>{}</link>\n    <guid>{}</guid>\n",
+                escape(item.title.unwrap_or(item.id)),
+                url,
+                url
+            )
+            .as_str(),
+        );
+        if let Some(date) = date(&item).and_then(rfc822) {
+            xml.push_str(format!("    <pubDate>{}</pubDate>\n", date).as_str());
+        }
+        if let Some(description) = item.extra_data.get("description") {
+            xml.push_str(
+                format!("    <description>{}</description>\n", escape(description)).as_str(),
+            );
+        }
+        xml.push_str("  </item>\n");
+    }
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+fn atom(
+    sitemap: &fastn_core::sitemap::Sitemap,
+    groups: &Groups,
+    section: &fastn_core::sitemap::section::Section,
+    base: &str,
+    feed_path: &str,
+) -> String {
+    let items = feed_items(sitemap, groups, section);
+    // an Atom feed has to say when it was last updated, the date of its newest page
+    let updated = items.iter().filter_map(date).max().unwrap_or("1970-01-01");
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <feed xmlns=\"http://www.w3.org/2005/Atom\">\n  <title>{}</title>\n  \
+        <link href=\"{}\"/>\n  <link rel=\"self\" href=\"{}\"/>\n  <id>{}</id>\n  \
+        <updated>{}T00:00:00Z</updated>\n",
+        escape(section.title.as_deref().unwrap_or(section.id.as_str())),
+        escape(format!("{}{}", base, path(section.id.as_str())).as_str()),
+        escape(format!("{}{}", base, feed_path).as_str()),
+        escape(format!("{}{}", base, feed_path).as_str()),
+        updated,
+    );
+    for item in items {
+        let url = escape(format!("{}{}", base, path(item.id)).as_str());
+        xml.push_str(
+            format!(
+                "  <entry>\n    <title>{}</title>\n    <link href=\"{}\"/>\n    <id>{}</id>\n    \
+                <updated>{}T00:00:00Z</updated>\n",
+                escape(item.title.unwrap_or(item.id)),
+                url,
+                url,
+                date(&item).unwrap_or(updated)
+            )
+            .as_str(),
+        );
+        if let Some(description) = item.extra_data.get("description") {
+            xml.push_str(format!("    <summary>{}</summary>\n", escape(description)).as_str());
+        }
+        xml.push_str("  </entry>\n");
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+/// `rfc822()` converts a `YYYY-MM-DD` date to the date format of RSS, like
+/// `Tue, 10 Jan 2023 00:00:00 +0000`.
+fn rfc822(date: &str) -> Option<String> {
+    chrono::NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+        .ok()
+        .map(|date| date.format("%a, %d %b %Y 00:00:00 +0000").to_string())
+}
+
+pub(crate) fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn rfc822() {
+        assert_eq!(
+            super::rfc822("2023-01-10").as_deref(),
+            Some("Tue, 10 Jan 2023 00:00:00 +0000")
+        );
+        assert_eq!(
+            super::rfc822("2024-02-29").as_deref(),
+            Some("Thu, 29 Feb 2024 00:00:00 +0000")
+        );
+        assert_eq!(super::rfc822("2023-13-10"), None);
+        assert_eq!(super::rfc822("2023-02-31"), None);
+        assert_eq!(
+            super::rfc822("0000-01-10").as_deref(),
+            Some("Mon, 10 Jan 0000 00:00:00 +0000")
+        );
+    }
+
+    const SITEMAP: &str = r#"
+# Home: /
+
+# Blog: /blog/
+  feed: rss
+  description: Posts & news
+
+- Tom & Jerry <3: /blog/tom-and-jerry/
+  date: 2023-01-10
+  description: Cats & "mice"
+- Newest: /blog/newest/
+  date: 2023-02-01
+- Undated: /blog/undated/
+- Draft: /blog/draft/
+  skip: true
+- Secret: /blog/secret/
+  readers: staff
+- Shared: /blog/shared/
+  readers: staff
+  confidential: false
+
+# News: /news/
+  feed: atom
+
+- First: /news/first/
+  date: 2022-12-01
+- Second: /news/second/
+  date: 2023-03-01
+"#;
+
+    fn files() -> std::collections::HashMap<String, String> {
+        let sitemap = fastn_core::sitemap::Sitemap::parse_body(
+            SITEMAP,
+            "foo.com",
+            &std::collections::HashMap::new(),
+        )
+        .unwrap();
+        let groups = std::collections::BTreeMap::from([(
+            "staff".to_string(),
+            fastn_core::user_group::UserGroup {
+                title: None,
+                id: "staff".to_string(),
+                identities: vec![],
+                excluded_identities: vec![],
+                groups: vec![],
+                excluded_groups: vec![],
+                description: None,
+            },
+        )]);
+        super::generate(&sitemap, &groups, "https://foo.com/")
+            .into_iter()
+            .map(|(path, content, _)| (path, content))
+            .collect()
+    }
+
+    /// The text of every `<tag>` in `xml`, in order.
+    fn tags<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+        let (open, close) = (format!("<{}>", tag), format!("</{}>", tag));
+        xml.split(open.as_str())
+            .skip(1)
+            .filter_map(|s| s.split_once(close.as_str()).map(|(text, _)| text))
+            .collect()
+    }
+
+    #[test]
+    fn sitemap_xml() {
+        assert_eq!(
+            tags(files()["sitemap.xml"].as_str(), "loc"),
+            vec![
+                "https://foo.com/",
+                "https://foo.com/blog/",
+                "https://foo.com/blog/tom-and-jerry/",
+                "https://foo.com/blog/newest/",
+                "https://foo.com/blog/undated/",
+                "https://foo.com/blog/shared/",
+                "https://foo.com/news/",
+                "https://foo.com/news/first/",
+                "https://foo.com/news/second/",
+            ]
+        );
+    }
+
+    #[test]
+    fn robots_txt() {
+        // the urls only readers can see are not listed
+        assert_eq!(
+            files()["robots.txt"],
+            "User-agent: *\nDisallow: /-/\n\nSitemap: https://foo.com/sitemap.xml\n"
+        );
+    }
+
+    #[test]
+    fn feeds() {
+        let files = files();
+        let rss = files["blog/feed.xml"].as_str();
+        assert_eq!(
+            tags(rss, "title"),
+            vec![
+                "Blog",
+                "Newest",
+                "Tom &amp; Jerry &lt;3",
+                "Undated",
+                "Shared"
+            ]
+        );
+        assert_eq!(tags(rss, "description")[0], "Posts &amp; news");
+        assert_eq!(tags(rss, "description")[1], "Cats &amp; &quot;mice&quot;");
+        assert_eq!(
+            tags(rss, "pubDate"),
+            vec![
+                "Wed, 01 Feb 2023 00:00:00 +0000",
+                "Tue, 10 Jan 2023 00:00:00 +0000"
+            ]
+        );
+
+        let atom = files["news/feed.xml"].as_str();
+        assert_eq!(tags(atom, "title"), vec!["News", "Second", "First"]);
+        assert_eq!(
+            tags(atom, "updated"),
+            vec![
+                "2023-03-01T00:00:00Z",
+                "2023-03-01T00:00:00Z",
+                "2022-12-01T00:00:00Z"
+            ]
+        );
+        assert_eq!(tags(atom, "id")[0], "https://foo.com/news/feed.xml");
+    }
+}
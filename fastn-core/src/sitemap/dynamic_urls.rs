@@ -203,6 +203,224 @@ impl DynamicUrls {
     }
 }
 
+/// The values of the parameters of one page of a dynamic url, by parameter name.
+type BuildParams = std::collections::HashMap<String, String>;
+
+impl DynamicUrls {
+    /// `build_urls()` lists the pages `fastn build` renders for the dynamic urls, as (url,
+    /// document, path parameters). Only the dynamic urls that say where the values of their
+    /// parameters come from have pages, with one of:
+    ///
+    /// - `build-params: arpita, amitu`, the values, or `build-params: username=arpita&age=28,
+    ///   username=amitu&age=30` if the url has more than one parameter. There is no quoting, the
+    ///   values can not contain `,`, and `&` or `=` when they are named, use `build-params-json`
+    ///   for such values
+    /// - `build-params-json: users.json`, a json list of values, or of objects with a key per
+    ///   parameter
+    /// - `build-params-db: users.sqlite` and `build-params-query: SELECT username, age FROM user`,
+    ///   a query returning a column per parameter, like the `package-query` processor
+    ///
+    /// The page of a url is written to `.build/<url>/index.html`, so values can not be `.` or
+    /// `..`, or contain `/` or `\`, except for `path` parameters, whose parts can not be `.` or
+    /// `..`.
+    pub(crate) async fn build_urls(
+        &self,
+        root: &camino::Utf8Path,
+    ) -> fastn_core::Result<Vec<(String, String, Vec<(String, ftd::Value)>)>> {
+        let mut urls = vec![];
+        for item in fastn_core::sitemap::items(&self.sections) {
+            let document = match item.document {
+                Some(document) if !item.path_parameters.is_empty() => document,
+                _ => continue,
+            };
+            for params in build_params(&item, root).await? {
+                let url = build_url(&item, &params)?;
+                match fastn_core::sitemap::utils::url_match(url.as_str(), item.path_parameters)? {
+                    (true, path_params) => {
+                        urls.push((url, document.to_string(), path_params));
+                    }
+                    (false, _) => {
+                        return Err(fastn_core::Error::PackageError {
+                            message: format!(
+                                "dynamic url {}: {:?} do not match the parameters of the url",
+                                item.id, params
+                            ),
+                        })
+                    }
+                }
+            }
+        }
+        Ok(urls)
+    }
+}
+
+async fn build_params(
+    item: &fastn_core::sitemap::Item<'_>,
+    root: &camino::Utf8Path,
+) -> fastn_core::Result<Vec<BuildParams>> {
+    let names = item
+        .path_parameters
+        .iter()
+        .filter_map(|p| match p {
+            fastn_core::sitemap::PathParams::NamedParm { name, .. } => Some(name.as_str()),
+            fastn_core::sitemap::PathParams::ValueParam { .. } => None,
+        })
+        .collect::<Vec<_>>();
+    // a value without a name is for the only parameter of the url
+    let only_param = |value: String| -> fastn_core::Result<BuildParams> {
+        match names.as_slice() {
+            [name] => Ok(std::iter::once((name.to_string(), value)).collect()),
+            _ => Err(fastn_core::Error::PackageError {
+                message: format!(
+                    "dynamic url {}: {} has no parameter name, it is needed as the url has {} \
+                    parameters",
+                    item.id,
+                    value,
+                    names.len()
+                ),
+            }),
+        }
+    };
+
+    if let Some(values) = item.extra_data.get("build-params") {
+        return values
+            .split(',')
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(|v| {
+                if !v.contains('=') {
+                    return only_param(v.to_string());
+                }
+                Ok(v.split('&')
+                    .filter_map(|kv| kv.split_once('='))
+                    .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+                    .collect())
+            })
+            .collect();
+    }
+
+    if let Some(path) = item.extra_data.get("build-params-json") {
+        let values: Vec<serde_json::Value> =
+            serde_json::from_str(tokio::fs::read_to_string(root.join(path)).await?.as_str())?;
+        return values
+            .into_iter()
+            .map(|value| match value {
+                serde_json::Value::Object(o) => Ok(o
+                    .into_iter()
+                    .filter_map(|(k, v)| json_to_string(v).map(|v| (k, v)))
+                    .collect()),
+                value => only_param(json_to_string(value).unwrap_or_default()),
+            })
+            .collect();
+    }
+
+    if let Some(query) = item.extra_data.get("build-params-query") {
+        let db = item.extra_data.get("build-params-db").ok_or_else(|| {
+            fastn_core::Error::PackageError {
+                message: format!(
+                    "dynamic url {}: `build-params-query` needs `build-params-db`",
+                    item.id
+                ),
+            }
+        })?;
+        let conn = rusqlite::Connection::open_with_flags(
+            root.join(db),
+            rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY,
+        )?;
+        let mut stmt = conn.prepare(query)?;
+        let columns = stmt
+            .column_names()
+            .into_iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        let mut rows = stmt.query([])?;
+        let mut params = vec![];
+        while let Some(row) = rows.next()? {
+            let mut values = BuildParams::new();
+            for (i, column) in columns.iter().enumerate() {
+                let value = match row.get::<usize, rusqlite::types::Value>(i)? {
+                    rusqlite::types::Value::Null => continue,
+                    rusqlite::types::Value::Integer(v) => v.to_string(),
+                    rusqlite::types::Value::Real(v) => v.to_string(),
+                    rusqlite::types::Value::Text(v) => v,
+                    rusqlite::types::Value::Blob(_) => {
+                        return Err(fastn_core::Error::PackageError {
+                            message: format!(
+                                "dynamic url {}: column {} is a blob, not a parameter value",
+                                item.id, column
+                            ),
+                        })
+                    }
+                };
+                values.insert(column.to_string(), value);
+            }
+            params.push(values);
+        }
+        return Ok(params);
+    }
+
+    Ok(vec![])
+}
+
+fn json_to_string(value: serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::Null => None,
+        serde_json::Value::String(s) => Some(s),
+        v => Some(v.to_string()),
+    }
+}
+
+/// `build_url()` is the url of the dynamic url `item` with the parameter values `params`.
+fn build_url(
+    item: &fastn_core::sitemap::Item<'_>,
+    params: &BuildParams,
+) -> fastn_core::Result<String> {
+    let mut parts = vec![];
+    for param in item.path_parameters.iter() {
+        match param {
+            fastn_core::sitemap::PathParams::ValueParam { value, .. } => parts.push(value.as_str()),
+            fastn_core::sitemap::PathParams::NamedParm {
+                name,
+                optional,
+                param_type,
+                ..
+            } => match params.get(name).filter(|v| !v.is_empty()) {
+                Some(value) => {
+                    let value = value.trim_matches('/');
+                    let is_path = matches!(param_type, fastn_core::sitemap::ParamType::Path);
+                    if value.contains('\\')
+                        || (!is_path && value.contains('/'))
+                        || value.split('/').any(|part| matches!(part, "" | "." | ".."))
+                    {
+                        return Err(fastn_core::Error::PackageError {
+                            message: format!(
+                                "dynamic url {}: {:?} is not a valid value for the \
+                                    parameter {}",
+                                item.id, value, name
+                            ),
+                        });
+                    }
+                    parts.push(value)
+                }
+                None if *optional => {}
+                None => {
+                    return Err(fastn_core::Error::PackageError {
+                        message: format!(
+                            "dynamic url {}: no value for the parameter {} in {:?}",
+                            item.id, name, params
+                        ),
+                    })
+                }
+            },
+        }
+    }
+    Ok(if parts.is_empty() {
+        "/".to_string()
+    } else {
+        format!("/{}/", parts.join("/"))
+    })
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -278,7 +496,7 @@ mod tests {
                                 fastn_core::sitemap::PathParams::named(
                                     1,
                                     "name".to_string(),
-                                    "string".to_string(),
+                                    fastn_core::sitemap::ParamType::String,
                                 ),
                             ],
                         },
@@ -311,7 +529,7 @@ mod tests {
                                 fastn_core::sitemap::PathParams::named(
                                     1,
                                     "name".to_string(),
-                                    "string".to_string(),
+                                    fastn_core::sitemap::ParamType::String,
                                 ),
                             ],
                         },
@@ -333,4 +551,103 @@ mod tests {
         });
         assert_eq!(left, right)
     }
+
+    #[test]
+    fn build_url() {
+        let dynamic_urls = fastn_core::sitemap::DynamicUrls::parse(
+            &std::collections::HashMap::new(),
+            "abrark.com",
+            r#"
+# Dynamic Urls Section
+- Person
+  url: /person/<string:name>/<integer:age?>/
+  document: person.ftd
+  build-params: name=arpita&age=28, name=amitu
+"#,
+        )
+        .unwrap();
+        let items = fastn_core::sitemap::items(&dynamic_urls.sections);
+        let item = items.iter().find(|i| i.document.is_some()).unwrap();
+        let params = |v: &[(&str, &str)]| {
+            v.iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<super::BuildParams>()
+        };
+
+        assert_eq!(
+            super::build_url(item, &params(&[("name", "arpita"), ("age", "28")])).unwrap(),
+            "/person/arpita/28/"
+        );
+        assert_eq!(
+            super::build_url(item, &params(&[("name", "amitu")])).unwrap(),
+            "/person/amitu/"
+        );
+        assert!(super::build_url(item, &params(&[("age", "28")])).is_err());
+        for name in ["..", ".", "a/b", "..\\x"] {
+            assert!(super::build_url(item, &params(&[("name", name)])).is_err());
+        }
+    }
+
+    #[actix_web::test]
+    async fn build_params_json_and_db() {
+        let root: camino::Utf8PathBuf = std::env::temp_dir()
+            .join(format!("fastn-build-params-{}", std::process::id()))
+            .try_into()
+            .unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(
+            root.join("people.json"),
+            r#"[{"name": "arpita", "age": 28}, {"name": "amitu", "age": null}]"#,
+        )
+        .unwrap();
+        std::fs::write(root.join("names.json"), r#"["arpita", "amitu"]"#).unwrap();
+        rusqlite::Connection::open(root.join("people.sqlite"))
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE person (name TEXT, age INTEGER);
+                 INSERT INTO person VALUES ('arpita', 28), ('amitu', NULL);",
+            )
+            .unwrap();
+
+        let dynamic_urls = fastn_core::sitemap::DynamicUrls::parse(
+            &std::collections::HashMap::new(),
+            "abrark.com",
+            r#"
+# Dynamic Urls Section
+- Person
+  url: /person/<string:name>/<integer:age?>/
+  document: person.ftd
+  build-params-json: people.json
+- Name
+  url: /name/<string:name>/
+  document: name.ftd
+  build-params-json: names.json
+- Db
+  url: /db/<string:name>/<integer:age?>/
+  document: person.ftd
+  build-params-db: people.sqlite
+  build-params-query: SELECT name, age FROM person ORDER BY name DESC
+"#,
+        )
+        .unwrap();
+        let urls = dynamic_urls.build_urls(&root).await;
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert_eq!(
+            urls.unwrap()
+                .into_iter()
+                .map(|(url, document, _)| (url, document))
+                .collect::<Vec<_>>(),
+            [
+                ("/person/arpita/28/", "person.ftd"),
+                ("/person/amitu/", "person.ftd"),
+                ("/name/arpita/", "name.ftd"),
+                ("/name/amitu/", "name.ftd"),
+                ("/db/arpita/28/", "person.ftd"),
+                ("/db/amitu/", "person.ftd"),
+            ]
+            .map(|(url, document)| (url.to_string(), document.to_string()))
+        );
+    }
 }
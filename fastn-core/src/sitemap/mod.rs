@@ -54,7 +54,9 @@ pub enum PathParams {
     NamedParm {
         index: usize,
         name: String,
-        param_type: String,
+        param_type: ParamType,
+        /// `<string:name?>`, the url matches with or without this part
+        optional: bool,
     },
     ValueParam {
         index: usize,
@@ -63,11 +65,12 @@ pub enum PathParams {
 }
 
 impl PathParams {
-    pub fn named(index: usize, name: String, param_type: String) -> Self {
+    pub fn named(index: usize, name: String, param_type: ParamType) -> Self {
         PathParams::NamedParm {
             index,
             name,
             param_type,
+            optional: false,
         }
     }
 
@@ -80,6 +83,82 @@ impl PathParams {
     }
 }
 
+/// The types of the parameters of dynamic urls, `<type:name>`:
+///
+/// - `string`, `integer`, `decimal` and `boolean`, converted to the ftd type of the same name
+/// - `slug`: lowercase letters, digits and `-`, like `hello-world-2`
+/// - `date`: a date as `YYYY-MM-DD`
+/// - `enum(a|b|c)`: one of the listed values
+/// - `regex(<regex>)`: a part matching the regex, which can not contain `/`
+/// - `path`: the rest of the url, so it has to be the last part of the url
+///
+/// All but `integer`, `decimal` and `boolean` are ftd `string`s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParamType {
+    String,
+    Integer,
+    Decimal,
+    Boolean,
+    Slug,
+    Date,
+    Enum(Vec<String>),
+    Regex(Pattern),
+    Path,
+}
+
+/// Regex of a `regex(<regex>)` parameter, matched against the whole part.
+#[derive(Debug, Clone)]
+pub struct Pattern(regex::Regex);
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl Eq for Pattern {}
+
+impl std::str::FromStr for ParamType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        Ok(match s {
+            "string" => ParamType::String,
+            "integer" => ParamType::Integer,
+            "decimal" => ParamType::Decimal,
+            "boolean" => ParamType::Boolean,
+            "slug" => ParamType::Slug,
+            "date" => ParamType::Date,
+            "path" => ParamType::Path,
+            t if t.starts_with("enum(") && t.ends_with(')') => {
+                let values = t["enum(".len()..t.len() - 1]
+                    .split('|')
+                    .map(|v| v.trim().to_string())
+                    .collect::<Vec<_>>();
+                if values.iter().any(|v| v.is_empty()) {
+                    return Err(format!("empty value in {}", t));
+                }
+                ParamType::Enum(values)
+            }
+            t if t.starts_with("regex(") && t.ends_with(')') => {
+                let regex = &t["regex(".len()..t.len() - 1];
+                ParamType::Regex(Pattern(
+                    regex::Regex::new(format!("^(?:{})$", regex).as_str())
+                        .map_err(|e| format!("invalid regex {}: {}", regex, e))?,
+                ))
+            }
+            t => {
+                return Err(format!(
+                    "unknown type {}, expected string, integer, decimal, boolean, slug, date, \
+                    path, enum(..) or regex(..)",
+                    t
+                ))
+            }
+        })
+    }
+}
+
 impl SitemapElement {
     pub(crate) fn insert_key_value(&mut self, key: &str, value: &str) {
         let element_title = match self {
@@ -553,23 +632,7 @@ impl Sitemap {
         config: &mut fastn_core::Config,
         resolve_sitemap: bool,
     ) -> Result<Self, ParseError> {
-        let mut parser = SitemapParser {
-            state: ParsingState::WaitingForSection,
-            sections: vec![],
-            temp_item: None,
-            doc_name: package.name.to_string(),
-        };
-        for line in s.split('\n') {
-            parser.read_line(line, &config.global_ids)?;
-        }
-        if parser.temp_item.is_some() {
-            parser.eval_temp_item(&config.global_ids)?;
-        }
-        let mut sitemap = Sitemap {
-            sections: construct_tree_util(parser.finalize()?),
-            readers: vec![],
-            writers: vec![],
-        };
+        let mut sitemap = Sitemap::parse_body(s, package.name.as_str(), &config.global_ids)?;
 
         // TODO: Need to fix it later
         // sitemap should not contain the dynamic parameters
@@ -592,6 +655,32 @@ impl Sitemap {
         Ok(sitemap)
     }
 
+    /// `parse_body()` parses the body of `fastn.sitemap`, without resolving the documents of its
+    /// urls.
+    pub(crate) fn parse_body(
+        s: &str,
+        package_name: &str,
+        global_ids: &std::collections::HashMap<String, String>,
+    ) -> Result<Self, ParseError> {
+        let mut parser = SitemapParser {
+            state: ParsingState::WaitingForSection,
+            sections: vec![],
+            temp_item: None,
+            doc_name: package_name.to_string(),
+        };
+        for line in s.split('\n') {
+            parser.read_line(line, global_ids)?;
+        }
+        if parser.temp_item.is_some() {
+            parser.eval_temp_item(global_ids)?;
+        }
+        Ok(Sitemap {
+            sections: construct_tree_util(parser.finalize()?),
+            readers: vec![],
+            writers: vec![],
+        })
+    }
+
     async fn resolve(
         &mut self,
         package: &fastn_core::Package,
@@ -1297,6 +1386,48 @@ impl Sitemap {
         }
     }
 
+    /// `group_ids()` returns the ids of all the user groups used as readers or writers
+    /// anywhere in the sitemap.
+    pub fn group_ids(&self) -> Vec<String> {
+        use itertools::Itertools;
+
+        fn toc_group_ids(toc: &toc::TocItem, ids: &mut Vec<String>) {
+            ids.extend(toc.readers.iter().chain(toc.writers.iter()).cloned());
+            for child in toc.children.iter() {
+                toc_group_ids(child, ids);
+            }
+        }
+
+        let mut ids: Vec<String> = self
+            .readers
+            .iter()
+            .chain(self.writers.iter())
+            .cloned()
+            .collect();
+        for section in self.sections.iter() {
+            ids.extend(
+                section
+                    .readers
+                    .iter()
+                    .chain(section.writers.iter())
+                    .cloned(),
+            );
+            for subsection in section.subsections.iter() {
+                ids.extend(
+                    subsection
+                        .readers
+                        .iter()
+                        .chain(subsection.writers.iter())
+                        .cloned(),
+                );
+                for toc in subsection.toc.iter() {
+                    toc_group_ids(toc, &mut ids);
+                }
+            }
+        }
+        ids.into_iter().unique().collect()
+    }
+
     /// This function will return all the readers and readers which are inherited from parent
 
     // TODO: need to handle special reader: everyone, writer: everyone
@@ -1693,6 +1824,61 @@ fn construct_tree(elements: Vec<(toc::TocItem, usize)>, smallest_level: usize) -
     stack_tree
 }
 
+/// A section, subsection or toc item, of the sitemap or of the dynamic urls.
+pub(crate) struct Item<'a> {
+    pub id: &'a str,
+    pub title: Option<&'a str>,
+    pub skip: bool,
+    pub document: Option<&'a str>,
+    pub path_parameters: &'a [PathParams],
+    pub extra_data: &'a std::collections::BTreeMap<String, String>,
+}
+
+/// `items()` lists all the sections, subsections and toc items, in the order they are listed.
+pub(crate) fn items(sections: &[section::Section]) -> Vec<Item> {
+    fn toc_items<'a>(toc: &'a toc::TocItem, items: &mut Vec<Item<'a>>) {
+        items.push(Item {
+            id: toc.id.as_str(),
+            title: toc.title.as_deref(),
+            skip: toc.skip,
+            document: toc.document.as_deref(),
+            path_parameters: toc.path_parameters.as_slice(),
+            extra_data: &toc.extra_data,
+        });
+        for child in toc.children.iter() {
+            toc_items(child, items);
+        }
+    }
+
+    let mut items = vec![];
+    for section in sections.iter() {
+        items.push(Item {
+            id: section.id.as_str(),
+            title: section.title.as_deref(),
+            skip: section.skip,
+            document: section.document.as_deref(),
+            path_parameters: section.path_parameters.as_slice(),
+            extra_data: &section.extra_data,
+        });
+        for subsection in section.subsections.iter() {
+            if let Some(id) = subsection.id.as_ref() {
+                items.push(Item {
+                    id: id.as_str(),
+                    title: subsection.title.as_deref(),
+                    skip: subsection.skip,
+                    document: subsection.document.as_deref(),
+                    path_parameters: subsection.path_parameters.as_slice(),
+                    extra_data: &subsection.extra_data,
+                });
+            }
+            for toc in subsection.toc.iter() {
+                toc_items(toc, &mut items);
+            }
+        }
+    }
+    items
+}
+
 pub fn resolve(
     package: &fastn_core::Package,
     path: &str,
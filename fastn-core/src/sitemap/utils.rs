@@ -18,67 +18,149 @@ pub fn url_match(
 ) -> fastn_core::Result<(bool, Vec<(String, ftd::Value)>)> {
     use itertools::Itertools;
     // request_attrs: [abrark, foo, 28]
-    let request_parts = request_url.trim_matches('/').split('/').collect_vec();
-    // This should go to config request [username: abrark, age: 28]
-    if request_parts.len().ne(&sitemap_params.len()) {
-        return Ok((false, vec![]));
-    }
+    let request_parts = request_url
+        .trim_matches('/')
+        .split('/')
+        .filter(|part| !part.is_empty())
+        .collect_vec();
 
     // match logic
     // req: [a, ak, foo]
     // d-urls: [(0, a, None), (1, username, Some(string)), (2, foo, None)]
     // [(param_name, value)]
+    // This should go to config request [username: abrark, age: 28]
     let mut path_parameters: Vec<(String, ftd::Value)> = vec![];
-    let mut count = 0;
-    for req_part in request_parts {
-        match &sitemap_params[count] {
-            fastn_core::sitemap::PathParams::ValueParam { index: _, value } => {
-                count += 1;
-                if req_part.eq(value) {
-                    continue;
-                } else {
-                    return Ok((false, vec![]));
-                }
+    if match_parts(&request_parts, sitemap_params, &mut path_parameters) {
+        Ok((true, path_parameters))
+    } else {
+        Ok((false, vec![]))
+    }
+}
+
+// Optional parameters are first tried with the request part, and then without it, if the rest of
+// the url does not match that way.
+fn match_parts(
+    request_parts: &[&str],
+    sitemap_params: &[fastn_core::sitemap::PathParams],
+    path_parameters: &mut Vec<(String, ftd::Value)>,
+) -> bool {
+    let (param, rest) = match sitemap_params.split_first() {
+        Some(v) => v,
+        None => return request_parts.is_empty(),
+    };
+
+    match param {
+        fastn_core::sitemap::PathParams::ValueParam { index: _, value } => {
+            request_parts.first() == Some(&value.as_str())
+                && match_parts(&request_parts[1..], rest, path_parameters)
+        }
+        // `path` is always the last parameter, see `parse_named_params()`
+        fastn_core::sitemap::PathParams::NamedParm {
+            index: _,
+            name,
+            param_type: fastn_core::sitemap::ParamType::Path,
+            optional,
+        } => {
+            if request_parts.is_empty() && !optional {
+                return false;
             }
-            fastn_core::sitemap::PathParams::NamedParm {
-                index: _,
-                name,
-                param_type,
-            } => {
-                count += 1;
-                if let Ok(value) = get_value_type(req_part, param_type) {
-                    path_parameters.push((name.to_string(), value));
-                } else {
-                    return Ok((false, vec![]));
+            path_parameters.push((
+                name.to_string(),
+                ftd::Value::String {
+                    text: request_parts.join("/"),
+                    source: ftd::TextSource::Default,
+                },
+            ));
+            true
+        }
+        fastn_core::sitemap::PathParams::NamedParm {
+            index: _,
+            name,
+            param_type,
+            optional,
+        } => {
+            if let Some(value) = request_parts
+                .first()
+                .and_then(|part| get_value_type(part, param_type))
+            {
+                let matched = path_parameters.len();
+                path_parameters.push((name.to_string(), value));
+                if match_parts(&request_parts[1..], rest, path_parameters) {
+                    return true;
                 }
+                path_parameters.truncate(matched);
             }
-        };
+            *optional && match_parts(request_parts, rest, path_parameters)
+        }
     }
-    return Ok((true, path_parameters));
+}
 
-    fn get_value_type(value: &str, r#type: &str) -> fastn_core::Result<ftd::Value> {
-        match r#type {
-            "string" => Ok(ftd::Value::String {
-                text: value.to_string(),
-                source: ftd::TextSource::Default,
-            }),
-            "integer" => {
-                let value = value.parse::<i64>()?;
-                Ok(ftd::Value::Integer { value })
-            }
-            "decimal" => {
-                let value = value.parse::<f64>()?;
-                Ok(ftd::Value::Decimal { value })
-            }
-            "boolean" => {
-                let value = value.parse::<bool>()?;
-                Ok(ftd::Value::Boolean { value })
-            }
-            _ => unimplemented!(),
+pub(crate) fn get_value_type(
+    value: &str,
+    r#type: &fastn_core::sitemap::ParamType,
+) -> Option<ftd::Value> {
+    let string = || ftd::Value::String {
+        text: value.to_string(),
+        source: ftd::TextSource::Default,
+    };
+    match r#type {
+        fastn_core::sitemap::ParamType::String | fastn_core::sitemap::ParamType::Path => {
+            Some(string())
+        }
+        fastn_core::sitemap::ParamType::Integer => value
+            .parse::<i64>()
+            .ok()
+            .map(|value| ftd::Value::Integer { value }),
+        fastn_core::sitemap::ParamType::Decimal => value
+            .parse::<f64>()
+            .ok()
+            .map(|value| ftd::Value::Decimal { value }),
+        fastn_core::sitemap::ParamType::Boolean => value
+            .parse::<bool>()
+            .ok()
+            .map(|value| ftd::Value::Boolean { value }),
+        fastn_core::sitemap::ParamType::Slug => {
+            let is_slug = !value.is_empty()
+                && value
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+            is_slug.then(string)
+        }
+        fastn_core::sitemap::ParamType::Date => is_date(value).then(string),
+        fastn_core::sitemap::ParamType::Enum(values) => {
+            values.iter().any(|v| v.eq(value)).then(string)
         }
+        fastn_core::sitemap::ParamType::Regex(pattern) => pattern.0.is_match(value).then(string),
     }
 }
 
+/// `YYYY-MM-DD`, with a valid month and day
+pub(crate) fn is_date(value: &str) -> bool {
+    let parts = value.split('-').collect::<Vec<_>>();
+    let (year, month, day) = match parts.as_slice() {
+        [y, m, d] if y.len() == 4 && m.len() == 2 && d.len() == 2 => {
+            match (y.parse::<u32>(), m.parse::<u32>(), d.parse::<u32>()) {
+                (Ok(y), Ok(m), Ok(d)) => (y, m, d),
+                _ => return false,
+            }
+        }
+        _ => return false,
+    };
+    let is_leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    (1..=days).contains(&day)
+}
+
+fn is_named(param: &fastn_core::sitemap::PathParams, param_name: &str) -> bool {
+    matches!(param, fastn_core::sitemap::PathParams::NamedParm { name, .. } if name == param_name)
+}
+
 /// Please check test case: `parse_path_params_test_0`
 /// This method is for parsing the dynamic params from fastn.dynamic-urls
 pub fn parse_named_params(
@@ -94,20 +176,38 @@ pub fn parse_named_params(
     for part in parts.into_iter().map(|x| x.trim()) {
         if !part.is_empty() {
             if part.contains(':') && part.starts_with('<') && part.ends_with('>') {
-                // <string:username>
-                if let Some(colon_index) = part.find(':') {
+                // <string:username>, the type can contain `:` in case of regex(..)
+                if let Some(colon_index) = part.rfind(':') {
                     let type_part = part[1..colon_index].trim();
                     let param_name_part = part[colon_index + 1..part.len() - 1].trim();
+                    let (param_name_part, optional) = match param_name_part.strip_suffix('?') {
+                        Some(name) => (name.trim(), true),
+                        None => (param_name_part, false),
+                    };
                     if type_part.is_empty() || param_name_part.is_empty() {
                         return Err(fastn_core::sitemap::ParseError::InvalidDynamicUrls {
                             message: format!("dynamic-urls format is wrong for: {}", part),
                         });
                     }
-                    output.push(fastn_core::sitemap::PathParams::named(
+                    let param_type = type_part.parse().map_err(|message| {
+                        fastn_core::sitemap::ParseError::InvalidDynamicUrls {
+                            message: format!("{}: {}", part, message),
+                        }
+                    })?;
+                    if output.iter().any(|p| is_named(p, param_name_part)) {
+                        return Err(fastn_core::sitemap::ParseError::InvalidDynamicUrls {
+                            message: format!(
+                                "{}: {} is repeated in {}",
+                                part, param_name_part, url
+                            ),
+                        });
+                    }
+                    output.push(fastn_core::sitemap::PathParams::NamedParm {
                         index,
-                        param_name_part.to_string(),
-                        type_part.to_string(), // TODO: check the type which are supported in the sitemap
-                    ));
+                        name: param_name_part.to_string(),
+                        param_type,
+                        optional,
+                    });
                     index += 1;
                 }
             } else {
@@ -120,6 +220,25 @@ pub fn parse_named_params(
             }
         }
     }
+
+    if let Some(position) = output.iter().position(|p| {
+        matches!(
+            p,
+            fastn_core::sitemap::PathParams::NamedParm {
+                param_type: fastn_core::sitemap::ParamType::Path,
+                ..
+            }
+        )
+    }) {
+        if position + 1 != output.len() {
+            return Err(fastn_core::sitemap::ParseError::InvalidDynamicUrls {
+                message: format!(
+                    "{}: a path parameter has to be the last part of the url",
+                    url
+                ),
+            });
+        }
+    }
     Ok(output)
 }
 
@@ -133,8 +252,16 @@ mod tests {
         let output = super::parse_named_params("/b/<string:username>/<integer:age>/foo/");
         let test_output = vec![
             fastn_core::sitemap::PathParams::value(0, "b".to_string()),
-            fastn_core::sitemap::PathParams::named(1, "username".to_string(), "string".to_string()),
-            fastn_core::sitemap::PathParams::named(2, "age".to_string(), "integer".to_string()),
+            fastn_core::sitemap::PathParams::named(
+                1,
+                "username".to_string(),
+                fastn_core::sitemap::ParamType::String,
+            ),
+            fastn_core::sitemap::PathParams::named(
+                2,
+                "age".to_string(),
+                fastn_core::sitemap::ParamType::Integer,
+            ),
             fastn_core::sitemap::PathParams::value(3, "foo".to_string()),
         ];
         assert!(output.is_ok());
@@ -147,8 +274,16 @@ mod tests {
         let output = super::parse_named_params("/b/ <  string  :  username > / <integer:age>/foo/");
         let test_output = vec![
             fastn_core::sitemap::PathParams::value(0, "b".to_string()),
-            fastn_core::sitemap::PathParams::named(1, "username".to_string(), "string".to_string()),
-            fastn_core::sitemap::PathParams::named(2, "age".to_string(), "integer".to_string()),
+            fastn_core::sitemap::PathParams::named(
+                1,
+                "username".to_string(),
+                fastn_core::sitemap::ParamType::String,
+            ),
+            fastn_core::sitemap::PathParams::named(
+                2,
+                "age".to_string(),
+                fastn_core::sitemap::ParamType::Integer,
+            ),
             fastn_core::sitemap::PathParams::value(3, "foo".to_string()),
         ];
         assert!(output.is_ok());
@@ -172,10 +307,14 @@ mod tests {
                 fastn_core::sitemap::PathParams::named(
                     0,
                     "username".to_string(),
-                    "string".to_string(),
+                    fastn_core::sitemap::ParamType::String,
                 ),
                 fastn_core::sitemap::PathParams::value(1, "foo".to_string()),
-                fastn_core::sitemap::PathParams::named(2, "age".to_string(), "integer".to_string()),
+                fastn_core::sitemap::PathParams::named(
+                    2,
+                    "age".to_string(),
+                    fastn_core::sitemap::ParamType::Integer,
+                ),
             ],
         );
 
@@ -210,10 +349,14 @@ mod tests {
                 fastn_core::sitemap::PathParams::named(
                     0,
                     "username".to_string(),
-                    "integer".to_string(),
+                    fastn_core::sitemap::ParamType::Integer,
                 ),
                 fastn_core::sitemap::PathParams::value(1, "foo".to_string()),
-                fastn_core::sitemap::PathParams::named(2, "age".to_string(), "integer".to_string()),
+                fastn_core::sitemap::PathParams::named(
+                    2,
+                    "age".to_string(),
+                    fastn_core::sitemap::ParamType::Integer,
+                ),
             ],
         );
 
@@ -235,10 +378,14 @@ mod tests {
                 fastn_core::sitemap::PathParams::named(
                     0,
                     "username".to_string(),
-                    "integer".to_string(),
+                    fastn_core::sitemap::ParamType::Integer,
                 ),
                 fastn_core::sitemap::PathParams::value(1, "foo".to_string()),
-                fastn_core::sitemap::PathParams::named(2, "age".to_string(), "integer".to_string()),
+                fastn_core::sitemap::PathParams::named(
+                    2,
+                    "age".to_string(),
+                    fastn_core::sitemap::ParamType::Integer,
+                ),
             ],
         );
         assert!(!output.unwrap().0)
@@ -255,7 +402,7 @@ mod tests {
                 fastn_core::sitemap::PathParams::named(
                     1,
                     "username".to_string(),
-                    "string".to_string(),
+                    fastn_core::sitemap::ParamType::String,
                 ),
                 fastn_core::sitemap::PathParams::value(2, "person".to_string()),
             ],
@@ -285,7 +432,7 @@ mod tests {
                 fastn_core::sitemap::PathParams::named(
                     1,
                     "username".to_string(),
-                    "string".to_string(),
+                    fastn_core::sitemap::ParamType::String,
                 ),
                 fastn_core::sitemap::PathParams::value(2, "person".to_string()),
             ],
@@ -304,10 +451,14 @@ mod tests {
                 fastn_core::sitemap::PathParams::named(
                     1,
                     "username".to_string(),
-                    "string".to_string(),
+                    fastn_core::sitemap::ParamType::String,
                 ),
                 fastn_core::sitemap::PathParams::value(2, "person".to_string()),
-                fastn_core::sitemap::PathParams::named(3, "age".to_string(), "integer".to_string()),
+                fastn_core::sitemap::PathParams::named(
+                    3,
+                    "age".to_string(),
+                    fastn_core::sitemap::ParamType::Integer,
+                ),
             ],
         );
         let output = output.unwrap();
@@ -326,4 +477,63 @@ mod tests {
             ]
         );
     }
+
+    // cargo test --package fastn --lib sitemap::utils::tests::parse_path_params_test_03
+    #[test]
+    fn parse_path_params_test_03() {
+        // unknown type
+        assert!(super::parse_named_params("/b/<str:username>/").is_err());
+        // path is not the last part
+        assert!(super::parse_named_params("/b/<path:rest>/foo/").is_err());
+        // repeated parameter
+        assert!(super::parse_named_params("/<string:a>/<integer:a>/").is_err());
+        assert!(super::parse_named_params("/<regex(a(:b)c):a>/<enum(x|y):b?>/").is_ok());
+    }
+
+    // cargo test --package fastn --lib sitemap::utils::tests::url_match_6
+    #[test]
+    fn url_match_6() {
+        let url_match = |url: &str, sitemap_url: &str| {
+            let params = super::parse_named_params(sitemap_url).unwrap();
+            let (matched, values) = super::url_match(url, &params).unwrap();
+            matched.then(|| {
+                values
+                    .into_iter()
+                    .map(|(name, value)| match value {
+                        ftd::Value::String { text, .. } => format!("{}={}", name, text),
+                        v => format!("{}={:?}", name, v),
+                    })
+                    .collect::<Vec<_>>()
+            })
+        };
+        assert_eq!(url_match("/blog/", "/blog/<integer:page?>/"), Some(vec![]));
+        assert_eq!(
+            url_match("/docs/a/b/c/", "/docs/<path:rest>/"),
+            Some(vec!["rest=a/b/c".to_string()])
+        );
+        assert_eq!(url_match("/docs/", "/docs/<path:rest>/"), None);
+        assert_eq!(
+            url_match("/docs/", "/docs/<path:rest?>/"),
+            Some(vec!["rest=".to_string()])
+        );
+        assert_eq!(
+            url_match("/post/published/", "/post/<enum(draft|published):status>/"),
+            Some(vec!["status=published".to_string()])
+        );
+        assert_eq!(
+            url_match("/post/old/", "/post/<enum(draft|published):status>/"),
+            None
+        );
+        assert_eq!(
+            url_match("/y/2023/", "/y/<regex([0-9]{4}):year>/"),
+            Some(vec!["year=2023".to_string()])
+        );
+        assert_eq!(url_match("/y/20234/", "/y/<regex([0-9]{4}):year>/"), None);
+        assert_eq!(url_match("/p/Hello/", "/p/<slug:title>/"), None);
+        assert_eq!(
+            url_match("/d/2024-02-29/", "/d/<date:day>/"),
+            Some(vec!["day=2024-02-29".to_string()])
+        );
+        assert_eq!(url_match("/d/2023-02-29/", "/d/<date:day>/"), None);
+    }
 }
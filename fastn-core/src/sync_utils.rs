@@ -326,8 +326,7 @@ impl fastn_core::Config {
                     {
                         content
                     } else {
-                        // binary file like images, can't resolve conflict
-                        *status = Status::Conflict(server_file_edit.version);
+                        // binary file like images, can't be merged, the server keeps both
                         continue;
                     };
 
@@ -338,10 +337,12 @@ impl fastn_core::Config {
                     .await?;
                     let ours_content = String::from_utf8(content.clone())?;
 
-                    match diffy::MergeOptions::new()
-                        .set_conflict_style(diffy::ConflictStyle::Merge)
-                        .merge(&ancestor_content, &ours_content, &theirs_content)
-                    {
+                    match fastn_core::ftd_merge::merge(
+                        path,
+                        &ancestor_content,
+                        &ours_content,
+                        &theirs_content,
+                    ) {
                         Ok(data) => {
                             fastn_core::utils::update(self.root.join(filename), data.as_bytes())
                                 .await?;
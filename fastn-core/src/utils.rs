@@ -189,7 +189,7 @@ pub(crate) fn get_package_title(config: &fastn_core::Config) -> String {
         markdown: None,
         document_id: "index.ftd".to_string(),
         translated_data: Default::default(),
-        current_package: std::sync::Arc::new(std::sync::Mutex::new(vec![config.package.clone()])),
+        current_package: std::sync::Arc::new(std::sync::Mutex::new(vec![config.package.as_ref().clone()])),
     };
     let main_ftd_doc = match ftd::p2::Document::from("index.ftd", fastn.as_str(), &lib) {
         Ok(v) => v,
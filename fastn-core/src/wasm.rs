@@ -1,52 +1,176 @@
 use std::str::FromStr;
 
-#[derive(Default)]
-pub struct HostExports {}
+/// `HostExports` is what the host gives to the WASM backend of a package. Currently it is only
+/// the `http` capability, limited by the `backend-http-*` and `backend-allowed-hosts` settings
+/// of the package. Redirects are only followed to the allowed hosts, and at most
+/// `MAX_HTTP_RESPONSE_SIZE` bytes of a response body are read.
+pub struct HostExports {
+    http: fastn_core::package::BackendHttp,
+    client: reqwest::blocking::Client,
+    /// `backend-timeout` of the request, the guest can not be interrupted while it waits for an
+    /// `http` call, so the call itself must finish by then
+    deadline: std::time::Instant,
+}
+
+/// `HttpResult` is sent back to the guest as JSON in `Httpresponse.data`:
+///
+/// ```json
+/// {"status": 200, "headers": [["content-type", "application/json"]], "body": "..."}
+/// ```
+///
+/// or, if the request could not be made at all:
+///
+/// ```json
+/// {"error": "host not allowed: example.com"}
+/// ```
+#[derive(serde::Serialize, Debug)]
+#[serde(untagged)]
+enum HttpResult {
+    Response {
+        status: u16,
+        headers: Vec<(String, String)>,
+        body: String,
+    },
+    Error {
+        error: String,
+    },
+}
+
+impl HostExports {
+    fn send(
+        &self,
+        request: &fastn_utils::backend_host_export::host::Httprequest<'_>,
+    ) -> std::result::Result<HttpResult, String> {
+        let url = url::Url::parse(request.path)
+            .map_err(|e| format!("invalid url {}: {}", request.path, e))?;
+        let host = url.host_str().unwrap_or_default();
+        if !self.http.is_allowed(host) {
+            return Err(format!(
+                "host not allowed: {}, add it to `backend-allowed-hosts` in FASTN.ftd",
+                host
+            ));
+        }
+
+        let method = reqwest::Method::from_bytes(request.method.to_uppercase().as_bytes())
+            .map_err(|e| format!("invalid method {}: {}", request.method, e))?;
+
+        let mut headers = reqwest::header::HeaderMap::new();
+        for (key, value) in request.headers.iter() {
+            headers.insert(
+                reqwest::header::HeaderName::from_str(key)
+                    .map_err(|e| format!("invalid header name {}: {}", key, e))?,
+                reqwest::header::HeaderValue::from_str(value)
+                    .map_err(|e| format!("invalid value for header {}: {}", key, e))?,
+            );
+        }
+
+        let remaining = self
+            .deadline
+            .saturating_duration_since(std::time::Instant::now());
+        if remaining.is_zero() {
+            return Err(format!("{} exceeded", Limit::Timeout));
+        }
+
+        let mut builder = self
+            .client
+            .request(method, url)
+            .headers(headers)
+            .timeout(remaining.min(self.http.timeout));
+        if !request.payload.is_empty() {
+            builder = builder.body(request.payload.to_string());
+        }
+
+        let response = builder.send().map_err(|e| e.to_string())?;
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(k, v)| v.to_str().ok().map(|v| (k.to_string(), v.to_string())))
+            .collect();
+        let body = {
+            use std::io::Read;
+
+            let mut body = vec![];
+            response
+                .take(MAX_HTTP_RESPONSE_SIZE + 1)
+                .read_to_end(&mut body)
+                .map_err(|e| e.to_string())?;
+            if body.len() as u64 > MAX_HTTP_RESPONSE_SIZE {
+                return Err(format!(
+                    "response of {} is larger than {} bytes",
+                    request.path, MAX_HTTP_RESPONSE_SIZE
+                ));
+            }
+            String::from_utf8_lossy(&body).into_owned()
+        };
+
+        Ok(HttpResult::Response {
+            status,
+            headers,
+            body,
+        })
+    }
+}
 
 impl fastn_utils::backend_host_export::host::Host for HostExports {
     fn http(
         &mut self,
         request: fastn_utils::backend_host_export::host::Httprequest<'_>,
     ) -> fastn_utils::backend_host_export::host::Httpresponse {
-        let url = request.path.to_string();
-        let request_method = request.method.to_string();
-        let request_body = request.payload.to_string();
-        let mut headers = reqwest::header::HeaderMap::new();
-        request
-            .headers
-            .clone()
-            .into_iter()
-            .for_each(|(header_key, header_val)| {
-                headers.insert(
-                    reqwest::header::HeaderName::from_str(header_key).unwrap(),
-                    reqwest::header::HeaderValue::from_str(header_val).unwrap(),
+        let result = match self.send(&request) {
+            Ok(result) => result,
+            Err(error) => {
+                tracing::warn!(
+                    msg = "wasm backend http call failed",
+                    url = request.path,
+                    error = error.as_str()
                 );
-            });
-        let resp = std::thread::spawn(move || {
-            let request_client = reqwest::blocking::Client::new();
-            match request_method.as_str() {
-                "GET" => request_client.get(url).headers(headers),
-                "POST" => request_client.post(url).headers(headers).body(request_body),
-                "PATCH" => request_client
-                    .patch(url)
-                    .headers(headers)
-                    .body(request_body),
-                _ => panic!("METHOD not allowed"),
+                HttpResult::Error { error }
             }
-            .send()
-            .unwrap()
-            .text()
-            .unwrap()
-        })
-        .join()
-        .unwrap();
-        fastn_utils::backend_host_export::host::Httpresponse { data: resp }
+        };
+        fastn_utils::backend_host_export::host::Httpresponse {
+            data: serde_json::to_string(&result).unwrap_or_default(),
+        }
     }
 }
 
 pub struct Context<I, E> {
     pub imports: I,
     pub exports: E,
+    pub limits: wit_bindgen_host_wasmtime_rust::wasmtime::StoreLimits,
+}
+
+/// Used when neither the backend's package nor the package being served declare a limit.
+const DEFAULT_MAX_MEMORY: usize = 256 * 1024 * 1024;
+const DEFAULT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// The epoch of every engine is incremented once every `EPOCH_TICK`, `backend-timeout` is
+/// converted into a number of ticks.
+const EPOCH_TICK: std::time::Duration = std::time::Duration::from_millis(10);
+static EPOCH_TICKER: std::sync::Once = std::sync::Once::new();
+
+const WASM_PAGE_SIZE: u64 = 64 * 1024;
+
+/// The largest response body the `http` calls of a backend can read.
+const MAX_HTTP_RESPONSE_SIZE: u64 = 10 * 1024 * 1024;
+const MAX_HTTP_REDIRECTS: usize = 10;
+
+/// `Limit` is the resource a backend ran out of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Fuel,
+    Memory,
+    Timeout,
+}
+
+impl std::fmt::Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Limit::Fuel => "backend-fuel",
+            Limit::Memory => "backend-max-memory",
+            Limit::Timeout => "backend-timeout",
+        })
+    }
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -59,31 +183,207 @@ pub enum WASMError {
 
     #[error("WasmFunctionInvokeError: {}", _0)]
     WasmFunctionInvoke(String),
+
+    #[error("HttpClientError: {}", _0)]
+    HttpClient(#[from] reqwest::Error),
+
+    #[error("JoinError: {}", _0)]
+    Join(#[from] tokio::task::JoinError),
+
+    #[error("LimitExceeded: {}", _0)]
+    LimitExceeded(Limit),
 }
 
 pub type WasmRunnerResult<T> = std::result::Result<T, WASMError>;
 
+/// A compiled `backend.wasm`, along with the http client its `http` calls go through. `Engine`,
+/// `Module` and `Client` are all reference counted, so cloning a `Backend` is cheap.
+#[derive(Clone)]
+struct Backend {
+    engine: wit_bindgen_host_wasmtime_rust::wasmtime::Engine,
+    module: wit_bindgen_host_wasmtime_rust::wasmtime::Module,
+    client: reqwest::blocking::Client,
+}
+
+/// Compiling a module is expensive, so we do it once per package and keep the compiled module
+/// around till `invalidate()` is called, which `fastn_core::watcher` does when any `.wasm` file
+/// or FASTN.ftd changes. A module can only run on the engine it was compiled for, and metering
+/// fuel is a setting of the engine, so the module is cached per package and per `fuel` (whether
+/// `backend-fuel` is set for the request).
+static BACKENDS: once_cell::sync::Lazy<
+    antidote::RwLock<std::collections::HashMap<(String, bool), Backend>>,
+> = once_cell::sync::Lazy::new(|| antidote::RwLock::new(Default::default()));
+
+/// The engines shared by all the backends, one that meters fuel and one that does not. They are
+/// never dropped, so the epoch ticker keeps driving them even for the requests that are still
+/// running on a module `invalidate()` has removed from the cache.
+static ENGINES: once_cell::sync::Lazy<
+    antidote::RwLock<
+        std::collections::HashMap<bool, wit_bindgen_host_wasmtime_rust::wasmtime::Engine>,
+    >,
+> = once_cell::sync::Lazy::new(|| antidote::RwLock::new(Default::default()));
+
+pub(crate) fn invalidate() {
+    BACKENDS.write().clear();
+}
+
+// A single thread drives the epoch of the engines, so a store can be interrupted once its
+// deadline (`backend-timeout`) has passed.
+fn start_epoch_ticker() {
+    EPOCH_TICKER.call_once(|| {
+        std::thread::spawn(|| loop {
+            std::thread::sleep(EPOCH_TICK);
+            for engine in ENGINES.read().values() {
+                engine.increment_epoch();
+            }
+        });
+    });
+}
+
+fn get_engine(fuel: bool) -> WasmRunnerResult<wit_bindgen_host_wasmtime_rust::wasmtime::Engine> {
+    if let Some(engine) = ENGINES.read().get(&fuel) {
+        return Ok(engine.clone());
+    }
+
+    let mut engines = ENGINES.write();
+    if let Some(engine) = engines.get(&fuel) {
+        return Ok(engine.clone());
+    }
+    let mut wasm_config = wit_bindgen_host_wasmtime_rust::wasmtime::Config::new();
+    wasm_config.cache_config_load_default()?;
+    wasm_config.wasm_backtrace_details(
+        wit_bindgen_host_wasmtime_rust::wasmtime::WasmBacktraceDetails::Disable,
+    );
+    wasm_config.consume_fuel(fuel);
+    wasm_config.epoch_interruption(true);
+
+    let engine = wit_bindgen_host_wasmtime_rust::wasmtime::Engine::new(&wasm_config)?;
+    engines.insert(fuel, engine.clone());
+    Ok(engine)
+}
+
+// The blocking http client can not be created or used from async code, so this has to be
+// called from a blocking task.
+fn get_backend(
+    package: &fastn_core::Package,
+    limits: &fastn_core::package::BackendLimits,
+    wasm_module: &camino::Utf8Path,
+) -> WasmRunnerResult<Backend> {
+    start_epoch_ticker();
+
+    let package_name = package.name.as_str();
+    let key = (package_name.to_string(), limits.fuel.is_some());
+    if let Some(backend) = BACKENDS.read().get(&key) {
+        return Ok(backend.clone());
+    }
+
+    let engine = get_engine(limits.fuel.is_some())?;
+    let module = fastn_core::time(format!("WASM Compile: {}", package_name).as_str()).it(
+        wit_bindgen_host_wasmtime_rust::wasmtime::Module::from_file(&engine, wasm_module.as_str())?,
+    );
+
+    let backend = Backend {
+        engine,
+        module,
+        client: http_client(&package.backend_http)?,
+    };
+    BACKENDS.write().insert(key, backend.clone());
+    Ok(backend)
+}
+
+fn http_client(
+    http: &fastn_core::package::BackendHttp,
+) -> reqwest::Result<reqwest::blocking::Client> {
+    // `backend-allowed-hosts` applies to every hop of a redirect, not just the first url
+    let allowed = http.clone();
+    reqwest::blocking::Client::builder()
+        .timeout(http.timeout)
+        .connect_timeout(http.connect_timeout)
+        .redirect(reqwest::redirect::Policy::custom(move |attempt| {
+            let host = attempt.url().host_str().unwrap_or_default().to_string();
+            if attempt.previous().len() >= MAX_HTTP_REDIRECTS {
+                attempt.error("too many redirects")
+            } else if !allowed.is_allowed(host.as_str()) {
+                attempt.error(format!(
+                    "redirected to a host not allowed: {}, add it to `backend-allowed-hosts` in FASTN.ftd",
+                    host
+                ))
+            } else {
+                attempt.follow()
+            }
+        }))
+        .build()
+}
+
+/// `exceeded_limit()` finds out if a failed guest call failed because the guest ran out of one
+/// of its `limits`.
+fn exceeded_limit<I, E>(
+    store: &mut wit_bindgen_host_wasmtime_rust::wasmtime::Store<Context<I, E>>,
+    module: &wit_bindgen_host_wasmtime_rust::wasmtime::Module,
+    instance: Option<wit_bindgen_host_wasmtime_rust::wasmtime::Instance>,
+    limits: &fastn_core::package::BackendLimits,
+    elapsed: std::time::Duration,
+) -> Option<Limit> {
+    if let Some(fuel) = limits.fuel {
+        if store.fuel_consumed().unwrap_or_default() >= fuel {
+            return Some(Limit::Fuel);
+        }
+    }
+
+    if elapsed >= limits.timeout.unwrap_or(DEFAULT_TIMEOUT) {
+        return Some(Limit::Timeout);
+    }
+
+    // memory can not grow beyond the limit, if the guest is (almost) at the limit it most
+    // likely failed trying to grow it. If instantiation itself failed, the initial memory of the
+    // module may be more than what is allowed.
+    let max_memory = limits.max_memory.unwrap_or(DEFAULT_MAX_MEMORY) as u64;
+    let memory = match instance {
+        Some(instance) => instance
+            .get_memory(&mut *store, "memory")
+            .map(|m| m.data_size(&*store) as u64 + WASM_PAGE_SIZE),
+        None => match module.get_export("memory") {
+            Some(wit_bindgen_host_wasmtime_rust::wasmtime::ExternType::Memory(m)) => {
+                Some(m.minimum() * WASM_PAGE_SIZE)
+            }
+            _ => None,
+        },
+    };
+    if memory.map(|m| m > max_memory).unwrap_or(false) {
+        return Some(Limit::Memory);
+    }
+
+    None
+}
+
+/// A backend that ran out of time is a gateway timeout, one that ran out of fuel or memory can
+/// not handle the request at all.
+fn limit_exceeded(package_name: &str, limit: Limit) -> fastn_core::http::Response {
+    let message = format!("fastn-Error: {} backend exceeded {}", package_name, limit);
+    match limit {
+        Limit::Timeout => actix_web::HttpResponse::GatewayTimeout().body(message),
+        Limit::Fuel | Limit::Memory => actix_web::HttpResponse::ServiceUnavailable().body(message),
+    }
+}
+
 pub async fn handle_wasm(
     req: fastn_core::http::Request,
+    package: &fastn_core::Package,
+    limits: fastn_core::package::BackendLimits,
     wasm_module: camino::Utf8PathBuf,
-    backend_headers: Option<Vec<fastn_core::package::BackendHeader>>,
 ) -> fastn_core::http::Response {
-    pub async fn inner(
+    fn inner(
         req: fastn_core::http::Request,
+        package: fastn_core::Package,
+        limits: fastn_core::package::BackendLimits,
         wasm_module: camino::Utf8PathBuf,
-        backend_headers: Option<Vec<fastn_core::package::BackendHeader>>,
-    ) -> WasmRunnerResult<actix_web::HttpResponse> {
-        let mut wasm_config = wit_bindgen_host_wasmtime_rust::wasmtime::Config::new();
-        wasm_config.cache_config_load_default().unwrap();
-        wasm_config.wasm_backtrace_details(
-            wit_bindgen_host_wasmtime_rust::wasmtime::WasmBacktraceDetails::Disable,
-        );
-
-        let engine = wit_bindgen_host_wasmtime_rust::wasmtime::Engine::new(&wasm_config)?;
-        let module = wit_bindgen_host_wasmtime_rust::wasmtime::Module::from_file(
-            &engine,
-            wasm_module.as_str(),
-        )?;
+    ) -> WasmRunnerResult<fastn_utils::backend_host_import::guest_backend::Httpresponse> {
+        let Backend {
+            engine,
+            module,
+            client,
+        } = get_backend(&package, &limits, &wasm_module)?;
+        let timeout = limits.timeout.unwrap_or(DEFAULT_TIMEOUT);
 
         let mut linker: wit_bindgen_host_wasmtime_rust::wasmtime::Linker<
             fastn_core::wasm::Context<
@@ -94,21 +394,25 @@ pub async fn handle_wasm(
         let mut store = wit_bindgen_host_wasmtime_rust::wasmtime::Store::new(
             &engine,
             fastn_core::wasm::Context {
-                imports: fastn_core::wasm::HostExports {},
+                imports: fastn_core::wasm::HostExports {
+                    http: package.backend_http.clone(),
+                    client,
+                    deadline: std::time::Instant::now() + timeout,
+                },
                 exports: fastn_utils::backend_host_import::guest_backend::GuestBackendData {},
+                limits: wit_bindgen_host_wasmtime_rust::wasmtime::StoreLimitsBuilder::new()
+                    .memory_size(limits.max_memory.unwrap_or(DEFAULT_MAX_MEMORY))
+                    .build(),
             },
         );
+        store.limiter(|cx| &mut cx.limits);
+        if let Some(fuel) = limits.fuel {
+            store.add_fuel(fuel)?;
+        }
+        store.set_epoch_deadline(((timeout.as_millis() / EPOCH_TICK.as_millis()) as u64).max(1));
 
         fastn_utils::backend_host_export::host::add_to_linker(&mut linker, |cx| &mut cx.imports)?;
 
-        let (import, _i) =
-            fastn_utils::backend_host_import::guest_backend::GuestBackend::instantiate(
-                &mut store,
-                &module,
-                &mut linker,
-                |cx| &mut cx.exports,
-            )?;
-
         let uri = req.uri().to_string();
         // TODO: Fix body
         let b = req.body().to_vec();
@@ -122,18 +426,15 @@ pub async fn handle_wasm(
         req.headers()
             .iter()
             .for_each(|(header_name, header_value)| {
-                headers.push((
-                    header_name.as_str().to_string(),
-                    header_value
-                        .to_str()
-                        .expect("Unable to parse header value")
-                        .to_string(),
-                ));
+                // headers which are not valid utf-8 can not be passed to the guest
+                if let Ok(header_value) = header_value.to_str() {
+                    headers.push((header_name.as_str().to_string(), header_value.to_string()));
+                }
             });
-        if let Some(b_headers) = backend_headers {
+        if let Some(b_headers) = package.backend_headers {
             b_headers.into_iter().for_each(|header| {
                 let hk = header.header_key;
-                headers.push((format!("X-fastn-{hk}"), header.header_value));
+                headers.push((format!("X-FASTN-{hk}"), header.header_value));
             })
         };
         let headers: Vec<(&str, &str)> = headers
@@ -147,20 +448,263 @@ pub async fn handle_wasm(
             method: req.method(),
             payload: body_str,
         };
-        fastn_core::time("WASM Guest function").it(match import.handlerequest(&mut store, request) {
-            Ok(data) => Ok(actix_web::HttpResponse::Ok()
-                .content_type(actix_web::http::header::ContentType::json())
-                .status(if data.success {
-                    actix_web::http::StatusCode::OK
+
+        let start = std::time::Instant::now();
+        let (result, instance) =
+            match fastn_utils::backend_host_import::guest_backend::GuestBackend::instantiate(
+                &mut store,
+                &module,
+                &mut linker,
+                |cx| &mut cx.exports,
+            ) {
+                Ok((import, instance)) => (
+                    fastn_core::time("WASM Guest function").it(import
+                        .handlerequest(&mut store, request)
+                        .map_err(|err| WASMError::WasmFunctionInvoke(err.to_string()))),
+                    Some(instance),
+                ),
+                Err(err) => (Err(WASMError::from(err)), None),
+            };
+
+        if result.is_err() {
+            if let Some(limit) =
+                exceeded_limit(&mut store, &module, instance, &limits, start.elapsed())
+            {
+                return Err(WASMError::LimitExceeded(limit));
+            }
+        }
+        result
+    }
+
+    // the guest gets the whole body at once, a body too long for that is not streamed to it
+    if let Err(e) = req.whole_body() {
+        return actix_web::ResponseError::error_response(&e);
+    }
+
+    // The guest runs synchronously and its `http` calls block, so it must not run on the async
+    // executor
+    let package_name = package.name.clone();
+    let package = package.clone();
+    let result =
+        tokio::task::spawn_blocking(move || inner(req, package, limits, wasm_module)).await;
+    fastn_core::time("WASM Execution: ").it(match result.map_err(WASMError::from) {
+        Ok(Err(WASMError::LimitExceeded(limit))) => {
+            tracing::warn!(
+                msg = "wasm backend exceeded its limit",
+                package = package_name.as_str(),
+                limit = %limit
+            );
+            limit_exceeded(package_name.as_str(), limit)
+        }
+        Ok(Ok(data)) => actix_web::HttpResponse::Ok()
+            .content_type(actix_web::http::header::ContentType::json())
+            .status(if data.success {
+                actix_web::http::StatusCode::OK
+            } else {
+                actix_web::http::StatusCode::BAD_REQUEST
+            })
+            .body(data.data),
+        Ok(Err(err)) | Err(err) => fastn_core::server_error!("{}", err.to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    fn backend_wasm() -> camino::Utf8PathBuf {
+        camino::Utf8PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/27-wasm-backend/input/amitu/backend.wasm")
+    }
+
+    /// A server on 127.0.0.1 that redirects `/redirect/<to>` to `<to>`, sends `size` bytes for
+    /// `/size/<size>` and `ok` for everything else.
+    fn serve() -> u16 {
+        use std::io::{BufRead, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let mut reader = std::io::BufReader::new(stream);
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap_or_default();
+                let path = line.split(' ').nth(1).unwrap_or_default().to_string();
+                while reader.read_line(&mut line).map(|n| n > 2).unwrap_or(false) {
+                    line.clear();
+                }
+                let mut stream = reader.into_inner();
+                let _ = if let Some(to) = path.strip_prefix("/redirect/") {
+                    write!(
+                        stream,
+                        "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                        to
+                    )
+                } else if let Some(size) = path.strip_prefix("/size/") {
+                    let size: usize = size.parse().unwrap();
+                    write!(
+                        stream,
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        size
+                    )
+                    .and_then(|_| stream.write_all(&vec![b'a'; size]))
                 } else {
-                    actix_web::http::StatusCode::BAD_REQUEST
-                })
-                .body(data.data)),
-            Err(err) => Err(WASMError::WasmFunctionInvoke(err.to_string())),
+                    stream.write_all(
+                        b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+                    )
+                };
+            }
+        });
+        port
+    }
+
+    fn get(host: &super::HostExports, url: &str) -> Result<super::HttpResult, String> {
+        host.send(&fastn_utils::backend_host_export::host::Httprequest {
+            path: url,
+            method: "GET",
+            payload: "",
+            headers: &[],
         })
     }
-    fastn_core::time("WASM Execution: ").it(match inner(req, wasm_module, backend_headers).await {
-        Ok(resp) => resp,
-        Err(err) => fastn_core::server_error!("{}", err.to_string()),
-    })
+
+    #[test]
+    fn http_is_limited_to_allowed_hosts() {
+        let port = serve();
+        let http = fastn_core::package::BackendHttp {
+            allowed_hosts: vec!["127.0.0.1".to_string()],
+            ..Default::default()
+        };
+        let host = super::HostExports {
+            client: super::http_client(&http).unwrap(),
+            http,
+            deadline: std::time::Instant::now() + std::time::Duration::from_secs(30),
+        };
+        let ok = format!("http://127.0.0.1:{}/ok", port);
+
+        assert!(matches!(
+            get(&host, ok.as_str()),
+            Ok(super::HttpResult::Response { status: 200, ref body, .. }) if body == "ok"
+        ));
+        assert!(get(&host, format!("http://localhost:{}/ok", port).as_str())
+            .unwrap_err()
+            .starts_with("host not allowed: localhost"));
+
+        // redirects are followed to the allowed hosts only
+        assert!(matches!(
+            get(
+                &host,
+                format!("http://127.0.0.1:{}/redirect/{}", port, ok).as_str()
+            ),
+            Ok(super::HttpResult::Response { status: 200, .. })
+        ));
+        let error = get(
+            &host,
+            format!(
+                "http://127.0.0.1:{}/redirect/http://localhost:{}/ok",
+                port, port
+            )
+            .as_str(),
+        )
+        .unwrap_err();
+        assert!(error.contains("redirect"), "{}", error);
+
+        // at most `MAX_HTTP_RESPONSE_SIZE` bytes of a response are read
+        let size = super::MAX_HTTP_RESPONSE_SIZE as usize;
+        assert!(matches!(
+            get(&host, format!("http://127.0.0.1:{}/size/{}", port, size).as_str()),
+            Ok(super::HttpResult::Response { ref body, .. }) if body.len() == size
+        ));
+        assert!(get(
+            &host,
+            format!("http://127.0.0.1:{}/size/{}", port, size + 1).as_str()
+        )
+        .unwrap_err()
+        .contains("is larger than"));
+    }
+
+    /// The backend needs the headers of tests/27-wasm-backend/input/amitu/FASTN.ftd
+    fn echo_package(name: &str) -> fastn_core::Package {
+        let mut package = fastn_core::Package::new(name);
+        package.backend = true;
+        package.backend_headers = Some(vec![
+            fastn_core::package::BackendHeader {
+                header_key: "BLOG-APP-SUPABASE-BASE-URL".to_string(),
+                header_value: "a".to_string(),
+            },
+            fastn_core::package::BackendHeader {
+                header_key: "BLOG-APP-SUPABASE-API-KEY".to_string(),
+                header_value: "b".to_string(),
+            },
+        ]);
+        package
+    }
+
+    // tests/27-wasm-backend/wasm_backend echoes the request it gets back as json
+    #[actix_web::test]
+    async fn backend_headers_reach_the_guest() {
+        let package = echo_package("wasm-echo.fpm.local");
+        let req = fastn_core::http::Request::from_actix(
+            actix_web::test::TestRequest::post()
+                .uri("/-/blog-backend.fpm.local/echo/")
+                .to_http_request(),
+            actix_web::web::Bytes::from_static(b"{\"hello\": \"world\"}"),
+        );
+
+        let response = super::handle_wasm(req, &package, Default::default(), backend_wasm()).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let echo: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(echo["path"], "/-/blog-backend.fpm.local/echo/");
+        assert_eq!(echo["method"], "POST");
+        assert_eq!(echo["payload"], "{\"hello\": \"world\"}");
+        let headers = echo["headers"].as_array().unwrap();
+        assert!(headers.contains(&serde_json::json!([
+            "X-FASTN-BLOG-APP-SUPABASE-BASE-URL",
+            "a"
+        ])));
+        assert!(headers.contains(&serde_json::json!([
+            "X-FASTN-BLOG-APP-SUPABASE-API-KEY",
+            "b"
+        ])));
+    }
+
+    fn echo_request() -> fastn_core::http::Request {
+        fastn_core::http::Request::from_actix(
+            actix_web::test::TestRequest::get()
+                .uri("/-/blog-backend.fpm.local/echo/")
+                .to_http_request(),
+            Default::default(),
+        )
+    }
+
+    #[actix_web::test]
+    async fn exceeded_limits() {
+        let package = echo_package("wasm-limits.fpm.local");
+
+        let fuel = fastn_core::package::BackendLimits {
+            fuel: Some(1),
+            ..Default::default()
+        };
+        let response = super::handle_wasm(echo_request(), &package, fuel, backend_wasm()).await;
+        assert_eq!(
+            response.status(),
+            actix_web::http::StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        // less than the initial memory of the module
+        let memory = fastn_core::package::BackendLimits {
+            max_memory: Some(super::WASM_PAGE_SIZE as usize),
+            ..Default::default()
+        };
+        let response = super::handle_wasm(echo_request(), &package, memory, backend_wasm()).await;
+        assert_eq!(
+            response.status(),
+            actix_web::http::StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        assert_eq!(
+            super::limit_exceeded("foo", super::Limit::Timeout).status(),
+            actix_web::http::StatusCode::GATEWAY_TIMEOUT
+        );
+    }
 }
@@ -60,7 +60,18 @@ fn watcher() -> (
 fn create_watcher(f_tx: tokio::sync::mpsc::Sender<()>) -> notify::RecommendedWatcher {
     use notify::Watcher;
 
-    let mut watcher = notify::recommended_watcher(move |_res| {
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        if let Ok(ref event) = res {
+            if event.paths.iter().any(|p| invalidates_config(p)) {
+                fastn_core::config::cache::invalidate();
+            }
+            if event.paths.iter().any(|p| {
+                p.extension().map(|e| e == "wasm").unwrap_or(false)
+                    || p.file_name().map(|f| f == "FASTN.ftd").unwrap_or(false)
+            }) {
+                fastn_core::wasm::invalidate();
+            }
+        }
         if let Err(e) = f_tx.blocking_send(()) {
             eprintln!("watcher: failed to send signal: {}", e);
         }
@@ -77,6 +88,33 @@ fn create_watcher(f_tx: tokio::sync::mpsc::Sender<()>) -> notify::RecommendedWat
     watcher
 }
 
+/// Changes inside the folders fastn itself writes to (build output, history, downloaded
+/// packages etc) do not change FASTN.ftd or any document of the package, so they do not need
+/// the cached config to be read again.
+fn invalidates_config(path: &std::path::Path) -> bool {
+    const IGNORED: &[&str] = &[
+        ".build",
+        ".packages",
+        ".history",
+        ".tracks",
+        ".fastn",
+        ".remote-state",
+        ".clone-state",
+    ];
+
+    !path.components().any(|c| {
+        c.as_os_str()
+            .to_str()
+            .map(|c| IGNORED.contains(&c))
+            .unwrap_or(false)
+    })
+}
+
+/// `start()` starts the file watcher if it is not running yet.
+pub(crate) fn start() {
+    once_cell::sync::Lazy::force(&WATCHER);
+}
+
 fn next_id() -> usize {
     GLOBAL_POLL_COUNT.fetch_add(1, std::sync::atomic::Ordering::SeqCst)
 }
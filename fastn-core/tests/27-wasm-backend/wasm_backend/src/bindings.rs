@@ -0,0 +1,169 @@
+// The bindings of `wits/host.wit`, which the backend imports, and of `wits/backend.wit`, which it
+// exports, as `wit-bindgen` generates them for the canonical ABI fastn's host uses. They are
+// written out here so the backend builds with crates.io dependencies only. The name of every
+// import and export is its signature, which is how the host finds them.
+
+pub mod host {
+    pub struct Httprequest<'a> {
+        pub path: &'a str,
+        pub method: &'a str,
+        pub payload: &'a str,
+        pub headers: &'a [(&'a str, &'a str)],
+    }
+
+    pub struct Httpresponse {
+        pub data: String,
+    }
+
+    #[link(wasm_import_module = "host")]
+    extern "C" {
+        #[link_name = "http: func(request: record { path: string, method: string, payload: string, headers: list<tuple<string, string>> }) -> record { data: string }"]
+        fn import_http(
+            path: *const u8,
+            path_len: usize,
+            method: *const u8,
+            method_len: usize,
+            payload: *const u8,
+            payload_len: usize,
+            headers: *const [usize; 4],
+            headers_len: usize,
+            ret: *mut [usize; 2],
+        );
+    }
+
+    pub fn http(request: Httprequest<'_>) -> Httpresponse {
+        // a header is the pointer and length of its key, then of its value
+        let headers = request
+            .headers
+            .iter()
+            .map(|(k, v)| [k.as_ptr() as usize, k.len(), v.as_ptr() as usize, v.len()])
+            .collect::<Vec<_>>();
+        let mut ret = [0usize; 2];
+        unsafe {
+            import_http(
+                request.path.as_ptr(),
+                request.path.len(),
+                request.method.as_ptr(),
+                request.method.len(),
+                request.payload.as_ptr(),
+                request.payload.len(),
+                headers.as_ptr(),
+                headers.len(),
+                &mut ret,
+            );
+            // the host allocated the data with `cabi_realloc`, it is ours now
+            Httpresponse {
+                data: super::string(ret[0] as *mut u8, ret[1]),
+            }
+        }
+    }
+}
+
+pub mod guest_backend {
+    #[derive(serde::Serialize, Debug)]
+    pub struct Httprequest {
+        pub path: String,
+        pub headers: Vec<(String, String)>,
+        pub querystring: String,
+        pub payload: String,
+        pub method: String,
+    }
+
+    pub struct Httpresponse {
+        pub data: String,
+        pub success: bool,
+    }
+
+    /// The pointer and length of `data`, then `success`, which the host reads the response from.
+    static mut RET_AREA: [usize; 3] = [0; 3];
+
+    #[export_name = "handlerequest: func(a: record { path: string, headers: list<tuple<string, string>>, querystring: string, payload: string, method: string }) -> record { data: string, success: bool }"]
+    unsafe extern "C" fn export_handlerequest(
+        path: *mut u8,
+        path_len: usize,
+        headers: *mut [usize; 4],
+        headers_len: usize,
+        querystring: *mut u8,
+        querystring_len: usize,
+        payload: *mut u8,
+        payload_len: usize,
+        method: *mut u8,
+        method_len: usize,
+    ) -> *const [usize; 3] {
+        // the host allocated the arguments with `cabi_realloc`, they are ours now
+        let headers = if headers_len == 0 {
+            vec![]
+        } else {
+            Vec::from_raw_parts(headers, headers_len, headers_len)
+        };
+        let request = Httprequest {
+            path: super::string(path, path_len),
+            headers: headers
+                .into_iter()
+                .map(|[k, k_len, v, v_len]| {
+                    (
+                        super::string(k as *mut u8, k_len),
+                        super::string(v as *mut u8, v_len),
+                    )
+                })
+                .collect(),
+            querystring: super::string(querystring, querystring_len),
+            payload: super::string(payload, payload_len),
+            method: super::string(method, method_len),
+        };
+
+        let response = crate::handlerequest(request);
+        // the host frees the data with `canonical_abi_free`
+        let data = std::mem::ManuallyDrop::new(response.data.into_bytes().into_boxed_slice());
+        let ret = std::ptr::addr_of_mut!(RET_AREA);
+        *ret = [
+            data.as_ptr() as usize,
+            data.len(),
+            response.success as usize,
+        ];
+        ret
+    }
+}
+
+/// `string()` takes over a string the host allocated with `cabi_realloc()`.
+unsafe fn string(ptr: *mut u8, len: usize) -> String {
+    if len == 0 {
+        return String::new();
+    }
+    String::from_utf8_unchecked(Vec::from_raw_parts(ptr, len, len))
+}
+
+#[no_mangle]
+unsafe extern "C" fn cabi_realloc(
+    old_ptr: *mut u8,
+    old_len: usize,
+    align: usize,
+    new_len: usize,
+) -> *mut u8 {
+    let layout;
+    let ptr = if old_len == 0 {
+        if new_len == 0 {
+            return align as *mut u8;
+        }
+        layout = std::alloc::Layout::from_size_align_unchecked(new_len, align);
+        std::alloc::alloc(layout)
+    } else {
+        layout = std::alloc::Layout::from_size_align_unchecked(old_len, align);
+        std::alloc::realloc(old_ptr, layout, new_len)
+    };
+    if ptr.is_null() {
+        std::alloc::handle_alloc_error(layout);
+    }
+    ptr
+}
+
+#[no_mangle]
+unsafe extern "C" fn canonical_abi_free(ptr: *mut u8, len: usize, align: usize) {
+    if len == 0 {
+        return;
+    }
+    std::alloc::dealloc(
+        ptr,
+        std::alloc::Layout::from_size_align_unchecked(len, align),
+    );
+}
@@ -1,12 +1,11 @@
-use serde_json;
+mod bindings;
 mod types;
 
-wit_bindgen_guest_rust::import!("/Users/shobhitsharma/repos/fifthtry/fpm-utils/wits/host.wit");
+use bindings::{guest_backend, host};
 
-#[fpm_utils_macro::wasm_backend]
 fn handlerequest(a: guest_backend::Httprequest) -> guest_backend::Httpresponse {
-    let base_url_header_key = String::from("X-FPM-BLOG-APP-SUPABASE-BASE-URL");
-    let apikey_header_key = String::from("X-FPM-BLOG-APP-SUPABASE-API-KEY");
+    let base_url_header_key = String::from("X-FASTN-BLOG-APP-SUPABASE-BASE-URL");
+    let apikey_header_key = String::from("X-FASTN-BLOG-APP-SUPABASE-API-KEY");
     let (_, base_url) = a
         .headers
         .iter()
@@ -58,8 +57,10 @@ fn handlerequest(a: guest_backend::Httprequest) -> guest_backend::Httpresponse {
             }
         }
     };
+    // host::http returns {"status", "headers", "body"} or {"error"} as json
+    let resp: serde_json::Value = serde_json::from_str(resp.data.as_str()).unwrap();
     guest_backend::Httpresponse {
-        data: resp.data,
-        success: true,
+        data: resp["body"].as_str().unwrap_or_default().to_string(),
+        success: resp["error"].is_null(),
     }
 }
@@ -72,6 +72,21 @@ async fn fastn_core_commands(matches: &clap::ArgMatches) -> fastn_core::Result<(
     use fastn_core::utils::ValueOf;
 
     match matches.subcommand() {
+        Some((fastn_core::commands::checkout::COMMAND, matches)) => {
+            return fastn_core::commands::checkout::handle_command(matches).await;
+        }
+        Some((fastn_core::commands::log::COMMAND, matches)) => {
+            return fastn_core::commands::log::handle_command(matches).await;
+        }
+        Some((fastn_core::commands::review::COMMAND, matches)) => {
+            return fastn_core::commands::review::handle_command(matches).await;
+        }
+        Some((fastn_core::commands::routes::COMMAND, matches)) => {
+            return fastn_core::commands::routes::handle_command(matches).await;
+        }
+        Some((fastn_core::commands::show::COMMAND, matches)) => {
+            return fastn_core::commands::show::handle_command(matches).await;
+        }
         Some((fastn_core::commands::stop_tracking::COMMAND, matches)) => {
             return fastn_core::commands::stop_tracking::handle_command(matches).await;
         }
@@ -186,6 +201,7 @@ async fn fastn_core_commands(matches: &clap::ArgMatches) -> fastn_core::Result<(
             build.value_of_("file"), // TODO: handle more than one files
             build.value_of_("base").unwrap_or("/"),
             build.get_flag("ignore-failed"),
+            build.get_flag("force"),
         )
         .await;
     }
@@ -203,15 +219,21 @@ async fn fastn_core_commands(matches: &clap::ArgMatches) -> fastn_core::Result<(
     }
 
     if let Some(sync) = matches.subcommand_matches("sync") {
+        let message = sync.value_of_("message");
         return if let Some(source) = sync.get_many::<String>("file") {
             let sources = source.map(|v| v.to_string()).collect();
-            fastn_core::sync2(&config, Some(sources)).await
+            fastn_core::sync2(&config, Some(sources), message).await
         } else {
-            fastn_core::sync2(&config, None).await
+            fastn_core::sync2(&config, None, message).await
         };
     }
     if let Some(create_cr) = matches.subcommand_matches("create-cr") {
-        return fastn_core::create_cr(&config, create_cr.value_of_("title")).await;
+        return fastn_core::create_cr(
+            &config,
+            create_cr.value_of_("title"),
+            create_cr.value_of_("reviewers"),
+        )
+        .await;
     }
     if let Some(close_cr) = matches.subcommand_matches("close-cr") {
         return fastn_core::close_cr(&config, close_cr.value_of_("cr").unwrap()).await;
@@ -282,6 +304,7 @@ fn app(version: &'static str) -> clap::Command {
                 .arg(clap::arg!(file: [FILE]... "The file to build (if specified only these are built, else entire package is built)"))
                 .arg(clap::arg!(-b --base [BASE] "The base path.").default_value("/"))
                 .arg(clap::arg!(--"ignore-failed" "Ignore failed files."))
+                .arg(clap::arg!(--force "Build all the files, even the ones that did not change since the last build."))
                 .arg(clap::arg!(--"external-js" <URL> "Script added in ftd files")
                     .action(clap::ArgAction::Append))
                 .arg(clap::arg!(--"js" <URL> "Script text added in ftd files")
@@ -353,6 +376,7 @@ fn app(version: &'static str) -> clap::Command {
             clap::Command::new("sync")
                 .about("Sync with fastn-repo (or .history folder if not using fastn-repo)")
                 .arg(clap::arg!(file: <FILE>... "The file(s) to sync (leave empty to sync entire package)"))
+                .arg(clap::arg!(-m --message <MESSAGE> "The message stored in the history of the synced files").required(false))
                 .hide(true) // hidden since the feature is not being released yet.
         )
         .subcommand(
@@ -362,10 +386,15 @@ fn app(version: &'static str) -> clap::Command {
                 .hide(true) // hidden since the feature is not being released yet.
         )
         .subcommand(fastn_core::commands::sync_status::command())
+        .subcommand(fastn_core::commands::log::command())
+        .subcommand(fastn_core::commands::review::command())
+        .subcommand(fastn_core::commands::show::command())
+        .subcommand(fastn_core::commands::checkout::command())
         .subcommand(
             clap::Command::new("create-cr")
                 .about("Create a Change Request")
                 .arg(clap::arg!(title: <TITLE> "The title of the new CR"))
+                .arg(clap::arg!(--reviewers <GROUP> "The user-group asked to review the CR").required(false))
                 .hide(true) // hidden since the feature is not being released yet.
         )
         .subcommand(
@@ -417,6 +446,7 @@ fn app(version: &'static str) -> clap::Command {
                 .hide(true) // hidden since the feature is not being released yet.
         )
         .subcommand(fastn_core::commands::stop_tracking::command())
+        .subcommand(fastn_core::commands::routes::command())
         .subcommand(sub_command::serve())
         .subcommand(sub_command::publish_static())
 }